@@ -5,8 +5,31 @@ pub mod traits {
 }
 
 pub mod macros {
-    pub use celkit_core::impl_for_struct;
+    pub use celkit_core::{impl_for_struct, value};
 }
 
+pub use celkit_core::{
+    field_or_default, from_value, from_value_with_options, struct_extras, struct_field, to_value,
+    DeserializeOptions, MissingFields, UnknownFields, FALSE_WORDS, TRUE_WORDS,
+};
+
+#[cfg(feature = "string")]
+pub use celkit_string::{
+    estimated_size, from_string, from_string_prefix, from_string_prefix_with_options,
+    from_string_with_options, get_path, get_path_with_options, mini_encoder, parse,
+    parse_all_errors, parse_all_errors_with_options, parse_spanned, parse_spanned_with_options,
+    parse_with_options, pretty_encoder, to_canonical, to_mini_into, to_mini_string, to_string,
+    to_string_with, Annotations, DecodeOptions, DuplicateKeys, EncodeOptions, EncodeStyle,
+    ExpandMode, KeyStyle, LineEnding, MiniEncoder, NonFiniteMode, Position, PrettyEncoder,
+    RawValue, Span, Spanned, SpannedEntry, SpannedValue, StreamParser, DEFAULT_MAX_DEPTH,
+};
+
 #[cfg(feature = "string")]
-pub use celkit_string::{from_string, to_mini, to_pretty, to_string};
+#[allow(deprecated)]
+pub use celkit_string::{to_mini, to_pretty};
+
+#[cfg(all(feature = "string", feature = "std"))]
+pub use celkit_string::{
+    from_file, from_lines, from_reader, from_reader_with_options, to_lines, to_writer,
+    to_writer_pretty, ArrayEncoder, ObjectEncoder, StreamEncoder, StructEncoder,
+};
@@ -0,0 +1,286 @@
+use crate::core::Deserialize;
+use crate::internal::sys::*;
+use crate::internal::{Error, Number, Result, Value};
+
+// ------------------------------- Helpers -------------------------------- //
+
+macro_rules! impl_from_number {
+    ($type:ty, $variant:ident) => {
+        impl From<$type> for Number {
+            fn from(number: $type) -> Self {
+                Number::$variant(number)
+            }
+        }
+
+        impl From<$type> for Value {
+            fn from(number: $type) -> Self {
+                Value::Number(Number::from(number))
+            }
+        }
+    };
+    ($type:ty as $target:ty, $variant:ident) => {
+        impl From<$type> for Number {
+            fn from(number: $type) -> Self {
+                Number::$variant(number as $target)
+            }
+        }
+
+        impl From<$type> for Value {
+            fn from(number: $type) -> Self {
+                Value::Number(Number::from(number))
+            }
+        }
+    };
+}
+
+macro_rules! impl_try_from_value {
+    ($type:ty) => {
+        impl TryFrom<Value> for $type {
+            type Error = Error;
+
+            fn try_from(value: Value) -> Result<Self> {
+                <$type>::deserialize(value)
+            }
+        }
+    };
+}
+
+// ------------------------ Into `Number`/`Value` ------------------------- //
+
+impl_from_number!(u8, U8);
+impl_from_number!(i8, I8);
+impl_from_number!(u16, U16);
+impl_from_number!(i16, I16);
+impl_from_number!(u32, U32);
+impl_from_number!(i32, I32);
+impl_from_number!(u64, U64);
+impl_from_number!(i64, I64);
+impl_from_number!(u128, U128);
+impl_from_number!(i128, I128);
+impl_from_number!(f32, F32);
+impl_from_number!(f64, F64);
+
+// Pointer-sized integers use the variant matching the target's pointer width
+#[cfg(target_pointer_width = "16")]
+impl_from_number!(usize as u16, U16);
+#[cfg(target_pointer_width = "16")]
+impl_from_number!(isize as i16, I16);
+#[cfg(target_pointer_width = "32")]
+impl_from_number!(usize as u32, U32);
+#[cfg(target_pointer_width = "32")]
+impl_from_number!(isize as i32, I32);
+#[cfg(target_pointer_width = "64")]
+impl_from_number!(usize as u64, U64);
+#[cfg(target_pointer_width = "64")]
+impl_from_number!(isize as i64, I64);
+
+impl From<Number> for Value {
+    fn from(number: Number) -> Self {
+        Value::Number(number)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(boolean: bool) -> Self {
+        Value::Boolean(boolean)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Self {
+        Value::Text(text.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Self {
+        Value::Text(text)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(option: Option<T>) -> Self {
+        match option {
+            Some(value) => value.into(),
+            None => Value::Null,
+        }
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Value>> From<BTreeMap<String, T>> for Value {
+    fn from(entries: BTreeMap<String, T>) -> Self {
+        Value::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key, value.into()))
+                .collect(),
+        )
+    }
+}
+
+// ---------------------------- From `Value` ------------------------------ //
+
+impl_try_from_value!(u8);
+impl_try_from_value!(i8);
+impl_try_from_value!(u16);
+impl_try_from_value!(i16);
+impl_try_from_value!(u32);
+impl_try_from_value!(i32);
+impl_try_from_value!(u64);
+impl_try_from_value!(i64);
+impl_try_from_value!(u128);
+impl_try_from_value!(i128);
+impl_try_from_value!(usize);
+impl_try_from_value!(isize);
+impl_try_from_value!(f32);
+impl_try_from_value!(f64);
+impl_try_from_value!(bool);
+impl_try_from_value!(String);
+
+impl TryFrom<Value> for Vec<Value> {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self> {
+        match value {
+            Value::Array(items) => Ok(items),
+            value => Err(Error::new(format!(
+                "Expected array, found {}",
+                value.kind()
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn numbers_keep_their_variant() {
+        assert!(matches!(Value::from(1u8), Value::Number(Number::U8(1))));
+        assert!(matches!(Value::from(-1i8), Value::Number(Number::I8(-1))));
+        assert!(matches!(Value::from(1u16), Value::Number(Number::U16(1))));
+        assert!(matches!(Value::from(-1i16), Value::Number(Number::I16(-1))));
+        assert!(matches!(Value::from(1u32), Value::Number(Number::U32(1))));
+        assert!(matches!(Value::from(-1i32), Value::Number(Number::I32(-1))));
+        assert!(matches!(Value::from(1u64), Value::Number(Number::U64(1))));
+        assert!(matches!(Value::from(-1i64), Value::Number(Number::I64(-1))));
+        assert!(matches!(Value::from(1u128), Value::Number(Number::U128(1))));
+        assert!(matches!(
+            Value::from(-1i128),
+            Value::Number(Number::I128(-1))
+        ));
+        assert!(matches!(Value::from(1.5f32), Value::Number(Number::F32(n)) if n == 1.5));
+        assert!(matches!(Value::from(1.5f64), Value::Number(Number::F64(n)) if n == 1.5));
+        assert_eq!(
+            Value::from(usize::MAX),
+            Value::from(Number::from(usize::MAX))
+        );
+        assert_eq!(
+            Value::from(isize::MIN),
+            Value::from(Number::from(isize::MIN))
+        );
+
+        #[cfg(target_pointer_width = "64")]
+        assert!(matches!(Value::from(1usize), Value::Number(Number::U64(1))));
+    }
+
+    #[test]
+    fn other_types_map_to_their_kind() {
+        assert_eq!(Value::from(true), Value::Boolean(true));
+        assert_eq!(Value::from("text"), Value::Text("text".to_string()));
+        assert_eq!(
+            Value::from("text".to_string()),
+            Value::Text("text".to_string())
+        );
+        assert_eq!(Value::from(Some(1u8)), Value::Number(Number::U8(1)));
+        assert_eq!(Value::from(None::<u8>), Value::Null);
+        assert_eq!(Value::from(Some(None::<u8>)), Value::Null);
+
+        let array: Value = vec![1, 2, 3].into();
+
+        assert_eq!(
+            array,
+            Value::Array(vec![Value::from(1), Value::from(2), Value::from(3)])
+        );
+        assert_eq!(Value::from(Vec::<bool>::new()), Value::Array(Vec::new()));
+
+        let map = BTreeMap::from([
+            ("a".to_string(), vec![Some("x")]),
+            ("b".to_string(), vec![None]),
+        ]);
+
+        assert_eq!(
+            Value::from(map),
+            Value::Object(BTreeMap::from([
+                ("a".to_string(), Value::Array(vec![Value::from("x")])),
+                ("b".to_string(), Value::Array(vec![Value::Null])),
+            ]))
+        );
+    }
+
+    fn try_into<T: TryFrom<Value, Error = Error>>(value: impl Into<Value>) -> Result<T> {
+        T::try_from(value.into())
+    }
+
+    #[test]
+    fn numbers_are_range_checked() {
+        assert_eq!(try_into::<u32>(Number::I64(7)).unwrap(), 7);
+        assert_eq!(try_into::<i32>(2.0).unwrap(), 2);
+        assert_eq!(try_into::<u8>(u8::MAX as u128).unwrap(), u8::MAX);
+        assert_eq!(try_into::<f64>(3u8).unwrap(), 3.0);
+
+        assert_eq!(
+            try_into::<u32>(Number::I64(-1)).unwrap_err().message,
+            "Value -1 out of range for u32"
+        );
+        assert_eq!(
+            try_into::<i32>(2.5).unwrap_err().message,
+            "Value 2.5 is not an integer, expected i32"
+        );
+        assert_eq!(
+            try_into::<i32>(1e10).unwrap_err().message,
+            "Value 10000000000.0 out of range for i32"
+        );
+        assert!(try_into::<u16>(u16::MAX as u32 + 1).is_err());
+        assert!(try_into::<i64>(u64::MAX).is_err());
+        assert!(try_into::<usize>(-1).is_err());
+        assert!(try_into::<f32>(1e39).is_err());
+    }
+
+    #[test]
+    fn kinds_must_match() {
+        assert!(try_into::<bool>(true).unwrap());
+        assert_eq!(try_into::<String>("text").unwrap(), "text");
+        assert_eq!(
+            try_into::<Vec<Value>>(vec![1, 2]).unwrap(),
+            [Value::from(1), Value::from(2)]
+        );
+
+        assert_eq!(
+            try_into::<bool>("true").unwrap_err().message,
+            "Expected boolean, found text"
+        );
+        assert_eq!(
+            try_into::<String>(1).unwrap_err().message,
+            "Expected text, found number"
+        );
+        assert_eq!(
+            try_into::<u8>(Value::Null).unwrap_err().message,
+            "Expected number for u8, found null"
+        );
+        assert_eq!(
+            try_into::<Vec<Value>>(Value::Tuple(Vec::new()))
+                .unwrap_err()
+                .message,
+            "Expected array, found tuple"
+        );
+    }
+}
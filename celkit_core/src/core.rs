@@ -1,7 +1,14 @@
 use crate::internal::sys::*;
+use crate::pointer::escape_segment;
+use crate::walk::PathSegment;
+use alloc::borrow::Cow;
+use alloc::sync::Arc;
+use core::cmp::Ordering;
 use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::Range;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Number {
     U8(u8),
     I8(i8),
@@ -15,45 +22,1067 @@ pub enum Number {
     I128(i128),
     F32(f32),
     F64(f64),
+    /// Digits that don't fit any other variant without losing precision, kept
+    /// verbatim as they were written
+    Big(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NumberKind {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    U128,
+    I128,
+    F32,
+    F64,
+    Big,
+}
+
+impl fmt::Display for NumberKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            NumberKind::U8 => "u8",
+            NumberKind::I8 => "i8",
+            NumberKind::U16 => "u16",
+            NumberKind::I16 => "i16",
+            NumberKind::U32 => "u32",
+            NumberKind::I32 => "i32",
+            NumberKind::U64 => "u64",
+            NumberKind::I64 => "i64",
+            NumberKind::U128 => "u128",
+            NumberKind::I128 => "i128",
+            NumberKind::F32 => "f32",
+            NumberKind::F64 => "f64",
+            NumberKind::Big => "big",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// Integer variants widened to a common representation, used to compare
+/// numbers across variants by their mathematical value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Widened {
+    Signed(i128),
+    Unsigned(u128),
+    Float(f64),
 }
 
-impl ToString for Number {
-    fn to_string(&self) -> String {
+impl Number {
+    pub fn kind(&self) -> NumberKind {
+        match self {
+            Number::U8(_) => NumberKind::U8,
+            Number::I8(_) => NumberKind::I8,
+            Number::U16(_) => NumberKind::U16,
+            Number::I16(_) => NumberKind::I16,
+            Number::U32(_) => NumberKind::U32,
+            Number::I32(_) => NumberKind::I32,
+            Number::U64(_) => NumberKind::U64,
+            Number::I64(_) => NumberKind::I64,
+            Number::U128(_) => NumberKind::U128,
+            Number::I128(_) => NumberKind::I128,
+            Number::F32(_) => NumberKind::F32,
+            Number::F64(_) => NumberKind::F64,
+            Number::Big(_) => NumberKind::Big,
+        }
+    }
+
+    /// Whether the number is one of the signed integer variants
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            Number::I8(_) | Number::I16(_) | Number::I32(_) | Number::I64(_) | Number::I128(_)
+        )
+    }
+
+    /// Whether the number is one of the unsigned integer variants
+    pub fn is_unsigned(&self) -> bool {
+        matches!(
+            self,
+            Number::U8(_) | Number::U16(_) | Number::U32(_) | Number::U64(_) | Number::U128(_)
+        )
+    }
+
+    pub fn is_integer(&self) -> bool {
+        !self.is_float()
+    }
+
+    /// Whether the number is a float, or big digits with a fraction or exponent
+    pub fn is_float(&self) -> bool {
+        match self {
+            Number::F32(_) | Number::F64(_) => true,
+            Number::Big(digits) => is_big_decimal(digits),
+            _ => false,
+        }
+    }
+
+    /// Whether the number is neither NaN nor infinite, always true for integers
+    pub fn is_finite(&self) -> bool {
+        match *self {
+            Number::F32(n) => n.is_finite(),
+            Number::F64(n) => n.is_finite(),
+            _ => true,
+        }
+    }
+
+    /// Integer value of the number if it's representable as an `i128`, floats
+    /// are only converted when they're integral
+    pub fn as_i128(&self) -> Option<i128> {
+        match self.widened() {
+            Widened::Signed(n) => Some(n),
+            Widened::Unsigned(_) => None,
+            Widened::Float(n) => match float_to_integer(n)? {
+                Widened::Signed(n) => Some(n),
+                _ => None,
+            },
+        }
+    }
+
+    /// Integer value of the number if it's representable as an `u128`, floats
+    /// are only converted when they're integral
+    pub fn as_u128(&self) -> Option<u128> {
+        match self.widened() {
+            Widened::Signed(n) => u128::try_from(n).ok(),
+            Widened::Unsigned(n) => Some(n),
+            Widened::Float(n) => match float_to_integer(n)? {
+                Widened::Signed(n) => u128::try_from(n).ok(),
+                Widened::Unsigned(n) => Some(n),
+                Widened::Float(_) => None,
+            },
+        }
+    }
+
+    /// Whether the value is an integer (or integral float) that fits an `i64`
+    pub fn fits_in_i64(&self) -> bool {
+        self.as_i64().is_some()
+    }
+
+    /// Whether the value is an integer (or integral float) that fits an `u64`
+    pub fn fits_in_u64(&self) -> bool {
+        self.as_u64().is_some()
+    }
+
+    /// Normalizes integer variants to `I128`, or `U128` for values above
+    /// `i128::MAX`. Floats are returned unchanged.
+    pub fn widen(&self) -> Number {
+        match self.widened() {
+            Widened::Signed(n) => Number::I128(n),
+            Widened::Unsigned(n) => Number::U128(n),
+            Widened::Float(_) => self.clone(),
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_i128().and_then(|n| i64::try_from(n).ok())
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_u128().and_then(|n| u64::try_from(n).ok())
+    }
+
+    /// Integer value of the number if it fits a `usize` on the current target
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_u128().and_then(|n| usize::try_from(n).ok())
+    }
+
+    /// Integer value of the number if it fits an `isize` on the current target
+    pub fn as_isize(&self) -> Option<isize> {
+        self.as_i128().and_then(|n| isize::try_from(n).ok())
+    }
+
+    /// Lossy conversion to `f64`, integers with more than 53 significant bits
+    /// (possible with the 64-bit and 128-bit variants) are rounded
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Number::U8(n) => n as f64,
+            Number::I8(n) => n as f64,
+            Number::U16(n) => n as f64,
+            Number::I16(n) => n as f64,
+            Number::U32(n) => n as f64,
+            Number::I32(n) => n as f64,
+            Number::U64(n) => n as f64,
+            Number::I64(n) => n as f64,
+            Number::U128(n) => n as f64,
+            Number::I128(n) => n as f64,
+            Number::F32(n) => n as f64,
+            Number::F64(n) => n,
+            Number::Big(ref digits) => digits.parse().unwrap_or(f64::NAN),
+        }
+    }
+
+    fn widened(&self) -> Widened {
+        match *self {
+            Number::U8(n) => Widened::Signed(n as i128),
+            Number::I8(n) => Widened::Signed(n as i128),
+            Number::U16(n) => Widened::Signed(n as i128),
+            Number::I16(n) => Widened::Signed(n as i128),
+            Number::U32(n) => Widened::Signed(n as i128),
+            Number::I32(n) => Widened::Signed(n as i128),
+            Number::U64(n) => Widened::Signed(n as i128),
+            Number::I64(n) => Widened::Signed(n as i128),
+            Number::U128(n) => match i128::try_from(n) {
+                Ok(n) => Widened::Signed(n),
+                Err(_) => Widened::Unsigned(n),
+            },
+            Number::I128(n) => Widened::Signed(n),
+            Number::F32(n) => Widened::Float(n as f64),
+            Number::F64(n) => Widened::Float(n),
+            Number::Big(ref digits) => widen_big(digits),
+        }
+    }
+}
+
+fn is_big_decimal(digits: &str) -> bool {
+    digits.contains(['.', 'e', 'E'])
+}
+
+/// Closest widened value of big digits, integers that exceed `u128` are
+/// approximated as floats. Comparisons involving `Big` go through `Exact`
+/// instead.
+fn widen_big(digits: &str) -> Widened {
+    if !is_big_decimal(digits) {
+        if let Ok(n) = digits.parse::<i128>() {
+            return Widened::Signed(n);
+        }
+
+        if let Ok(n) = digits.parse::<u128>() {
+            return Widened::Unsigned(n);
+        }
+    }
+
+    Widened::Float(digits.parse().unwrap_or(f64::NAN))
+}
+
+/// Exact value of a finite number as `digits` × 10^`exponent`, with neither
+/// leading nor trailing zeros in `digits` so each value has a single form.
+/// Zero has no digits.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Exact {
+    negative: bool,
+    digits: Vec<u8>,
+    exponent: i64,
+}
+
+impl Exact {
+    fn new(negative: bool, mut digits: Vec<u8>, mut exponent: i64) -> Exact {
+        let leading = digits.iter().take_while(|digit| **digit == 0).count();
+        digits.drain(..leading);
+
+        while digits.last() == Some(&0) {
+            digits.pop();
+            exponent += 1;
+        }
+
+        if digits.is_empty() {
+            return Exact {
+                negative: false,
+                digits,
+                exponent: 0,
+            };
+        }
+
+        Exact {
+            negative,
+            digits,
+            exponent,
+        }
+    }
+
+    /// `None` for NaN, infinities and big digits that aren't a number
+    fn of(number: &Number) -> Option<Exact> {
+        match *number {
+            Number::F32(n) => Exact::from_float(n as f64),
+            Number::F64(n) => Exact::from_float(n),
+            Number::Big(ref digits) => Exact::of_big(digits),
+            ref integer => Exact::parse(&integer.to_string()),
+        }
+    }
+
+    /// Big decimals that an `f64` reproduces, like `0.10`, stand for that
+    /// `f64` so they equal the float the same text parses to. Other digits,
+    /// integers included, keep their exact value.
+    fn of_big(digits: &str) -> Option<Exact> {
+        let exact = Exact::parse(digits)?;
+
+        if !is_big_decimal(digits) {
+            return Some(exact);
+        }
+
+        match digits.parse::<f64>() {
+            Ok(float)
+                if float.is_finite()
+                    && Exact::parse(&format!("{:e}", float)).as_ref() == Some(&exact) =>
+            {
+                Exact::from_float(float)
+            }
+            _ => Some(exact),
+        }
+    }
+
+    /// Reads `-12.5e3` style digits
+    fn parse(text: &str) -> Option<Exact> {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+
+        let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+        if integer.is_empty() && fraction.is_empty() {
+            return None;
+        }
+
+        let mut digits = Vec::with_capacity(integer.len() + fraction.len());
+
+        for c in integer.chars().chain(fraction.chars()) {
+            digits.push(c.to_digit(10)? as u8);
+        }
+
+        let exponent = exponent.checked_sub(i64::try_from(fraction.len()).ok()?)?;
+
+        Some(Exact::new(negative, digits, exponent))
+    }
+
+    /// Every finite float is a dyadic fraction, so its decimal expansion ends
+    fn from_float(float: f64) -> Option<Exact> {
+        if !float.is_finite() {
+            return None;
+        }
+
+        let bits = float.to_bits();
+        let biased = ((bits >> 52) & 0x7ff) as i64;
+        let fraction = bits & ((1 << 52) - 1);
+
+        let (mantissa, power) = match biased {
+            0 => (fraction, -1074),
+            _ => (fraction | (1 << 52), biased - 1075),
+        };
+
+        // Little-endian digits of `mantissa` × 2^`power`, a negative power is
+        // written as `mantissa` × 5^-`power` × 10^`power`
+        let mut digits = Vec::new();
+        let mut rest = mantissa;
+
+        while rest > 0 {
+            digits.push((rest % 10) as u8);
+            rest /= 10;
+        }
+
+        let (factor, times, exponent) = match power {
+            0.. => (2, power, 0),
+            _ => (5, -power, power),
+        };
+
+        for _ in 0..times {
+            let mut carry = 0;
+
+            for digit in digits.iter_mut() {
+                let product = *digit * factor + carry;
+                *digit = product % 10;
+                carry = product / 10;
+            }
+
+            if carry > 0 {
+                digits.push(carry);
+            }
+        }
+
+        digits.reverse();
+
+        Some(Exact::new(float.is_sign_negative(), digits, exponent))
+    }
+
+    /// Widened integer with the same value, when it's integral and in range
+    fn to_integer(&self) -> Option<Widened> {
+        if self.exponent < 0 || self.digits.len() as i64 + self.exponent > 39 {
+            return None;
+        }
+
+        let mut text = String::with_capacity(41);
+
+        if self.negative {
+            text.push('-');
+        }
+
+        text.extend(self.digits.iter().map(|digit| char::from(b'0' + digit)));
+        text.extend((0..self.exponent).map(|_| '0'));
+
+        match text.parse::<i128>() {
+            Ok(n) => Some(Widened::Signed(n)),
+            Err(_) => text.parse::<u128>().ok().map(Widened::Unsigned),
+        }
+    }
+
+    /// Positional digits, or `d.ddde±n` for magnitudes from 1e309, past any
+    /// `f64`, and for fractions more than 20 zeros past the decimal point.
+    /// Integers below that stay positional since `1e63` would read back as
+    /// an `f64`.
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        if self.digits.is_empty() {
+            return "0".to_string();
+        }
+
+        if self.negative {
+            text.push('-');
+        }
+
+        let digits: String = self
+            .digits
+            .iter()
+            .map(|digit| char::from(b'0' + digit))
+            .collect();
+        let point = self.digits.len() as i64 + self.exponent;
+        let zeros = |count: i64| (0..count).map(|_| '0').collect::<String>();
+
+        match (self.exponent, point) {
+            (0.., ..=309) => {
+                text.push_str(&digits);
+                text.push_str(&zeros(self.exponent));
+            }
+            (_, 1..) if self.exponent < 0 => {
+                let (integer, fraction) = digits.split_at(point as usize);
+
+                text.push_str(&format!("{}.{}", integer, fraction));
+            }
+            (_, -20..=0) if self.exponent < 0 => {
+                text.push_str(&format!("0.{}{}", zeros(-point), digits));
+            }
+            _ => {
+                let (first, rest) = digits.split_at(1);
+
+                text.push_str(first);
+
+                if !rest.is_empty() {
+                    text.push('.');
+                    text.push_str(rest);
+                }
+
+                text.push_str(&format!("e{}", point - 1));
+            }
+        }
+
+        text
+    }
+
+    fn cmp(&self, other: &Exact) -> Ordering {
+        let sign = |exact: &Exact| match (exact.digits.is_empty(), exact.negative) {
+            (true, _) => 0,
+            (false, true) => -1,
+            (false, false) => 1,
+        };
+
+        // Same sign, so the magnitude decides: first the position of the
+        // leading digit, then the digits themselves
+        let magnitude = |exact: &Exact| exact.digits.len() as i64 + exact.exponent;
+        let ordering = sign(self).cmp(&sign(other)).then_with(|| {
+            magnitude(self)
+                .cmp(&magnitude(other))
+                .then_with(|| self.digits.cmp(&other.digits))
+        });
+
+        match self.negative && other.negative {
+            true => ordering.reverse(),
+            false => ordering,
+        }
+    }
+}
+
+/// Place of a number next to a `Big`, which has no infinities or NaN: finite
+/// numbers compare by `Exact` value, big digits that aren't a number go last
+fn big_rank(number: &Number, exact: &Option<Exact>) -> i8 {
+    if exact.is_some() {
+        return 0;
+    }
+
+    match number.as_f64() {
+        _ if matches!(number, Number::Big(_)) => 3,
+        n if n.is_nan() => 2,
+        n if n > 0.0 => 1,
+        _ => -1,
+    }
+}
+
+/// Comparison of two numbers one of which is `Big`, exact so that `1.0`,
+/// `1.00` and `I64(1)` are all equal
+fn cmp_big(a: &Number, b: &Number) -> Ordering {
+    let (exact_a, exact_b) = (Exact::of(a), Exact::of(b));
+
+    match (exact_a, exact_b) {
+        (Some(exact_a), Some(exact_b)) => exact_a.cmp(&exact_b),
+        (exact_a, exact_b) => {
+            let ordering = big_rank(a, &exact_a).cmp(&big_rank(b, &exact_b));
+
+            match (a, b) {
+                (Number::Big(a), Number::Big(b)) if ordering == Ordering::Equal => a.cmp(b),
+                _ => ordering,
+            }
+        }
+    }
+}
+
+/// Numbers are compared by their mathematical value regardless of variant, so
+/// `U32(7) == I64(7)` while `F64(7.5) != I64(7)`. All NaNs are considered equal
+/// to each other, which keeps the comparison reflexive. Comparisons involving
+/// `Big` are by exact value, `Big("1.0") == Big("1.00") == I64(1)`, while
+/// digits that an `f64` can't reproduce never equal one.
+impl PartialEq for Number {
+    fn eq(&self, other: &Number) -> bool {
+        if matches!(self, Number::Big(_)) || matches!(other, Number::Big(_)) {
+            return cmp_big(self, other) == Ordering::Equal;
+        }
+
+        match (self.widened(), other.widened()) {
+            (Widened::Float(a), Widened::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (Widened::Float(float), integer) | (integer, Widened::Float(float)) => {
+                float_to_integer(float) == Some(integer)
+            }
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl Eq for Number {}
+
+/// Consistent with `PartialEq`: integral floats hash like the integer they are
+/// equal to, other floats hash by bit pattern with NaN normalized.
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let widened = match self {
+            Number::Big(digits) => match big_widened(digits) {
+                Ok(widened) => widened,
+                // No other variant holds this value
+                Err(Some(exact)) => {
+                    state.write_u8(3);
+                    exact.hash(state);
+
+                    return;
+                }
+                Err(None) => {
+                    state.write_u8(4);
+                    digits.hash(state);
+
+                    return;
+                }
+            },
+            _ => self.widened(),
+        };
+
+        let widened = match widened {
+            Widened::Float(float) => float_to_integer(float).unwrap_or(Widened::Float(float)),
+            integer => integer,
+        };
+
+        match widened {
+            Widened::Signed(n) => {
+                state.write_u8(0);
+                n.hash(state);
+            }
+            Widened::Unsigned(n) => {
+                state.write_u8(1);
+                n.hash(state);
+            }
+            Widened::Float(n) => {
+                let bits = if n.is_nan() {
+                    f64::NAN.to_bits()
+                } else {
+                    n.to_bits()
+                };
+
+                state.write_u8(2);
+                bits.hash(state);
+            }
+        }
+    }
+}
+
+/// Widened value of big digits when another variant can hold it exactly, so
+/// they hash alike, or else their `Exact` value. Digits that aren't a number
+/// hash verbatim.
+fn big_widened(digits: &str) -> core::result::Result<Widened, Option<Exact>> {
+    let Some(exact) = Exact::of_big(digits) else {
+        return Err(None);
+    };
+
+    if let Some(integer) = exact.to_integer() {
+        return Ok(integer);
+    }
+
+    match digits.parse::<f64>() {
+        Ok(float) if Exact::from_float(float).as_ref() == Some(&exact) => Ok(Widened::Float(float)),
+        _ => Err(Some(exact)),
+    }
+}
+
+// 2^127, the first float that doesn't fit in an `i128`
+const I128_LIMIT: f64 = 170141183460469231731687303715884105728.0;
+// 2^128, the first float that doesn't fit in an `u128`
+const U128_LIMIT: f64 = 340282366920938463463374607431768211456.0;
+
+// 2^52, from which on every `f64` is integral
+const INTEGRAL_LIMIT: f64 = 4503599627370496.0;
+
+/// `f64::trunc` is only available with `std`, floats below 2^52 fit an `i64`
+fn trunc(float: f64) -> f64 {
+    if float.is_nan() || float.abs() >= INTEGRAL_LIMIT {
+        return float;
+    }
+
+    float as i64 as f64
+}
+
+/// Converts an integral float into the widened integer it's equal to
+fn float_to_integer(float: f64) -> Option<Widened> {
+    if !float.is_finite() || trunc(float) != float {
+        return None;
+    }
+
+    if (-I128_LIMIT..I128_LIMIT).contains(&float) {
+        return Some(Widened::Signed(float as i128));
+    }
+
+    if (I128_LIMIT..U128_LIMIT).contains(&float) {
+        return Some(Widened::Unsigned(float as u128));
+    }
+
+    None
+}
+
+fn cmp_integers(a: Widened, b: Widened) -> Ordering {
+    match (a, b) {
+        (Widened::Signed(a), Widened::Signed(b)) => a.cmp(&b),
+        (Widened::Unsigned(a), Widened::Unsigned(b)) => a.cmp(&b),
+        // Unsigned values are only used for numbers above `i128::MAX`
+        (Widened::Signed(_), Widened::Unsigned(_)) => Ordering::Less,
+        (Widened::Unsigned(_), Widened::Signed(_)) => Ordering::Greater,
+        _ => unreachable!(),
+    }
+}
+
+fn canonical_float(float: f64) -> Number {
+    match float_to_integer(float) {
+        Some(Widened::Signed(n)) => Number::I128(n),
+        Some(Widened::Unsigned(n)) => Number::U128(n),
+        _ => Number::F64(float),
+    }
+}
+
+/// Compares an integer with a float without going through a lossy cast
+fn cmp_integer_float(integer: Widened, float: f64) -> Ordering {
+    if float.is_nan() || float >= U128_LIMIT {
+        return Ordering::Less;
+    }
+
+    if float < -I128_LIMIT {
+        return Ordering::Greater;
+    }
+
+    let truncated = trunc(float);
+
+    // The truncated float is integral and in range, so the conversion is exact
+    match cmp_integers(integer, float_to_integer(truncated).unwrap()) {
+        Ordering::Equal => 0.0.partial_cmp(&(float - truncated)).unwrap(),
+        ordering => ordering,
+    }
+}
+
+impl Number {
+    /// Total ordering by mathematical value across variants, NaN is placed
+    /// after every other number (and equal to other NaNs)
+    pub fn total_cmp(&self, other: &Number) -> Ordering {
+        if matches!(self, Number::Big(_)) || matches!(other, Number::Big(_)) {
+            return cmp_big(self, other);
+        }
+
+        self.cmp_widened(other)
+    }
+
+    /// The single number `to_canonical` writes for every number equal to this
+    /// one. Integers and integral floats become `I128`, or `U128` above
+    /// `i128::MAX`, other floats become `F64`. Big digits equal to one of
+    /// those become it, the rest are normalized, see `to_canonical`.
+    pub fn canonical(&self) -> Number {
+        let float = match *self {
+            Number::F32(n) => n as f64,
+            Number::F64(n) => n,
+            Number::Big(ref digits) => {
+                return match big_widened(digits) {
+                    Ok(Widened::Signed(n)) => Number::I128(n),
+                    Ok(Widened::Unsigned(n)) => Number::U128(n),
+                    Ok(Widened::Float(float)) => canonical_float(float),
+                    Err(Some(exact)) => Number::Big(exact.to_text()),
+                    Err(None) => self.clone(),
+                };
+            }
+            _ => return self.widen(),
+        };
+
+        canonical_float(float)
+    }
+
+    fn cmp_widened(&self, other: &Number) -> Ordering {
+        match (self.widened(), other.widened()) {
+            (Widened::Float(a), Widened::Float(b)) => match (a.is_nan(), b.is_nan()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => a.partial_cmp(&b).unwrap(),
+            },
+            (Widened::Float(float), integer) => cmp_integer_float(integer, float).reverse(),
+            (integer, Widened::Float(float)) => cmp_integer_float(integer, float),
+            (a, b) => cmp_integers(a, b),
+        }
+    }
+}
+
+/// Orders numbers by their mathematical value across variants without lossy
+/// casts. NaN is unordered against other numbers, but it's equal to another
+/// NaN to stay consistent with `PartialEq`.
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Number) -> Option<Ordering> {
+        let is_nan = |number: &Number| matches!(number.widened(), Widened::Float(n) if n.is_nan());
+
+        match (is_nan(self), is_nan(other)) {
+            (false, false) | (true, true) => Some(self.total_cmp(other)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::U8(number) => write!(f, "{}", number),
+            Number::I8(number) => write!(f, "{}", number),
+            Number::U16(number) => write!(f, "{}", number),
+            Number::I16(number) => write!(f, "{}", number),
+            Number::U32(number) => write!(f, "{}", number),
+            Number::I32(number) => write!(f, "{}", number),
+            Number::U64(number) => write!(f, "{}", number),
+            Number::I64(number) => write!(f, "{}", number),
+            Number::U128(number) => write!(f, "{}", number),
+            Number::I128(number) => write!(f, "{}", number),
+            Number::F32(number) => write_float(f, *number),
+            Number::F64(number) => write_float(f, *number),
+            Number::Big(digits) => f.write_str(digits),
+        }
+    }
+}
+
+/// Writes the shortest digits that parse back to the exact same float, which
+/// is what the standard `Display` impl of `f32`/`f64` produces
+fn write_float<F: fmt::Display + Into<f64> + Copy>(
+    f: &mut fmt::Formatter<'_>,
+    number: F,
+) -> fmt::Result {
+    let wide: f64 = number.into();
+
+    if !wide.is_finite() {
+        return write_non_finite(f, wide);
+    }
+
+    write!(f, "{}", number)?;
+
+    // Integral floats are displayed without a decimal point, which would be
+    // read back as an integer (and drop the sign of `-0.0`)
+    if wide % 1.0 == 0.0 {
+        f.write_str(".0")?;
+    }
+
+    Ok(())
+}
+
+/// Writes the `nan`, `inf` and `-inf` tokens understood by the parser
+fn write_non_finite(f: &mut fmt::Formatter<'_>, number: f64) -> fmt::Result {
+    if number.is_nan() {
+        f.write_str("nan")
+    } else if number.is_sign_negative() {
+        f.write_str("-inf")
+    } else {
+        f.write_str("inf")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    Null,
+    Boolean,
+    Number,
+    Text,
+    Array,
+    Tuple,
+    Object,
+    Struct,
+}
+
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ValueKind::Null => "null",
+            ValueKind::Boolean => "boolean",
+            ValueKind::Number => "number",
+            ValueKind::Text => "text",
+            ValueKind::Array => "array",
+            ValueKind::Tuple => "tuple",
+            ValueKind::Object => "object",
+            ValueKind::Struct => "struct",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// Struct names don't participate in equality (or hashing), since the
+/// encoders only emit them with `struct_names` enabled and a struct decoded
+/// from `@(...)` should still compare equal to its original.
+#[derive(Debug, Clone, Default)]
+pub enum Value {
+    #[default]
+    Null,
+    Boolean(bool),
+    Number(Number),
+    Text(String),
+    Array(Vec<Value>),
+    Tuple(Vec<Value>),
+    Object(BTreeMap<String, Value>),
+    Struct(String, BTreeMap<String, Value>),
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Text(a), Value::Text(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Tuple(a), Value::Tuple(b)) => a == b,
+            (Value::Object(a), Value::Object(b)) => a == b,
+            (Value::Struct(_, a), Value::Struct(_, b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        core::mem::discriminant(self).hash(state);
+
+        match self {
+            Value::Null => {}
+            Value::Boolean(boolean) => boolean.hash(state),
+            Value::Number(number) => number.hash(state),
+            Value::Text(text) => text.hash(state),
+            Value::Array(items) | Value::Tuple(items) => items.hash(state),
+            Value::Object(map) | Value::Struct(_, map) => map.hash(state),
+        }
+    }
+}
+
+impl Value {
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Value::Null => ValueKind::Null,
+            Value::Boolean(_) => ValueKind::Boolean,
+            Value::Number(_) => ValueKind::Number,
+            Value::Text(_) => ValueKind::Text,
+            Value::Array(_) => ValueKind::Array,
+            Value::Tuple(_) => ValueKind::Tuple,
+            Value::Object(_) => ValueKind::Object,
+            Value::Struct(_, _) => ValueKind::Struct,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn is_boolean(&self) -> bool {
+        matches!(self, Value::Boolean(_))
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, Value::Number(_))
+    }
+
+    pub fn is_text(&self) -> bool {
+        matches!(self, Value::Text(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, Value::Array(_))
+    }
+
+    pub fn is_tuple(&self) -> bool {
+        matches!(self, Value::Tuple(_))
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self, Value::Object(_))
+    }
+
+    pub fn is_struct(&self) -> bool {
+        matches!(self, Value::Struct(_, _))
+    }
+
+    pub fn as_boolean(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(boolean) => Some(*boolean),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            Value::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Value::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_tuple(&self) -> Option<&Vec<Value>> {
         match self {
-            Number::U8(number) => number.to_string(),
-            Number::I8(number) => number.to_string(),
-            Number::U16(number) => number.to_string(),
-            Number::I16(number) => number.to_string(),
-            Number::U32(number) => number.to_string(),
-            Number::I32(number) => number.to_string(),
-            Number::U64(number) => number.to_string(),
-            Number::I64(number) => number.to_string(),
-            Number::U128(number) => number.to_string(),
-            Number::I128(number) => number.to_string(),
-            Number::F32(number) => number.to_string(),
-            Number::F64(number) => number.to_string(),
+            Value::Tuple(tuple) => Some(tuple),
+            _ => None,
         }
     }
-}
 
-#[derive(Debug, Clone)]
-pub enum Value {
-    Null,
-    Boolean(bool),
-    Number(Number),
-    Text(String),
-    Array(Vec<Value>),
-    Tuple(Vec<Value>),
-    Object(BTreeMap<String, Value>),
-    Struct(String, BTreeMap<String, Value>),
+    pub fn as_object(&self) -> Option<&BTreeMap<String, Value>> {
+        match self {
+            Value::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    /// Returns the struct name along with its fields
+    pub fn as_struct(&self) -> Option<(&str, &BTreeMap<String, Value>)> {
+        match self {
+            Value::Struct(name, fields) => Some((name, fields)),
+            _ => None,
+        }
+    }
+
+    pub fn as_boolean_mut(&mut self) -> Option<&mut bool> {
+        match self {
+            Value::Boolean(boolean) => Some(boolean),
+            _ => None,
+        }
+    }
+
+    pub fn as_number_mut(&mut self) -> Option<&mut Number> {
+        match self {
+            Value::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    pub fn as_text_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Value::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_tuple_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Tuple(tuple) => Some(tuple),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut BTreeMap<String, Value>> {
+        match self {
+            Value::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn as_struct_mut(&mut self) -> Option<(&mut String, &mut BTreeMap<String, Value>)> {
+        match self {
+            Value::Struct(name, fields) => Some((name, fields)),
+            _ => None,
+        }
+    }
+
+    /// Number of elements of a container, or of characters of a text. Other
+    /// kinds have no length.
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            Value::Text(text) => Some(text.chars().count()),
+            Value::Array(items) | Value::Tuple(items) => Some(items.len()),
+            Value::Object(map) | Value::Struct(_, map) => Some(map.len()),
+            _ => None,
+        }
+    }
+
+    /// Whether the value is an empty container or text
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+
+    /// Moves the value out, leaving `Value::Null` in its place
+    pub fn take(&mut self) -> Value {
+        core::mem::take(self)
+    }
+
+    /// Replaces the value with `new`, returning the previous one
+    pub fn replace(&mut self, new: Value) -> Value {
+        core::mem::replace(self, new)
+    }
 }
 
+/// Characters of source kept in `Error::context` on either side of the column
+const CONTEXT_RADIUS: usize = 40;
+
 #[derive(Debug)]
 pub struct Error {
     pub message: String,
+    /// Source line the error points at, see `Error::with_source`
     pub context: Option<String>,
     pub line: Option<usize>,
     pub column: Option<usize>,
+    /// Byte offset of the offending token in the source
+    pub offset: Option<usize>,
+    /// Byte length of the offending token, `0` at the end of the input
+    pub len: Option<usize>,
+    /// See `Error::path`, boxed as a thin pointer to keep `Result`s small
+    #[allow(clippy::box_collection)]
+    path: Option<Box<String>>,
 }
 
 impl Error {
@@ -63,6 +1092,9 @@ impl Error {
             context: None,
             line: None,
             column: None,
+            offset: None,
+            len: None,
+            path: None,
         }
     }
 
@@ -72,6 +1104,9 @@ impl Error {
             context: None,
             line: Some(line),
             column: Some(column),
+            offset: None,
+            len: None,
+            path: None,
         }
     }
 
@@ -86,7 +1121,81 @@ impl Error {
             context: Some(context.into()),
             line: Some(line),
             column: Some(column),
+            offset: None,
+            len: None,
+            path: None,
+        }
+    }
+
+    /// Sets the byte span of the offending token
+    pub fn with_span(mut self, offset: usize, len: usize) -> Self {
+        self.offset = Some(offset);
+        self.len = Some(len);
+
+        self
+    }
+
+    /// Prepends `segment` to `path`, for containers passing on the error of
+    /// one of their values
+    pub fn at(mut self, segment: PathSegment<'_>) -> Self {
+        let mut path = String::new();
+
+        push_segment(&mut path, segment);
+        path.push_str(self.path().unwrap_or_default());
+        self.path = Some(Box::new(path));
+
+        self
+    }
+
+    /// Value that failed to deserialize, in `Value::pointer` syntax, `None`
+    /// for the root or errors that aren't about a value
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref().map(String::as_str)
+    }
+
+    /// Byte range of the offending token, to slice the source with
+    pub fn span(&self) -> Option<core::ops::Range<usize>> {
+        let offset = self.offset?;
+
+        Some(offset..offset + self.len.unwrap_or(0))
+    }
+
+    /// Fills in `context` with the line of `source` the error points at, so
+    /// it's displayed with a `^` under the column. Long lines are cut to
+    /// `CONTEXT_RADIUS` characters on either side, marking the cuts with
+    /// `...`.
+    pub fn with_source(mut self, source: &str) -> Self {
+        let (Some(line), Some(column), None) = (self.line, self.column, &self.context) else {
+            return self;
+        };
+
+        // Columns don't count a leading byte order mark
+        let source = source.strip_prefix('\u{FEFF}').unwrap_or(source);
+
+        let Some(text) = line.checked_sub(1).and_then(|i| source.split('\n').nth(i)) else {
+            return self;
+        };
+
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        let before = column.saturating_sub(1);
+        let start = before.saturating_sub(CONTEXT_RADIUS);
+        let length = before - start + CONTEXT_RADIUS;
+        let mut chars = text.chars().skip(start);
+        let mut context = String::new();
+
+        if start > 0 {
+            context.push_str("...");
+        }
+
+        context.extend(chars.by_ref().take(length));
+
+        if chars.next().is_some() {
+            context.push_str("...");
         }
+
+        self.context = Some(context);
+
+        self
     }
 }
 
@@ -101,13 +1210,30 @@ impl fmt::Display for Error {
                 )?;
 
                 if let Some(context) = &self.context {
-                    write!(f, "\nContext: {}", context)?;
+                    write!(f, "\n    {}\n    ", context)?;
+
+                    // Where the column lands in the context, past a leading
+                    // `...` when the line was cut
+                    let caret = match column.saturating_sub(1) {
+                        before if before > CONTEXT_RADIUS => CONTEXT_RADIUS + 3,
+                        before => before,
+                    };
+
+                    // Tabs are kept so the caret lines up however wide they're shown
+                    for c in context.chars().take(caret) {
+                        f.write_str(if c == '\t' { "\t" } else { " " })?;
+                    }
+
+                    f.write_str("^")?;
                 }
 
                 Ok(())
             }
             _ => {
-                write!(f, "Error: {}", self.message)?;
+                match &self.path {
+                    Some(path) => write!(f, "Error at `{}`: {}", path, self.message)?,
+                    None => write!(f, "Error: {}", self.message)?,
+                }
 
                 Ok(())
             }
@@ -123,6 +1249,615 @@ pub trait Serialize {
     fn serialize(&self) -> Result<Value>;
 }
 
+/// What deserializing a struct does about a field the input leaves out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingFields {
+    /// Fail naming the field and the struct
+    #[default]
+    Error,
+    /// Fall back to the field type's `Deserialize::default_value`, or to
+    /// `Default::default()` for fields read with `field_or_default`. Other
+    /// fields still fail.
+    UseDefault,
+}
+
+/// What deserializing a struct does about fields it doesn't declare
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownFields {
+    #[default]
+    Ignore,
+    /// Fail naming the first unknown field and the fields the struct expects
+    Error,
+    /// Hand them to the struct through `struct_extras`, structs from
+    /// `impl_for_struct` have nowhere to keep them and drop them
+    Collect,
+}
+
+/// Leniencies applied while converting a `Value` into typed data
+#[derive(Debug, Clone, Default)]
+pub struct DeserializeOptions {
+    /// Lets an object stand in for a struct and a struct for an object
+    pub coerce_struct_object: bool,
+    pub missing_fields: MissingFields,
+    pub unknown_fields: UnknownFields,
+    /// Matches struct fields to keys ignoring ASCII case, two keys that only
+    /// differ in case are an error
+    pub case_insensitive_fields: bool,
+    /// Lets text such as `"8080"` stand in for a number, surrounding
+    /// whitespace and a leading `+` allowed but not `nan` or `inf`
+    pub coerce_numbers_from_text: bool,
+    /// Lets `1` and `0` and the texts in `TRUE_WORDS` and `FALSE_WORDS` stand
+    /// in for a boolean
+    pub coerce_booleans: bool,
+    /// Rejects `@Name(...)` whose name isn't the one the target struct is
+    /// declared with, nested structs included. Unnamed `@(...)` structs are
+    /// still accepted.
+    pub strict_struct_names: bool,
+    /// Text the value was decoded from, set by decoders when the target type
+    /// keeps its source text, see `Deserialize::captures_source`
+    pub source: Option<Source>,
+}
+
+impl DeserializeOptions {
+    /// Options for the value at `segment` below the one being deserialized.
+    /// Only copied when there's a `source` to follow the path in.
+    pub fn at(&self, segment: PathSegment<'_>) -> Cow<'_, DeserializeOptions> {
+        let Some(source) = &self.source else {
+            return Cow::Borrowed(self);
+        };
+
+        let mut options = self.clone();
+
+        options.source = Some(source.child(segment));
+
+        Cow::Owned(options)
+    }
+}
+
+/// Text a document was decoded from and the span of every value in it, for
+/// types that keep the text of their value
+#[derive(Debug, Clone, Default)]
+pub struct Source {
+    text: Arc<str>,
+    spans: Arc<BTreeMap<String, Range<usize>>>,
+    /// Path of the value being deserialized, in `Value::pointer` syntax
+    path: String,
+}
+
+impl Source {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: Arc::from(text),
+            ..Self::default()
+        }
+    }
+
+    /// Records the byte span in the text of the value at `path`
+    pub fn insert(&mut self, path: &[PathSegment<'_>], span: Range<usize>) {
+        let mut pointer = String::new();
+
+        for segment in path {
+            push_segment(&mut pointer, *segment);
+        }
+
+        Arc::make_mut(&mut self.spans).insert(pointer, span);
+    }
+
+    /// Source text of the value being deserialized
+    pub fn text(&self) -> Option<&str> {
+        let span = self.spans.get(&self.path)?;
+
+        self.text.get(span.clone())
+    }
+
+    fn child(&self, segment: PathSegment<'_>) -> Self {
+        let mut child = self.clone();
+
+        push_segment(&mut child.path, segment);
+
+        child
+    }
+}
+
+fn push_segment(pointer: &mut String, segment: PathSegment<'_>) {
+    pointer.push('/');
+
+    match segment {
+        PathSegment::Key(key) => pointer.push_str(&escape_segment(key)),
+        PathSegment::Index(index) => pointer.push_str(&index.to_string()),
+    }
+}
+
 pub trait Deserialize: Sized {
     fn deserialize(value: Value) -> Result<Self>;
+
+    /// Same as `deserialize` with leniencies, types holding other values
+    /// pass `options` on to them
+    fn deserialize_with(value: Value, _options: &DeserializeOptions) -> Result<Self> {
+        Self::deserialize(value)
+    }
+
+    /// Name of the struct this type is read from, if it's declared with one
+    fn struct_name() -> Option<&'static str> {
+        None
+    }
+
+    /// Value a missing struct field of this type falls back to under
+    /// `MissingFields::UseDefault`, `None` when it has no default
+    fn default_value() -> Option<Self> {
+        None
+    }
+
+    /// Whether this type keeps the source text of its value, decoders then
+    /// set `DeserializeOptions::source`. Types holding other values answer
+    /// for them.
+    fn captures_source() -> bool {
+        false
+    }
+}
+
+/// Converts typed data into a `Value`
+pub fn to_value<T: ?Sized + Serialize>(value: &T) -> Result<Value> {
+    value.serialize()
+}
+
+/// Converts a `Value` into typed data
+pub fn from_value<T: Deserialize>(value: Value) -> Result<T> {
+    T::deserialize(value)
+}
+
+pub fn from_value_with_options<T: Deserialize>(
+    value: Value,
+    options: &DeserializeOptions,
+) -> Result<T> {
+    T::deserialize_with(value, options)
+}
+
+/// Checks the name a struct was written with against `struct_name` under
+/// `options.strict_struct_names`. For hand-written `Deserialize` impls,
+/// `impl_for_struct` uses it as well.
+pub fn check_struct_name(
+    name: &str,
+    struct_name: &str,
+    options: &DeserializeOptions,
+) -> Result<()> {
+    if options.strict_struct_names && !name.is_empty() && name != struct_name {
+        return Err(Error::new(format!(
+            "Expected struct `{}`, found `@{}`",
+            struct_name, name
+        )));
+    }
+
+    Ok(())
+}
+
+/// Takes the field `name` out of the fields of struct `struct_name` and
+/// deserializes it, applying `options.missing_fields` when it's absent. For
+/// hand-written `Deserialize` impls, `impl_for_struct` uses it as well.
+pub fn struct_field<T: Deserialize>(
+    fields: &mut BTreeMap<String, Value>,
+    name: &str,
+    struct_name: &str,
+    options: &DeserializeOptions,
+) -> Result<T> {
+    take_struct_field(fields, name, struct_name, options, T::default_value)
+}
+
+/// Same as `struct_field`, except that a missing field falls back to
+/// `T::default()` under `MissingFields::UseDefault`. For field types without
+/// a `Deserialize::default_value` of their own, such as nested structs.
+pub fn field_or_default<T: Deserialize + Default>(
+    fields: &mut BTreeMap<String, Value>,
+    name: &str,
+    struct_name: &str,
+    options: &DeserializeOptions,
+) -> Result<T> {
+    take_struct_field(fields, name, struct_name, options, || Some(T::default()))
+}
+
+fn take_struct_field<T: Deserialize>(
+    fields: &mut BTreeMap<String, Value>,
+    name: &str,
+    struct_name: &str,
+    options: &DeserializeOptions,
+    default: impl FnOnce() -> Option<T>,
+) -> Result<T> {
+    let Some((key, value)) = take_field(fields, name, struct_name, options)? else {
+        let default = match options.missing_fields {
+            MissingFields::UseDefault => default(),
+            MissingFields::Error => None,
+        };
+
+        return default
+            .ok_or_else(|| Error::new(format!("Missing `{}` field in {}", name, struct_name)));
+    };
+
+    T::deserialize_with(value, &options.at(PathSegment::Key(&key))).map_err(|mut error| {
+        error.message = format!(
+            "Invalid `{}` field in {}: {}",
+            name, struct_name, error.message
+        );
+
+        error.at(PathSegment::Key(&key))
+    })
+}
+
+/// Removes the key and value for field `name`, ignoring ASCII case under
+/// `case_insensitive_fields`. The map doesn't keep the order keys were
+/// written in, so keys clashing that way always fail rather than one winning.
+fn take_field(
+    fields: &mut BTreeMap<String, Value>,
+    name: &str,
+    struct_name: &str,
+    options: &DeserializeOptions,
+) -> Result<Option<(String, Value)>> {
+    if !options.case_insensitive_fields {
+        return Ok(fields.remove_entry(name));
+    }
+
+    let mut keys = fields.keys().filter(|key| key.eq_ignore_ascii_case(name));
+
+    let Some(key) = keys.next().cloned() else {
+        return Ok(None);
+    };
+
+    if let Some(other) = keys.next() {
+        return Err(Error::new(format!(
+            "Fields `{}` and `{}` in {} both match `{}` ignoring case",
+            key, other, struct_name, name
+        )));
+    }
+
+    Ok(fields.remove_entry(&key))
+}
+
+/// Applies `options.unknown_fields` to what's left of a struct's fields once
+/// `struct_field` has taken the `expected` ones, returning the leftovers under
+/// `UnknownFields::Collect` and nothing otherwise
+pub fn struct_extras(
+    fields: BTreeMap<String, Value>,
+    expected: &[&str],
+    struct_name: &str,
+    options: &DeserializeOptions,
+) -> Result<BTreeMap<String, Value>> {
+    match options.unknown_fields {
+        UnknownFields::Ignore => Ok(BTreeMap::new()),
+        UnknownFields::Collect => Ok(fields),
+        UnknownFields::Error => match fields.into_keys().next() {
+            Some(key) => Err(unknown_field(&key, expected, struct_name).at(PathSegment::Key(&key))),
+            None => Ok(BTreeMap::new()),
+        },
+    }
+}
+
+fn unknown_field(key: &str, expected: &[&str], struct_name: &str) -> Error {
+    let mut message = format!("Unknown `{}` field in {}", key, struct_name);
+
+    if expected.is_empty() {
+        message.push_str(", expected no fields");
+
+        return Error::new(message);
+    }
+
+    let names: Vec<String> = expected.iter().map(|name| format!("`{}`", name)).collect();
+
+    message.push_str(&format!(", expected one of {}", names.join(", ")));
+
+    // Close enough to be a typo, a third of the key's length at most, and
+    // case doesn't count towards it
+    let key = key.to_ascii_lowercase();
+    let closest = expected
+        .iter()
+        .map(|name| (edit_distance(&key, &name.to_ascii_lowercase()), name))
+        .filter(|(distance, _)| *distance <= key.chars().count().div_ceil(3))
+        .min_by_key(|(distance, _)| *distance);
+
+    if let Some((_, name)) = closest {
+        message.push_str(&format!(", did you mean `{}`?", name));
+    }
+
+    Error::new(message)
+}
+
+/// Levenshtein distance between `a` and `b` in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, &b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+
+    fn hash_of(number: &Number) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        number.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn big(digits: &str) -> Number {
+        Number::Big(digits.to_string())
+    }
+
+    fn numbers() -> Vec<Number> {
+        Vec::from([
+            Number::I64(1),
+            Number::U8(1),
+            Number::F64(1.0),
+            Number::F32(1.0),
+            big("1"),
+            big("1.0"),
+            big("1.00"),
+            big("10e-1"),
+            Number::F64(0.1),
+            big("0.1"),
+            big("0.10"),
+            big("0.1000000000000000000001"),
+            big("0.1000000000000000000002"),
+            Number::U128(u128::MAX),
+            big("340282366920938463463374607431768211455"),
+            big("340282366920938463463374607431768211456"),
+            big("340282366920938463463374607431768211457"),
+            Number::F64(340282366920938463463374607431768211456.0),
+            big("-0.0"),
+            Number::F64(-0.0),
+            Number::I8(0),
+            Number::F64(f64::NAN),
+            Number::F64(f64::INFINITY),
+            big("not a number"),
+        ])
+    }
+
+    #[test]
+    fn big_digits_equal_by_value() {
+        assert_eq!(big("1.0"), Number::I64(1));
+        assert_eq!(Number::I64(1), big("1.00"));
+        assert_eq!(big("1.0"), big("1.00"));
+        assert_eq!(big("0.10"), Number::F64(0.1));
+        assert_ne!(big("0.1000000000000000000001"), Number::F64(0.1));
+        assert_ne!(
+            big("0.1000000000000000000001"),
+            big("0.1000000000000000000002")
+        );
+    }
+
+    #[test]
+    fn big_integers_above_u128_stay_exact() {
+        let a = big("340282366920938463463374607431768211457");
+        let b = big("340282366920938463463374607431768211456");
+
+        assert_ne!(a, b);
+        assert_eq!(b, Number::F64(340282366920938463463374607431768211456.0));
+        assert_ne!(a, Number::F64(340282366920938463463374607431768211456.0));
+        assert_eq!(a.total_cmp(&b), Ordering::Greater);
+    }
+
+    #[test]
+    fn equality_is_transitive() {
+        let numbers = numbers();
+
+        for a in &numbers {
+            for b in &numbers {
+                for c in &numbers {
+                    if a == b && b == c {
+                        assert_eq!(a, c, "{:?} == {:?} == {:?}", a, b, c);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn equal_numbers_hash_and_order_alike() {
+        let numbers = numbers();
+
+        for a in &numbers {
+            for b in &numbers {
+                let equal = a == b;
+
+                assert_eq!(equal, b == a, "{:?} {:?}", a, b);
+                assert_eq!(equal, a.total_cmp(b) == Ordering::Equal, "{:?} {:?}", a, b);
+                assert_eq!(a.total_cmp(b), b.total_cmp(a).reverse(), "{:?} {:?}", a, b);
+
+                if equal {
+                    assert_eq!(hash_of(a), hash_of(b), "{:?} {:?}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn total_order_is_transitive() {
+        let numbers = numbers();
+
+        for a in &numbers {
+            for b in &numbers {
+                for c in &numbers {
+                    if a.total_cmp(b) != Ordering::Greater && b.total_cmp(c) != Ordering::Greater {
+                        assert_ne!(a.total_cmp(c), Ordering::Greater, "{:?} {:?} {:?}", a, b, c);
+                    }
+                }
+            }
+        }
+    }
+
+    fn value_hash_of(value: &Value) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The same data reached through different variants and typed round trips
+    fn values() -> Vec<Value> {
+        let mut values: Vec<Value> = numbers().into_iter().map(Value::Number).collect();
+
+        for n in [0u8, 1, 200] {
+            values.push(to_value(&n).unwrap());
+            values.push(to_value(&from_value::<u64>(to_value(&n).unwrap()).unwrap()).unwrap());
+            values.push(to_value(&from_value::<f32>(to_value(&n).unwrap()).unwrap()).unwrap());
+            values.push(to_value(&(n as i128)).unwrap());
+        }
+
+        let fields = |number: Number| {
+            BTreeMap::from([
+                ("a".to_string(), Value::Number(number)),
+                ("b".to_string(), Value::Array(Vec::from([Value::Null]))),
+            ])
+        };
+
+        values.extend([
+            Value::Null,
+            Value::Boolean(false),
+            Value::Text("1".to_string()),
+            Value::Array(Vec::from([Value::Number(Number::I8(1))])),
+            Value::Array(Vec::from([Value::Number(Number::F64(1.0))])),
+            Value::Tuple(Vec::from([Value::Number(Number::U64(1))])),
+            Value::Object(fields(Number::U16(1))),
+            Value::Object(fields(Number::F32(1.0))),
+            Value::Struct("Point".to_string(), fields(Number::I32(1))),
+            Value::Struct(String::new(), fields(Number::F64(1.0))),
+            Value::Struct("Point".to_string(), fields(Number::F64(f64::NAN))),
+        ]);
+
+        values
+    }
+
+    #[test]
+    fn equal_values_hash_alike() {
+        let values = values();
+
+        for a in &values {
+            assert_eq!(a, a, "{:?}", a);
+
+            for b in &values {
+                assert_eq!(a == b, b == a, "{:?} {:?}", a, b);
+
+                if a == b {
+                    assert_eq!(value_hash_of(a), value_hash_of(b), "{:?} {:?}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hash_set_deduplicates_across_variants() {
+        let set: std::collections::HashSet<Value> = [
+            Value::Number(Number::I8(1)),
+            Value::Number(Number::U128(1)),
+            Value::Number(Number::F64(1.0)),
+            Value::Number(Number::F64(f64::NAN)),
+            Value::Number(Number::F32(f32::NAN)),
+            Value::Number(Number::F64(0.0)),
+            Value::Number(Number::F64(-0.0)),
+            Value::Array(Vec::new()),
+            Value::Tuple(Vec::new()),
+        ]
+        .into_iter()
+        .collect();
+
+        // 1, NaN, 0, `[]` and `()`
+        assert_eq!(set.len(), 5);
+    }
+
+    /// `n` in every variant that holds it exactly
+    fn variants(n: i128) -> Vec<Number> {
+        let mut variants = Vec::from([Number::I128(n), big(&n.to_string())]);
+
+        variants.extend(i64::try_from(n).ok().map(Number::I64));
+        variants.extend(u64::try_from(n).ok().map(Number::U64));
+        variants.extend(u128::try_from(n).ok().map(Number::U128));
+        variants.extend(i32::try_from(n).ok().map(Number::I32));
+        variants.extend(u8::try_from(n).ok().map(Number::U8));
+
+        if (n as f64) as i128 == n {
+            variants.push(Number::F64(n as f64));
+        }
+
+        if (n as f32) as i128 == n {
+            variants.push(Number::F32(n as f32));
+        }
+
+        variants
+    }
+
+    #[test]
+    fn variants_compare_by_value_near_boundaries() {
+        let boundaries = [
+            u64::MAX as i128,
+            i64::MIN as i128,
+            i64::MAX as i128,
+            1 << 53,
+            -(1 << 53),
+            1 << 64,
+            0,
+        ];
+
+        for boundary in boundaries {
+            for offset in [-2, -1, 0, 1, 2] {
+                let n = boundary + offset;
+
+                for a in variants(n) {
+                    for b in variants(n) {
+                        assert_eq!(a, b);
+                        assert_eq!(a.partial_cmp(&b), Some(Ordering::Equal), "{:?} {:?}", a, b);
+                    }
+
+                    for b in variants(n + 1) {
+                        assert_eq!(a.partial_cmp(&b), Some(Ordering::Less), "{:?} {:?}", a, b);
+                        assert_eq!(b.total_cmp(&a), Ordering::Greater, "{:?} {:?}", b, a);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn integers_are_not_rounded_to_floats() {
+        let above = (1u64 << 53) + 1;
+
+        assert!(Number::U64(above) > Number::F64((1u64 << 53) as f64));
+        assert!(Number::U64(above) < Number::F64(((1u64 << 53) + 2) as f64));
+        assert!(Number::U64(u64::MAX) < Number::F64(u64::MAX as f64));
+        assert!(Number::I64(i64::MAX) < Number::F64(i64::MAX as f64));
+        assert!(Number::I64(i64::MIN) == Number::F64(i64::MIN as f64));
+        assert!(Number::I128(i64::MIN as i128 - 1) < Number::F64(i64::MIN as f64));
+        assert!(Number::U128(u128::MAX) < Number::F64(u128::MAX as f64));
+        assert!(Number::U8(200) > Number::I64(100));
+        assert!(Number::F64(1.5) > Number::I32(1));
+        assert!(Number::F64(-0.5) < Number::U8(0));
+    }
+
+    #[test]
+    fn nan_is_unordered_but_last_in_total_order() {
+        let nan = Number::F64(f64::NAN);
+
+        for other in [
+            Number::F64(f64::INFINITY),
+            Number::U128(u128::MAX),
+            Number::I8(-1),
+        ] {
+            assert_eq!(nan.partial_cmp(&other), None);
+            assert_eq!(other.partial_cmp(&nan), None);
+            assert_eq!(nan.total_cmp(&other), Ordering::Greater);
+            assert_eq!(other.total_cmp(&nan), Ordering::Less);
+        }
+
+        assert_eq!(nan.total_cmp(&Number::F32(f32::NAN)), Ordering::Equal);
+    }
 }
@@ -0,0 +1,177 @@
+use crate::core::{Deserialize, DeserializeOptions, Serialize};
+use crate::internal::sys::*;
+use crate::internal::{Error, Number, Result, Value};
+use rust_decimal::Decimal;
+
+/// Parses decimal digits, failing instead of rounding when they don't fit
+fn parse_decimal(digits: &str) -> core::result::Result<Decimal, rust_decimal::Error> {
+    match digits.split_once(['e', 'E']) {
+        // `from_scientific` rounds a mantissa with too many digits
+        Some((mantissa, _)) => {
+            Decimal::from_str_exact(mantissa)?;
+            Decimal::from_scientific(digits)
+        }
+        None => Decimal::from_str_exact(digits),
+    }
+}
+
+/// Converts a float through its shortest digits, which must read back as the
+/// same float
+fn from_float<F>(number: &Number, float: F) -> Result<Decimal>
+where
+    F: core::str::FromStr + PartialEq,
+{
+    let digits = number.to_string();
+    let error = || {
+        Error::new(format!(
+            "Cannot convert {} number {} to Decimal exactly",
+            number.kind(),
+            digits
+        ))
+    };
+
+    let decimal = parse_decimal(&digits).map_err(|_| error())?;
+
+    match decimal.to_string().parse::<F>() {
+        Ok(converted) if converted == float => Ok(decimal),
+        _ => Err(error()),
+    }
+}
+
+// The digits are kept verbatim, so the scale (`1.230`) survives encoding
+impl Serialize for Decimal {
+    fn serialize(&self) -> Result<Value> {
+        Ok(Value::Number(Number::Big(self.to_string())))
+    }
+}
+
+impl Deserialize for Decimal {
+    fn deserialize(value: Value) -> Result<Self> {
+        match value {
+            Value::Number(number) if !number.is_finite() => Err(Error::new(format!(
+                "Cannot convert non-finite number {} to Decimal",
+                number
+            ))),
+            Value::Number(Number::F32(float)) => from_float(&Number::F32(float), float),
+            Value::Number(Number::F64(float)) => from_float(&Number::F64(float), float),
+            // Integers and big digits, which keep the scale they were written
+            // with
+            Value::Number(number) => {
+                let digits = number.to_string();
+
+                parse_decimal(&digits).map_err(|error| {
+                    Error::new(format!(
+                        "Cannot convert {} number {} to Decimal: {}",
+                        number.kind(),
+                        digits,
+                        error
+                    ))
+                })
+            }
+            // Accept text for compatibility with formats that quote decimals
+            Value::Text(text) => parse_decimal(&text).map_err(|error| {
+                Error::new(format!("Cannot parse \"{}\" as Decimal: {}", text, error))
+            }),
+            value => Err(Error::new(format!(
+                "Expected number or text for Decimal, found {}",
+                value.kind()
+            ))),
+        }
+    }
+
+    fn deserialize_with(value: Value, options: &DeserializeOptions) -> Result<Self> {
+        match value {
+            // Same leniency as the number types: surrounding whitespace and a
+            // leading `+`
+            Value::Text(text) if options.coerce_numbers_from_text => {
+                let trimmed = text.trim();
+
+                <Self as Deserialize>::deserialize(Value::Text(
+                    trimmed.strip_prefix('+').unwrap_or(trimmed).to_string(),
+                ))
+            }
+            value => <Self as Deserialize>::deserialize(value),
+        }
+    }
+
+    fn default_value() -> Option<Self> {
+        Some(Decimal::ZERO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(digits: &str) -> Value {
+        Value::Number(Number::Big(digits.to_string()))
+    }
+
+    fn decimal(value: Value) -> Result<Decimal> {
+        <Decimal as Deserialize>::deserialize(value)
+    }
+
+    #[test]
+    fn scale_survives_a_round_trip() {
+        for digits in [
+            "1.230",
+            "0.10",
+            "-5.000",
+            "1",
+            "79228162514264337593543950335",
+        ] {
+            let original = Decimal::from_str_exact(digits).unwrap();
+            let decoded = decimal(Serialize::serialize(&original).unwrap()).unwrap();
+
+            assert_eq!(decoded.to_string(), digits);
+            assert_eq!(decoded.scale(), original.scale());
+        }
+    }
+
+    #[test]
+    fn every_digit_that_fits_is_kept() {
+        let digits = "1.2345678901234567890123456789";
+
+        assert_eq!(decimal(big(digits)).unwrap().to_string(), digits);
+        assert_eq!(decimal(big("1.5e2")).unwrap().to_string(), "150");
+    }
+
+    #[test]
+    fn floats_convert_through_their_shortest_digits() {
+        let converted = |number| decimal(Value::Number(number)).unwrap().to_string();
+
+        assert_eq!(converted(Number::F64(1.23)), "1.23");
+        assert_eq!(converted(Number::F64(1.0)), "1.0");
+        assert_eq!(converted(Number::F32(0.1)), "0.1");
+        assert_eq!(converted(Number::I64(-42)), "-42");
+    }
+
+    #[test]
+    fn precision_loss_is_an_error() {
+        assert!(decimal(big("1.23456789012345678901234567890123")).is_err());
+        assert!(decimal(big("1.23456789012345678901234567890123e2")).is_err());
+        assert!(decimal(Value::Number(Number::F64(1e-30))).is_err());
+        assert!(decimal(Value::Number(Number::F64(1e300))).is_err());
+        assert!(decimal(Value::Number(Number::F64(f64::NAN))).is_err());
+        assert!(decimal(big("79228162514264337593543950336")).is_err());
+    }
+
+    #[test]
+    fn text_is_accepted() {
+        assert_eq!(
+            decimal(Value::Text("12.50".to_string()))
+                .unwrap()
+                .to_string(),
+            "12.50"
+        );
+        assert!(decimal(Value::Text(" 12.50".to_string())).is_err());
+
+        let options = DeserializeOptions {
+            coerce_numbers_from_text: true,
+            ..DeserializeOptions::default()
+        };
+        let coerced = Decimal::deserialize_with(Value::Text(" +12.50 ".to_string()), &options);
+
+        assert_eq!(coerced.unwrap().to_string(), "12.50");
+    }
+}
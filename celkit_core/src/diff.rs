@@ -0,0 +1,129 @@
+use crate::internal::sys::*;
+use crate::internal::Value;
+use crate::pointer::escape_segment;
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffEntry {
+    /// Location of the change, same syntax as `Value::pointer`
+    pub path: String,
+    pub kind: DiffKind,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+impl fmt::Display for DiffEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => write!(f, "~ {}: {} -> {}", path, old, new),
+            (None, Some(new)) => write!(f, "+ {}: {}", path, new),
+            (Some(old), None) => write!(f, "- {}: {}", path, old),
+            (None, None) => write!(f, "  {}", path),
+        }
+    }
+}
+
+/// Computes the structural differences needed to turn `a` into `b`.
+///
+/// Arrays and tuples are compared positionally, objects and structs by key.
+/// Numbers are compared by value, so `U8(1)` and `I64(1)` are equal.
+pub fn diff(a: &Value, b: &Value) -> Vec<DiffEntry> {
+    let mut entries = Vec::new();
+    let mut path = String::new();
+
+    diff_at(a, b, &mut path, &mut entries);
+
+    entries
+}
+
+fn diff_at(a: &Value, b: &Value, path: &mut String, entries: &mut Vec<DiffEntry>) {
+    match (a, b) {
+        (Value::Object(a_map), Value::Object(b_map))
+        | (Value::Struct(_, a_map), Value::Struct(_, b_map)) => {
+            for (key, a_value) in a_map {
+                let path_len = path.len();
+
+                path.push('/');
+                path.push_str(&escape_segment(key));
+
+                match b_map.get(key) {
+                    Some(b_value) => diff_at(a_value, b_value, path, entries),
+                    None => entries.push(DiffEntry {
+                        path: path.clone(),
+                        kind: DiffKind::Removed,
+                        old: Some(a_value.clone()),
+                        new: None,
+                    }),
+                }
+
+                path.truncate(path_len);
+            }
+
+            for (key, b_value) in b_map {
+                if a_map.contains_key(key) {
+                    continue;
+                }
+
+                entries.push(DiffEntry {
+                    path: format!("{}/{}", path, escape_segment(key)),
+                    kind: DiffKind::Added,
+                    old: None,
+                    new: Some(b_value.clone()),
+                });
+            }
+        }
+        (Value::Array(a_items), Value::Array(b_items))
+        | (Value::Tuple(a_items), Value::Tuple(b_items)) => {
+            for (i, a_item) in a_items.iter().enumerate() {
+                let path_len = path.len();
+
+                path.push('/');
+                path.push_str(&i.to_string());
+
+                match b_items.get(i) {
+                    Some(b_item) => diff_at(a_item, b_item, path, entries),
+                    None => entries.push(DiffEntry {
+                        path: path.clone(),
+                        kind: DiffKind::Removed,
+                        old: Some(a_item.clone()),
+                        new: None,
+                    }),
+                }
+
+                path.truncate(path_len);
+            }
+
+            for (i, b_item) in b_items.iter().enumerate().skip(a_items.len()) {
+                entries.push(DiffEntry {
+                    path: format!("{}/{}", path, i),
+                    kind: DiffKind::Added,
+                    old: None,
+                    new: Some(b_item.clone()),
+                });
+            }
+        }
+        _ => {
+            if a != b {
+                entries.push(DiffEntry {
+                    path: path.clone(),
+                    kind: DiffKind::Changed,
+                    old: Some(a.clone()),
+                    new: Some(b.clone()),
+                });
+            }
+        }
+    }
+}
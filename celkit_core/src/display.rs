@@ -0,0 +1,124 @@
+use crate::internal::Value;
+use crate::is_bare_identifier;
+use core::fmt::{self, Write};
+
+// Renders the same output as the mini encoder of `celkit_string`, written
+// straight into the formatter so large values aren't buffered first.
+
+fn write_text(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    f.write_char('"')?;
+
+    for c in text.chars() {
+        match c {
+            '\x08' => f.write_str("\\b")?,
+            '\x0C' => f.write_str("\\f")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            '\\' => f.write_str("\\\\")?,
+            '"' => f.write_str("\\\"")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+
+    f.write_char('"')
+}
+
+fn write_items(
+    f: &mut fmt::Formatter<'_>,
+    items: &[Value],
+    open: char,
+    close: char,
+) -> fmt::Result {
+    f.write_char(open)?;
+
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_char(',')?;
+        }
+
+        fmt::Display::fmt(item, f)?;
+    }
+
+    f.write_char(close)
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Boolean(boolean) => write!(f, "{}", boolean),
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Text(text) => write_text(f, text),
+            Value::Array(items) => write_items(f, items, '[', ']'),
+            Value::Tuple(members) => write_items(f, members, '(', ')'),
+            Value::Object(entries) => {
+                f.write_char('{')?;
+
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+
+                    write_text(f, key)?;
+                    f.write_char(':')?;
+                    fmt::Display::fmt(value, f)?;
+                }
+
+                f.write_char('}')
+            }
+            Value::Struct(_, fields) => {
+                f.write_str("@(")?;
+
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+
+                    // Quoted like the mini encoder's default field style
+                    if is_bare_identifier(name) {
+                        f.write_str(name)?;
+                    } else {
+                        write_text(f, name)?;
+                    }
+
+                    f.write_char('=')?;
+                    fmt::Display::fmt(value, f)?;
+                }
+
+                f.write_char(')')
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::internal::sys::*;
+    use crate::internal::Value;
+
+    fn fields(names: &[&str]) -> Value {
+        let fields = names
+            .iter()
+            .map(|name| (name.to_string(), Value::Boolean(true)))
+            .collect();
+
+        Value::Struct("Point".into(), fields)
+    }
+
+    #[test]
+    fn field_names_that_are_not_identifiers_are_quoted() {
+        assert_eq!(fields(&["a b"]).to_string(), "@(\"a b\"=true)");
+        assert_eq!(fields(&["1x", ""]).to_string(), "@(\"\"=true,\"1x\"=true)");
+        assert_eq!(fields(&["say\"hi"]).to_string(), "@(\"say\\\"hi\"=true)");
+    }
+
+    #[test]
+    fn identifiers_stay_bare() {
+        assert_eq!(
+            fields(&["x", "null", "_y2"]).to_string(),
+            "@(_y2=true,null=true,x=true)"
+        );
+    }
+}
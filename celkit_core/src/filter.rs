@@ -0,0 +1,158 @@
+use crate::internal::sys::*;
+use crate::internal::Value;
+use crate::pointer::escape_segment;
+use alloc::collections::btree_map;
+use alloc::vec;
+
+enum Children {
+    Items(vec::IntoIter<Value>),
+    Entries(btree_map::IntoIter<String, Value>),
+}
+
+/// A container being rebuilt from its remaining (not yet visited) children
+struct Frame {
+    value: Value,
+    children: Children,
+    next_index: usize,
+    /// Key of this container inside its parent, if the parent is keyed
+    key: Option<String>,
+    /// Length of the path before entering this container
+    path_len: usize,
+}
+
+impl Frame {
+    fn open(value: Value, key: Option<String>, path_len: usize) -> Result<Self, Value> {
+        let (value, children) = match value {
+            Value::Array(items) => (Value::Array(Vec::new()), Children::Items(items.into_iter())),
+            Value::Tuple(items) => (Value::Tuple(Vec::new()), Children::Items(items.into_iter())),
+            Value::Object(map) => (
+                Value::Object(BTreeMap::new()),
+                Children::Entries(map.into_iter()),
+            ),
+            Value::Struct(name, map) => (
+                Value::Struct(name, BTreeMap::new()),
+                Children::Entries(map.into_iter()),
+            ),
+            value => return Err(value),
+        };
+
+        Ok(Self {
+            value,
+            children,
+            next_index: 0,
+            key,
+            path_len,
+        })
+    }
+
+    fn next_child(&mut self) -> Option<(Option<String>, Value)> {
+        match &mut self.children {
+            Children::Items(items) => items.next().map(|item| (None, item)),
+            Children::Entries(entries) => entries.next().map(|(key, value)| (Some(key), value)),
+        }
+    }
+
+    fn push(&mut self, key: Option<String>, child: Value) {
+        match (&mut self.value, key) {
+            (Value::Array(items) | Value::Tuple(items), None) => items.push(child),
+            (Value::Object(map) | Value::Struct(_, map), Some(key)) => {
+                map.insert(key, child);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Tuple members are positional, so they're never removed
+    fn is_removable(&self) -> bool {
+        !self.value.is_tuple()
+    }
+}
+
+/// Rebuilds `root` without the children that fail `keep`, using an explicit
+/// stack so that deeply nested values can't overflow the call stack.
+fn filter_tree<F>(root: &mut Value, mut keep: F, remove_empty: bool)
+where
+    F: FnMut(&str, &Value) -> bool,
+{
+    let mut path = String::new();
+
+    let frame = match Frame::open(root.take(), None, 0) {
+        Ok(frame) => frame,
+        Err(scalar) => {
+            *root = scalar;
+
+            return;
+        }
+    };
+
+    let mut stack = Vec::from([frame]);
+
+    while let Some(frame) = stack.last_mut() {
+        if let Some((key, child)) = frame.next_child() {
+            let path_len = path.len();
+
+            path.push('/');
+
+            match &key {
+                Some(key) => path.push_str(&escape_segment(key)),
+                None => path.push_str(&frame.next_index.to_string()),
+            }
+
+            frame.next_index += 1;
+
+            if frame.is_removable() && !keep(&path, &child) {
+                path.truncate(path_len);
+
+                continue;
+            }
+
+            match Frame::open(child, key.clone(), path_len) {
+                Ok(child_frame) => stack.push(child_frame),
+                Err(scalar) => {
+                    frame.push(key, scalar);
+                    path.truncate(path_len);
+                }
+            }
+
+            continue;
+        }
+
+        let frame = stack.pop().unwrap();
+
+        path.truncate(frame.path_len);
+
+        match stack.last_mut() {
+            Some(parent) => {
+                if remove_empty && parent.is_removable() && frame.value.is_empty() {
+                    continue;
+                }
+
+                parent.push(frame.key, frame.value);
+            }
+            None => *root = frame.value,
+        }
+    }
+}
+
+impl Value {
+    /// Recursively removes null object entries, struct fields and array items
+    pub fn prune_nulls(&mut self) {
+        filter_tree(self, |_, value| !value.is_null(), false);
+    }
+
+    /// Like `prune_nulls`, but also removes containers left empty afterwards
+    pub fn prune_nulls_and_empty(&mut self) {
+        filter_tree(self, |_, value| !value.is_null(), true);
+    }
+
+    /// Recursively removes object entries, struct fields and array items for
+    /// which `keep` returns `false`. It receives the path of each child (same
+    /// syntax as `Value::pointer`) and its value, removed children aren't
+    /// descended into. Tuple members are visited but never removed.
+    pub fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&str, &Value) -> bool,
+    {
+        filter_tree(self, keep, false);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::internal::sys::*;
+use crate::internal::{Error, Result, Value};
+
+impl Value {
+    /// Flattens nested containers into a single map whose keys are the joined
+    /// keys and indices leading to each leaf, e.g. `{"db": {"port": 1}}` turns
+    /// into `{"db.port": 1}` with a `.` separator.
+    ///
+    /// Scalars and empty containers are leaves, a leaf root is keyed by an
+    /// empty string. Keys that contain `separator` can't be told apart from
+    /// nesting, so they're rejected, and so is an empty key holding a leaf
+    /// directly in the root since it can't be told apart from the root.
+    pub fn flatten(&self, separator: &str) -> Result<BTreeMap<String, Value>> {
+        if separator.is_empty() {
+            return Err(Error::new("Flatten separator cannot be empty"));
+        }
+
+        let mut entries = BTreeMap::new();
+        // `None` is the root, an empty key in it is still a key
+        let mut stack = Vec::from([(None, self)]);
+
+        while let Some((prefix, value)) = stack.pop() {
+            let join = |segment: &str| match &prefix {
+                None => Some(segment.to_string()),
+                Some(prefix) => Some(format!("{}{}{}", prefix, separator, segment)),
+            };
+
+            match value {
+                Value::Array(items) | Value::Tuple(items) if !items.is_empty() => {
+                    for (i, item) in items.iter().enumerate() {
+                        stack.push((join(&i.to_string()), item));
+                    }
+                }
+                Value::Object(map) | Value::Struct(_, map) if !map.is_empty() => {
+                    for (key, value) in map {
+                        if key.contains(separator) {
+                            return Err(Error::new(format!(
+                                "Key `{}` contains the flatten separator `{}`",
+                                key, separator
+                            )));
+                        }
+
+                        stack.push((join(key), value));
+                    }
+                }
+                leaf => {
+                    let key = match prefix {
+                        None => String::new(),
+                        Some(key) if key.is_empty() => {
+                            return Err(Error::new(
+                                "Empty key holding a leaf in the root conflicts with the root",
+                            ));
+                        }
+                        Some(key) => key,
+                    };
+
+                    entries.insert(key, leaf.clone());
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Rebuilds a nested value from the output of `Value::flatten`.
+    ///
+    /// Keys are always rebuilt as objects, except for ones whose keys are
+    /// exactly `0..n` which become arrays. So tuples come back as arrays and
+    /// structs come back as objects.
+    pub fn unflatten(entries: BTreeMap<String, Value>, separator: &str) -> Result<Value> {
+        if separator.is_empty() {
+            return Err(Error::new("Flatten separator cannot be empty"));
+        }
+
+        if let Some(value) = entries.get("") {
+            if entries.len() > 1 {
+                return Err(Error::new("Root value conflicts with other flattened keys"));
+            }
+
+            return Ok(value.clone());
+        }
+
+        let mut root = Value::Object(BTreeMap::new());
+
+        for (key, value) in entries {
+            let mut target = &mut root;
+
+            for segment in key.split(separator) {
+                target = match target {
+                    Value::Object(map) => map
+                        .entry(segment.to_string())
+                        .or_insert_with(|| Value::Object(BTreeMap::new())),
+                    _ => {
+                        return Err(Error::new(format!(
+                            "Flattened key `{}` conflicts with a shorter key",
+                            key
+                        )));
+                    }
+                };
+            }
+
+            if !matches!(target, Value::Object(map) if map.is_empty()) {
+                return Err(Error::new(format!(
+                    "Flattened key `{}` conflicts with a longer key",
+                    key
+                )));
+            }
+
+            *target = value;
+        }
+
+        Ok(objects_to_arrays(root))
+    }
+}
+
+/// Turns rebuilt objects with keys `0..n` back into arrays
+fn objects_to_arrays(value: Value) -> Value {
+    let Value::Object(map) = value else {
+        return value;
+    };
+
+    let mut indices = Vec::with_capacity(map.len());
+
+    for key in map.keys() {
+        match key.parse::<usize>() {
+            // Leading zeros or signs mean the key wasn't produced from an index
+            Ok(index) if index.to_string() == *key => indices.push(index),
+            _ => break,
+        }
+    }
+
+    indices.sort_unstable();
+
+    let is_sequence = !map.is_empty()
+        && indices.len() == map.len()
+        && indices.iter().enumerate().all(|(i, index)| i == *index);
+
+    if is_sequence {
+        let mut items: Vec<(usize, Value)> = map
+            .into_iter()
+            .map(|(key, value)| (key.parse().unwrap(), objects_to_arrays(value)))
+            .collect();
+
+        items.sort_by_key(|(index, _)| *index);
+
+        return Value::Array(items.into_iter().map(|(_, value)| value).collect());
+    }
+
+    Value::Object(
+        map.into_iter()
+            .map(|(key, value)| (key, objects_to_arrays(value)))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value;
+
+    fn round_trip(value: Value) -> BTreeMap<String, Value> {
+        let entries = value.flatten(".").unwrap();
+
+        assert_eq!(Value::unflatten(entries.clone(), ".").unwrap(), value);
+
+        entries
+    }
+
+    #[test]
+    fn nested_values_round_trip() {
+        let entries = round_trip(value!({"db": {"hosts": ["a", "b"], "port": 1}, "empty": {}}));
+
+        assert_eq!(entries["db.hosts.1"], value!("b"));
+        assert_eq!(entries["db.port"], value!(1));
+        assert_eq!(entries["empty"], value!({}));
+        assert_eq!(round_trip(value!(1))[""], value!(1));
+    }
+
+    #[test]
+    fn empty_keys_are_kept_apart_from_the_root() {
+        let entries = round_trip(value!({"": {"b": 1, "": 2}, "a": {"": [3]}}));
+
+        assert_eq!(entries[".b"], value!(1));
+        assert_eq!(entries["."], value!(2));
+        assert_eq!(entries["a..0"], value!(3));
+    }
+
+    #[test]
+    fn empty_key_leaf_in_the_root_is_rejected() {
+        assert!(value!({"": 1}).flatten(".").is_err());
+        assert!(value!({"": [], "a": 1}).flatten(".").is_err());
+    }
+
+    #[test]
+    fn keys_with_the_separator_are_rejected() {
+        assert!(value!({"a.b": 1}).flatten(".").is_err());
+        assert!(value!({"a.b": 1}).flatten("/").is_ok());
+    }
+}
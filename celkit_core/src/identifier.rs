@@ -0,0 +1,22 @@
+/// Words the text format reads as values, so they can't double as bare keys
+/// in every reader
+pub const KEYWORDS: &[&str] = &["null", "true", "false", "nan", "inf"];
+
+/// Whether `c` can start a bare identifier: an ASCII letter or `_`
+pub fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+/// Whether `c` can follow the first character of a bare identifier
+pub fn is_identifier_continue(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Identifier rule shared by struct names, field names and bare object keys:
+/// letters, digits and `_`, not starting with a digit. Keywords like `true`
+/// match it too, see `KEYWORDS`.
+pub fn is_bare_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars.next().is_some_and(is_identifier_start) && chars.all(is_identifier_continue)
+}
@@ -1,9 +1,52 @@
-use crate::core::{Deserialize, Serialize};
+use crate::core::{Deserialize, DeserializeOptions, Serialize};
 use crate::internal::sys::*;
-use crate::internal::{Error, Number, Result, Value};
+use crate::internal::{Error, Number, PathSegment, Result, Value};
 
 // ------------------------------- Helpers -------------------------------- //
 
+/// Parses text standing in for a number under `coerce_numbers_from_text`,
+/// which then goes through the same checks as a number value. Only decimal
+/// literals are taken, not the `nan` and `inf` words the float parsers accept.
+fn number_from_text(text: &str, type_name: &str) -> Result<Value> {
+    let trimmed = text.trim();
+    let is_literal = trimmed.bytes().any(|b| b.is_ascii_digit())
+        && trimmed
+            .bytes()
+            .all(|b| b.is_ascii_digit() || matches!(b, b'+' | b'-' | b'.' | b'e' | b'E'));
+
+    if !is_literal {
+        return Err(Error::new(format!(
+            "Expected number for {}, found text `{}`",
+            type_name, text
+        )));
+    }
+
+    // The standard parsers already take a single leading `+`
+    let number = if let Ok(n) = trimmed.parse() {
+        Number::I128(n)
+    } else if let Ok(n) = trimmed.parse() {
+        Number::U128(n)
+    } else {
+        match trimmed.parse::<f64>() {
+            Ok(n) if n.is_finite() => Number::F64(n),
+            Ok(_) => {
+                return Err(Error::new(format!(
+                    "Value {} out of range for {}",
+                    trimmed, type_name
+                )));
+            }
+            Err(_) => {
+                return Err(Error::new(format!(
+                    "Expected number for {}, found text `{}`",
+                    type_name, text
+                )));
+            }
+        }
+    };
+
+    Ok(Value::Number(number))
+}
+
 macro_rules! impl_for_integer {
     ($type:ty, $variant:ident) => {
         impl Serialize for $type {
@@ -17,126 +60,101 @@ macro_rules! impl_for_integer {
                 match value {
                     Value::Number(Number::$variant(number)) => Ok(number),
                     Value::Number(number) => {
-                        // Try to convert from other numeric types
-                        match number {
-                            Number::U8(n) => <$type>::try_from(n).map_err(|_| {
-                                Error::new(format!(
-                                    "Cannot covert u8 number {} to {}",
-                                    n,
-                                    stringify!($type)
-                                ))
-                            }),
-                            Number::I8(n) => <$type>::try_from(n).map_err(|_| {
-                                Error::new(format!(
-                                    "Cannot covert i8 number {} to {}",
-                                    n,
-                                    stringify!($type)
-                                ))
-                            }),
-                            Number::U16(n) => <$type>::try_from(n).map_err(|_| {
-                                Error::new(format!(
-                                    "Cannot covert u16 number {} to {}",
-                                    n,
-                                    stringify!($type)
-                                ))
-                            }),
-                            Number::I16(n) => <$type>::try_from(n).map_err(|_| {
-                                Error::new(format!(
-                                    "Cannot covert i16 number {} to {}",
-                                    n,
-                                    stringify!($type)
-                                ))
-                            }),
-                            Number::U32(n) => <$type>::try_from(n).map_err(|_| {
-                                Error::new(format!(
-                                    "Cannot covert u32 number {} to {}",
-                                    n,
-                                    stringify!($type)
-                                ))
-                            }),
-                            Number::I32(n) => <$type>::try_from(n).map_err(|_| {
-                                Error::new(format!(
-                                    "Cannot covert i32 number {} to {}",
-                                    n,
+                        // Integral floats are accepted, anything else must be
+                        // an exact integer that fits the target type
+                        let converted = match (number.as_i128(), number.as_u128()) {
+                            (Some(n), _) => <$type>::try_from(n).ok(),
+                            (None, Some(n)) => <$type>::try_from(n).ok(),
+                            (None, None) if number.is_float() && number.as_f64() % 1.0 != 0.0 => {
+                                return Err(Error::new(format!(
+                                    "Value {} is not an integer, expected {}",
+                                    number,
                                     stringify!($type)
-                                ))
-                            }),
-                            Number::U64(n) => <$type>::try_from(n).map_err(|_| {
-                                Error::new(format!(
-                                    "Cannot covert u64 number {} to {}",
-                                    n,
-                                    stringify!($type)
-                                ))
-                            }),
-                            Number::I64(n) => <$type>::try_from(n).map_err(|_| {
-                                Error::new(format!(
-                                    "Cannot covert i64 number {} to {}",
-                                    n,
-                                    stringify!($type)
-                                ))
-                            }),
-                            Number::U128(n) => <$type>::try_from(n).map_err(|_| {
-                                Error::new(format!(
-                                    "Cannot covert u128 number {} to {}",
-                                    n,
-                                    stringify!($type)
-                                ))
-                            }),
-                            Number::I128(n) => <$type>::try_from(n).map_err(|_| {
-                                Error::new(format!(
-                                    "Cannot covert i128 number {} to {}",
-                                    n,
-                                    stringify!($type)
-                                ))
-                            }),
-                            Number::F32(n) => {
-                                let as_int = n as i128;
-
-                                if as_int as f32 != n {
-                                    return Err(Error::new(format!(
-                                        "Cannot covert f32 number {} to {} \
-                                         without loss of precision",
-                                        n,
-                                        stringify!($type)
-                                    )));
-                                }
-
-                                <$type>::try_from(as_int).map_err(|_| {
-                                    Error::new(format!(
-                                        "Cannot covert f32 number {} to {}",
-                                        n,
-                                        stringify!($type)
-                                    ))
-                                })
+                                )));
                             }
-                            Number::F64(n) => {
-                                let as_int = n as i128;
-
-                                if as_int as f64 != n {
-                                    return Err(Error::new(format!(
-                                        "Cannot covert f64 number {} to {} \
-                                         without loss of precision",
-                                        n,
-                                        stringify!($type)
-                                    )));
-                                }
-
-                                <$type>::try_from(as_int).map_err(|_| {
-                                    Error::new(format!(
-                                        "Cannot covert f64 number {} to {}",
-                                        n,
-                                        stringify!($type)
-                                    ))
-                                })
-                            }
-                        }
+                            (None, None) => None,
+                        };
+
+                        converted.ok_or_else(|| {
+                            Error::new(format!(
+                                "Value {} out of range for {}",
+                                number,
+                                stringify!($type)
+                            ))
+                        })
+                    }
+                    value => Err(Error::new(format!(
+                        "Expected number for {}, found {}",
+                        stringify!($type),
+                        value.kind()
+                    ))),
+                }
+            }
+
+            fn deserialize_with(value: Value, options: &DeserializeOptions) -> Result<Self> {
+                match value {
+                    Value::Text(text) if options.coerce_numbers_from_text => {
+                        Self::deserialize(number_from_text(&text, stringify!($type))?)
                     }
-                    _ => Err(Error::new(format!(
-                        "Expected number for {}",
-                        stringify!($type)
+                    value => Self::deserialize(value),
+                }
+            }
+
+            fn default_value() -> Option<Self> {
+                Some(0)
+            }
+        }
+    };
+}
+
+/// Pointer-sized integers serialize through the variant matching the target's
+/// pointer width, and are range-checked against it when deserialized
+macro_rules! impl_for_pointer_sized {
+    ($type:ty, $accessor:ident) => {
+        impl Serialize for $type {
+            fn serialize(&self) -> Result<Value> {
+                Ok(Value::Number(Number::from(*self)))
+            }
+        }
+
+        impl Deserialize for $type {
+            fn deserialize(value: Value) -> Result<Self> {
+                match value {
+                    Value::Number(number) if number.is_float() && number.as_f64() % 1.0 != 0.0 => {
+                        Err(Error::new(format!(
+                            "Value {} is not an integer, expected {}",
+                            number,
+                            stringify!($type)
+                        )))
+                    }
+                    Value::Number(number) => number.$accessor().ok_or_else(|| {
+                        Error::new(format!(
+                            "Value {} out of range for {} on a {}-bit target",
+                            number,
+                            stringify!($type),
+                            <$type>::BITS
+                        ))
+                    }),
+                    value => Err(Error::new(format!(
+                        "Expected number for {}, found {}",
+                        stringify!($type),
+                        value.kind()
                     ))),
                 }
             }
+
+            fn deserialize_with(value: Value, options: &DeserializeOptions) -> Result<Self> {
+                match value {
+                    Value::Text(text) if options.coerce_numbers_from_text => {
+                        Self::deserialize(number_from_text(&text, stringify!($type))?)
+                    }
+                    value => Self::deserialize(value),
+                }
+            }
+
+            fn default_value() -> Option<Self> {
+                Some(0)
+            }
         }
     };
 }
@@ -154,8 +172,12 @@ macro_rules! impl_for_tuple {
 
         impl<$($member: Deserialize),+> Deserialize for ($($member,)+) {
             fn deserialize(value: Value) -> Result<Self> {
+                Self::deserialize_with(value, &DeserializeOptions::default())
+            }
+
+            fn deserialize_with(value: Value, options: &DeserializeOptions) -> Result<Self> {
                 match value {
-                    Value::Tuple(mut tuple) => {
+                    Value::Tuple(tuple) => {
                         const EXPECTED_LEN: usize = 0
                             $(+ { let _ = stringify!($member); 1 })*;
 
@@ -167,20 +189,51 @@ macro_rules! impl_for_tuple {
                             )));
                         }
 
-                        // Reverse the tuple members to pop in correct order
-                        tuple.reverse();
+                        let mut members = tuple.into_iter().enumerate();
+
+                        Ok(($({
+                            let (index, member) = members.next().unwrap();
 
-                        Ok(($(
-                            $member::deserialize(tuple.pop().unwrap())?
-                        ),+,))
+                            $member::deserialize_with(member, &options.at(PathSegment::Index(index)))
+                                .map_err(|error| error.at(PathSegment::Index(index)))?
+                        }),+,))
                     }
-                    _ => Err(Error::new("Expected tuple")),
+                    value => Err(Error::new(format!(
+                        "Expected tuple, found {}",
+                        value.kind()
+                    ))),
                 }
             }
+
+            fn default_value() -> Option<Self> {
+                Some(($($member::default_value()?),+,))
+            }
+
+            fn captures_source() -> bool {
+                false $(|| $member::captures_source())+
+            }
         }
     };
 }
 
+// -------------------------------- Value --------------------------------- //
+
+impl Serialize for Value {
+    fn serialize(&self) -> Result<Value> {
+        Ok(self.clone())
+    }
+}
+
+impl Deserialize for Value {
+    fn deserialize(value: Value) -> Result<Self> {
+        Ok(value)
+    }
+
+    fn default_value() -> Option<Self> {
+        Some(Value::Null)
+    }
+}
+
 // ------------------------------- Option --------------------------------- //
 
 impl<T: Serialize> Serialize for Option<T> {
@@ -194,15 +247,37 @@ impl<T: Serialize> Serialize for Option<T> {
 
 impl<T: Deserialize> Deserialize for Option<T> {
     fn deserialize(value: Value) -> Result<Self> {
+        Self::deserialize_with(value, &DeserializeOptions::default())
+    }
+
+    fn deserialize_with(value: Value, options: &DeserializeOptions) -> Result<Self> {
         match value {
             Value::Null => Ok(None),
-            value => Ok(Some(T::deserialize(value)?)),
+            value => Ok(Some(T::deserialize_with(value, options)?)),
         }
     }
+
+    fn struct_name() -> Option<&'static str> {
+        T::struct_name()
+    }
+
+    fn default_value() -> Option<Self> {
+        Some(None)
+    }
+
+    fn captures_source() -> bool {
+        T::captures_source()
+    }
 }
 
 // ------------------------------- Boolean -------------------------------- //
 
+/// Texts read as `true` under `coerce_booleans`, ignoring ASCII case
+pub const TRUE_WORDS: &[&str] = &["true", "yes", "on"];
+
+/// Texts read as `false` under `coerce_booleans`, ignoring ASCII case
+pub const FALSE_WORDS: &[&str] = &["false", "no", "off"];
+
 impl Serialize for bool {
     fn serialize(&self) -> Result<Value> {
         Ok(Value::Boolean(*self))
@@ -213,9 +288,43 @@ impl Deserialize for bool {
     fn deserialize(value: Value) -> Result<Self> {
         match value {
             Value::Boolean(bool) => Ok(bool),
-            _ => Err(Error::new("Expected bool")),
+            value => Err(Error::new(format!(
+                "Expected boolean, found {}",
+                value.kind()
+            ))),
         }
     }
+
+    fn deserialize_with(value: Value, options: &DeserializeOptions) -> Result<Self> {
+        if !options.coerce_booleans {
+            return Self::deserialize(value);
+        }
+
+        let is_word =
+            |words: &[&str], text: &str| words.iter().any(|word| word.eq_ignore_ascii_case(text));
+
+        match value {
+            Value::Number(number) => match number.as_i128() {
+                Some(1) => Ok(true),
+                Some(0) => Ok(false),
+                _ => Err(Error::new(format!(
+                    "Expected boolean, found number {}",
+                    number
+                ))),
+            },
+            Value::Text(text) if is_word(TRUE_WORDS, &text) => Ok(true),
+            Value::Text(text) if is_word(FALSE_WORDS, &text) => Ok(false),
+            Value::Text(text) => Err(Error::new(format!(
+                "Expected boolean, found text `{}`",
+                text
+            ))),
+            value => Self::deserialize(value),
+        }
+    }
+
+    fn default_value() -> Option<Self> {
+        Some(false)
+    }
 }
 
 // ------------------------------- Integer -------------------------------- //
@@ -230,6 +339,8 @@ impl_for_integer!(u64, U64);
 impl_for_integer!(i64, I64);
 impl_for_integer!(u128, U128);
 impl_for_integer!(i128, I128);
+impl_for_pointer_sized!(usize, as_usize);
+impl_for_pointer_sized!(isize, as_isize);
 
 // -------------------------------- Float --------------------------------- //
 
@@ -250,14 +361,15 @@ impl Deserialize for f32 {
         match value {
             Value::Number(Number::F32(number)) => Ok(number),
             Value::Number(Number::F64(number)) => {
-                if number < f32::MIN as f64 || number > f32::MAX as f64 {
+                // Non-finite values have an f32 counterpart of their own
+                if number.is_finite() && (number < f32::MIN as f64 || number > f32::MAX as f64) {
                     return Err(Error::new("f64 value out of range for f32"));
                 }
 
                 Ok(number as f32)
             }
             Value::Number(number) => {
-                // Convert integers to f32
+                // Convert integers (and big digits) to f32
                 let n = match number {
                     Number::U8(n) => n as f32,
                     Number::I8(n) => n as f32,
@@ -269,14 +381,31 @@ impl Deserialize for f32 {
                     Number::I64(n) => n as f32,
                     Number::U128(n) => n as f32,
                     Number::I128(n) => n as f32,
+                    Number::Big(_) => number.as_f64() as f32,
                     _ => unreachable!(),
                 };
 
                 Ok(n)
             }
-            _ => Err(Error::new("Expected number for f32")),
+            value => Err(Error::new(format!(
+                "Expected number for f32, found {}",
+                value.kind()
+            ))),
+        }
+    }
+
+    fn deserialize_with(value: Value, options: &DeserializeOptions) -> Result<Self> {
+        match value {
+            Value::Text(text) if options.coerce_numbers_from_text => {
+                Self::deserialize(number_from_text(&text, "f32")?)
+            }
+            value => Self::deserialize(value),
         }
     }
+
+    fn default_value() -> Option<Self> {
+        Some(0.0)
+    }
 }
 
 impl Deserialize for f64 {
@@ -285,7 +414,7 @@ impl Deserialize for f64 {
             Value::Number(Number::F64(number)) => Ok(number),
             Value::Number(Number::F32(number)) => Ok(number as f64),
             Value::Number(number) => {
-                // Convert integers to f64
+                // Convert integers (and big digits) to f64
                 let n = match number {
                     Number::U8(n) => n as f64,
                     Number::I8(n) => n as f64,
@@ -297,14 +426,31 @@ impl Deserialize for f64 {
                     Number::I64(n) => n as f64,
                     Number::U128(n) => n as f64,
                     Number::I128(n) => n as f64,
+                    Number::Big(_) => number.as_f64(),
                     _ => unreachable!(),
                 };
 
                 Ok(n)
             }
-            _ => Err(Error::new("Expected number for f64")),
+            value => Err(Error::new(format!(
+                "Expected number for f64, found {}",
+                value.kind()
+            ))),
+        }
+    }
+
+    fn deserialize_with(value: Value, options: &DeserializeOptions) -> Result<Self> {
+        match value {
+            Value::Text(text) if options.coerce_numbers_from_text => {
+                Self::deserialize(number_from_text(&text, "f64")?)
+            }
+            value => Self::deserialize(value),
         }
     }
+
+    fn default_value() -> Option<Self> {
+        Some(0.0)
+    }
 }
 
 // ------------------------------- String --------------------------------- //
@@ -319,9 +465,13 @@ impl Deserialize for String {
     fn deserialize(value: Value) -> Result<Self> {
         match value {
             Value::Text(string) => Ok(string),
-            _ => Err(Error::new("Expected string")),
+            value => Err(Error::new(format!("Expected text, found {}", value.kind()))),
         }
     }
+
+    fn default_value() -> Option<Self> {
+        Some(String::new())
+    }
 }
 
 // --------------------------------- Vec ---------------------------------- //
@@ -340,19 +490,42 @@ impl<T: Serialize> Serialize for Vec<T> {
 
 impl<T: Deserialize> Deserialize for Vec<T> {
     fn deserialize(value: Value) -> Result<Self> {
+        Self::deserialize_with(value, &DeserializeOptions::default())
+    }
+
+    fn deserialize_with(value: Value, options: &DeserializeOptions) -> Result<Self> {
         match value {
             Value::Array(array) => {
                 let mut vec = Vec::with_capacity(array.len());
 
-                for value in array {
-                    vec.push(T::deserialize(value)?);
+                for (index, value) in array.into_iter().enumerate() {
+                    let options = options.at(PathSegment::Index(index));
+                    let item = T::deserialize_with(value, &options).map_err(|mut error| {
+                        error.message =
+                            format!("Invalid item at index {}: {}", index, error.message);
+
+                        error.at(PathSegment::Index(index))
+                    })?;
+
+                    vec.push(item);
                 }
 
                 Ok(vec)
             }
-            _ => Err(Error::new("Expected array")),
+            value => Err(Error::new(format!(
+                "Expected array, found {}",
+                value.kind()
+            ))),
         }
     }
+
+    fn default_value() -> Option<Self> {
+        Some(Vec::new())
+    }
+
+    fn captures_source() -> bool {
+        T::captures_source()
+    }
 }
 
 // -------------------------------- Tuple --------------------------------- //
@@ -388,18 +561,83 @@ impl<K: Into<String> + Clone, V: Serialize> Serialize for std::collections::Hash
 #[cfg(feature = "std")]
 impl<V: Deserialize> Deserialize for std::collections::HashMap<String, V> {
     fn deserialize(value: Value) -> Result<Self> {
-        match value {
-            Value::Object(object) => {
-                let mut map = std::collections::HashMap::with_capacity(object.len());
+        Self::deserialize_with(value, &DeserializeOptions::default())
+    }
 
-                for (key, value) in object {
-                    map.insert(key, V::deserialize(value)?);
-                }
+    fn deserialize_with(value: Value, options: &DeserializeOptions) -> Result<Self> {
+        let object = into_object(value, options)?;
+        let mut map = std::collections::HashMap::with_capacity(object.len());
 
-                Ok(map)
-            }
-            _ => Err(Error::new("Expected object")),
+        for (key, value) in object {
+            let value = V::deserialize_with(value, &options.at(PathSegment::Key(&key)))
+                .map_err(|error| error.at(PathSegment::Key(&key)))?;
+
+            map.insert(key, value);
+        }
+
+        Ok(map)
+    }
+
+    fn default_value() -> Option<Self> {
+        Some(std::collections::HashMap::new())
+    }
+
+    fn captures_source() -> bool {
+        V::captures_source()
+    }
+}
+
+// ------------------------------- BTreeMap ------------------------------- //
+
+impl<K: Into<String> + Clone, V: Serialize> Serialize for BTreeMap<K, V> {
+    fn serialize(&self) -> Result<Value> {
+        let mut values = BTreeMap::new();
+
+        for (key, value) in self {
+            values.insert(key.clone().into(), value.serialize()?);
+        }
+
+        Ok(Value::Object(values))
+    }
+}
+
+impl<V: Deserialize> Deserialize for BTreeMap<String, V> {
+    fn deserialize(value: Value) -> Result<Self> {
+        Self::deserialize_with(value, &DeserializeOptions::default())
+    }
+
+    fn deserialize_with(value: Value, options: &DeserializeOptions) -> Result<Self> {
+        let object = into_object(value, options)?;
+        let mut map = BTreeMap::new();
+
+        for (key, value) in object {
+            let value = V::deserialize_with(value, &options.at(PathSegment::Key(&key)))
+                .map_err(|error| error.at(PathSegment::Key(&key)))?;
+
+            map.insert(key, value);
         }
+
+        Ok(map)
+    }
+
+    fn default_value() -> Option<Self> {
+        Some(BTreeMap::new())
+    }
+
+    fn captures_source() -> bool {
+        V::captures_source()
+    }
+}
+
+/// Entries of an object, or of a struct under `coerce_struct_object`
+fn into_object(value: Value, options: &DeserializeOptions) -> Result<BTreeMap<String, Value>> {
+    match value {
+        Value::Object(object) => Ok(object),
+        Value::Struct(_, fields) if options.coerce_struct_object => Ok(fields),
+        value => Err(Error::new(format!(
+            "Expected object, found {}",
+            value.kind()
+        ))),
     }
 }
 
@@ -430,7 +668,7 @@ macro_rules! impl_for_struct {
                 $(
                     map.insert(
                         stringify!($field_name).to_string(),
-                        self.$field_name.serialize()?
+                        $crate::Serialize::serialize(&self.$field_name)?
                     );
                 )*
 
@@ -440,33 +678,287 @@ macro_rules! impl_for_struct {
 
         impl $crate::Deserialize for $name {
             fn deserialize(value: $crate::internal::Value) -> $crate::internal::Result<Self> {
-                match value {
-                    $crate::internal::Value::Struct(_, fields) => {
-                        $(
-                            let $field_name =
-                                match fields.get(stringify!($field_name)) {
-                                    Some(value) =>
-                                        <$field_type>::deserialize(value.clone())?,
-                                    None => return Err(
-                                        $crate::internal::Error::new(format!(
-                                            "Missing `{}` field",
-                                            stringify!($field_name)
-                                        ))
-                                    ),
-                            };
-                        )*
-
-                        Ok($name {
-                            $(
-                                $field_name
-                            ),*
-                        })
+                Self::deserialize_with(value, &$crate::DeserializeOptions::default())
+            }
+
+            fn deserialize_with(
+                value: $crate::internal::Value,
+                options: &$crate::DeserializeOptions,
+            ) -> $crate::internal::Result<Self> {
+                let mut fields = match value {
+                    $crate::internal::Value::Struct(name, fields) => {
+                        $crate::check_struct_name(&name, stringify!($name), options)?;
+
+                        fields
                     }
-                    _ => Err($crate::internal::Error::new(
-                        format!("Expected struct for {}", stringify!($name))
-                    ))
-                }
+                    $crate::internal::Value::Object(fields) if options.coerce_struct_object => {
+                        fields
+                    }
+                    value => return Err($crate::internal::Error::new(format!(
+                        "Expected struct for {}, found {}",
+                        stringify!($name),
+                        value.kind()
+                    ))),
+                };
+
+                $(
+                    let $field_name = $crate::struct_field::<$field_type>(
+                        &mut fields,
+                        stringify!($field_name),
+                        stringify!($name),
+                        options,
+                    )?;
+                )*
+
+                $crate::struct_extras(
+                    fields,
+                    &[$(stringify!($field_name)),*],
+                    stringify!($name),
+                    options,
+                )?;
+
+                Ok($name {
+                    $(
+                        $field_name
+                    ),*
+                })
+            }
+
+            fn struct_name() -> Option<&'static str> {
+                Some(stringify!($name))
+            }
+
+            fn captures_source() -> bool {
+                false $(|| <$field_type as $crate::Deserialize>::captures_source())*
+            }
+
+            // Defaults only when every field does
+            fn default_value() -> Option<Self> {
+                Some($name {
+                    $(
+                        $field_name: <$field_type as $crate::Deserialize>::default_value()?
+                    ),*
+                })
             }
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coerce<T: Deserialize>(text: &str) -> Result<T> {
+        let options = DeserializeOptions {
+            coerce_numbers_from_text: true,
+            ..DeserializeOptions::default()
+        };
+
+        T::deserialize_with(Value::Text(text.into()), &options)
+    }
+
+    #[test]
+    fn numbers_coerce_from_text() {
+        assert_eq!(coerce::<u16>(" 8080 ").unwrap(), 8080);
+        assert_eq!(coerce::<i8>("+12").unwrap(), 12);
+        assert_eq!(coerce::<i64>("-9223372036854775808").unwrap(), i64::MIN);
+        assert_eq!(
+            coerce::<u128>("340282366920938463463374607431768211455").unwrap(),
+            u128::MAX
+        );
+        assert_eq!(coerce::<u32>("1e3").unwrap(), 1000);
+        assert_eq!(coerce::<f64>("-1.5e-3").unwrap(), -0.0015);
+        assert_eq!(coerce::<f32>("12").unwrap(), 12.0);
+    }
+
+    #[test]
+    fn out_of_range_text_fails_like_a_number() {
+        assert_eq!(
+            coerce::<u8>("300").unwrap_err().message,
+            "Value 300 out of range for u8"
+        );
+        assert_eq!(
+            coerce::<u8>("-1").unwrap_err().message,
+            "Value -1 out of range for u8"
+        );
+        assert_eq!(
+            coerce::<u8>("1.5").unwrap_err().message,
+            "Value 1.5 is not an integer, expected u8"
+        );
+        assert_eq!(
+            coerce::<f64>("1e999").unwrap_err().message,
+            "Value 1e999 out of range for f64"
+        );
+        assert!(coerce::<f32>("1e39").is_err());
+    }
+
+    #[test]
+    fn non_finite_words_are_rejected() {
+        for text in ["nan", "NaN", "inf", "-inf", "+Infinity", "infinity"] {
+            assert_eq!(
+                coerce::<f64>(text).unwrap_err().message,
+                format!("Expected number for f64, found text `{}`", text)
+            );
+            assert!(coerce::<f32>(text).is_err());
+        }
+    }
+
+    #[test]
+    fn malformed_text_is_rejected() {
+        for text in ["", " ", "abc", "1_000", "1,000", "++1", "0x10", "1e", "-"] {
+            assert!(coerce::<i32>(text).is_err(), "{}", text);
+            assert!(coerce::<f64>(text).is_err(), "{}", text);
+        }
+    }
+
+    fn boolean(value: Value) -> Result<bool> {
+        let options = DeserializeOptions {
+            coerce_booleans: true,
+            ..DeserializeOptions::default()
+        };
+
+        bool::deserialize_with(value, &options)
+    }
+
+    #[test]
+    fn every_boolean_spelling_is_accepted() {
+        let spellings = [
+            ("true", true),
+            ("TRUE", true),
+            ("yes", true),
+            ("Yes", true),
+            ("on", true),
+            ("ON", true),
+            ("false", false),
+            ("False", false),
+            ("no", false),
+            ("NO", false),
+            ("off", false),
+            ("oFF", false),
+        ];
+
+        for (text, expected) in spellings {
+            assert_eq!(
+                boolean(Value::Text(text.into())).unwrap(),
+                expected,
+                "{}",
+                text
+            );
+        }
+
+        assert!(boolean(Value::Number(Number::U8(1))).unwrap());
+        assert!(!boolean(Value::Number(Number::I64(0))).unwrap());
+        assert!(!boolean(Value::Number(Number::F64(0.0))).unwrap());
+        assert!(boolean(Value::Boolean(true)).unwrap());
+    }
+
+    #[test]
+    fn other_booleans_are_rejected() {
+        assert_eq!(
+            boolean(Value::Text("2".into())).unwrap_err().message,
+            "Expected boolean, found text `2`"
+        );
+        assert_eq!(
+            boolean(Value::Text("n".into())).unwrap_err().message,
+            "Expected boolean, found text `n`"
+        );
+        assert_eq!(
+            boolean(Value::Number(Number::I32(2))).unwrap_err().message,
+            "Expected boolean, found number 2"
+        );
+        assert!(boolean(Value::Text(" yes".into())).is_err());
+        assert!(boolean(Value::Number(Number::F64(0.5))).is_err());
+        assert!(boolean(Value::Null).is_err());
+    }
+
+    #[test]
+    fn booleans_are_strict_by_default() {
+        let options = DeserializeOptions::default();
+
+        assert!(bool::deserialize_with(Value::Text("yes".into()), &options).is_err());
+        assert!(bool::deserialize_with(Value::Number(Number::U8(1)), &options).is_err());
+    }
+
+    fn same_bits_f32(number: f32) {
+        let back = f32::deserialize(number.serialize().unwrap()).unwrap();
+
+        assert_eq!(back.to_bits(), number.to_bits());
+
+        let widened = f32::deserialize(Value::Number(Number::F64(number as f64))).unwrap();
+
+        assert_eq!(widened.to_bits(), number.to_bits());
+        assert_eq!(
+            f64::deserialize(number.serialize().unwrap())
+                .unwrap()
+                .to_bits(),
+            (number as f64).to_bits()
+        );
+    }
+
+    #[test]
+    fn non_finite_floats_round_trip() {
+        for number in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -0.0] {
+            same_bits_f32(number);
+        }
+
+        for number in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, -0.0] {
+            let back = f64::deserialize(number.serialize().unwrap()).unwrap();
+
+            assert_eq!(back.to_bits(), number.to_bits());
+        }
+
+        assert!(f32::deserialize(Value::Number(Number::F64(1e39))).is_err());
+        assert!(f32::deserialize(Value::Number(Number::F64(-1e39))).is_err());
+    }
+
+    #[test]
+    fn integral_floats_are_accepted() {
+        assert_eq!(u8::deserialize(Value::Number(Number::F64(3.0))).unwrap(), 3);
+        assert_eq!(
+            i16::deserialize(Value::Number(Number::F64(-0.0))).unwrap(),
+            0
+        );
+        assert_eq!(
+            u8::deserialize(Value::Number(Number::F64(3.5)))
+                .unwrap_err()
+                .message,
+            "Value 3.5 is not an integer, expected u8"
+        );
+        assert_eq!(
+            u16::deserialize(Value::Number(Number::U32(65536)))
+                .unwrap_err()
+                .message,
+            "Value 65536 out of range for u16"
+        );
+        assert!(u8::deserialize(Value::Number(Number::F64(256.0))).is_err());
+    }
+
+    #[test]
+    fn errors_carry_the_path_of_the_value() {
+        let mut ports = BTreeMap::new();
+
+        ports.insert(
+            String::from("a/b"),
+            Value::Tuple(Vec::from([Value::from(1), Value::from(65536)])),
+        );
+
+        let value = Value::Array(Vec::from([
+            Value::Object(BTreeMap::new()),
+            Value::Object(ports),
+        ]));
+        let error = Vec::<BTreeMap<String, (u16, u16)>>::deserialize(value).unwrap_err();
+
+        assert_eq!(error.path(), Some("/1/a~1b/1"));
+        assert_eq!(
+            error.message,
+            "Invalid item at index 1: Value 65536 out of range for u16"
+        );
+        assert_eq!(
+            error.to_string(),
+            "Error at `/1/a~1b/1`: Invalid item at index 1: Value 65536 out of range for u16"
+        );
+
+        // Values at the root have no path
+        assert_eq!(u8::deserialize(Value::from(300)).unwrap_err().path(), None);
+    }
+}
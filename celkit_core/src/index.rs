@@ -0,0 +1,71 @@
+use crate::internal::sys::*;
+use crate::internal::Value;
+
+pub trait ValueIndex {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value>;
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value>;
+}
+
+impl ValueIndex for usize {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        match value {
+            Value::Array(items) | Value::Tuple(items) => items.get(*self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        match value {
+            Value::Array(items) | Value::Tuple(items) => items.get_mut(*self),
+            _ => None,
+        }
+    }
+}
+
+impl ValueIndex for str {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        match value {
+            Value::Object(map) | Value::Struct(_, map) => map.get(self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        match value {
+            Value::Object(map) | Value::Struct(_, map) => map.get_mut(self),
+            _ => None,
+        }
+    }
+}
+
+impl ValueIndex for String {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        self.as_str().index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        self.as_str().index_into_mut(value)
+    }
+}
+
+impl<T: ?Sized + ValueIndex> ValueIndex for &T {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        (**self).index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        (**self).index_into_mut(value)
+    }
+}
+
+impl Value {
+    /// Looks up a key of an object/struct or a position of an array/tuple
+    pub fn get<I: ValueIndex>(&self, index: I) -> Option<&Value> {
+        index.index_into(self)
+    }
+
+    pub fn get_mut<I: ValueIndex>(&mut self, index: I) -> Option<&mut Value> {
+        index.index_into_mut(self)
+    }
+}
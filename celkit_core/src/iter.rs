@@ -0,0 +1,82 @@
+use crate::internal::sys::*;
+use crate::internal::Value;
+
+impl Value {
+    /// Elements of an array or tuple, nothing for other kinds
+    pub fn items(&self) -> impl Iterator<Item = &Value> {
+        self.into_iter()
+    }
+
+    pub fn items_mut(&mut self) -> impl Iterator<Item = &mut Value> {
+        let items = match self {
+            Value::Array(items) | Value::Tuple(items) => Some(items.iter_mut()),
+            _ => None,
+        };
+
+        items.into_iter().flatten()
+    }
+
+    /// Entries of an object or fields of a struct, nothing for other kinds
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &Value)> {
+        let entries = match self {
+            Value::Object(map) | Value::Struct(_, map) => Some(map.iter()),
+            _ => None,
+        };
+
+        entries
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| (key.as_str(), value))
+    }
+
+    pub fn entries_mut(&mut self) -> impl Iterator<Item = (&str, &mut Value)> {
+        let entries = match self {
+            Value::Object(map) | Value::Struct(_, map) => Some(map.iter_mut()),
+            _ => None,
+        };
+
+        entries
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| (key.as_str(), value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.entries().map(|(_, value)| value)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Value> {
+        self.entries_mut().map(|(_, value)| value)
+    }
+}
+
+/// Drains the elements of an array or tuple, other kinds yield nothing
+impl IntoIterator for Value {
+    type Item = Value;
+    type IntoIter = alloc::vec::IntoIter<Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            Value::Array(items) | Value::Tuple(items) => items.into_iter(),
+            _ => Vec::new().into_iter(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Value {
+    type Item = &'a Value;
+    type IntoIter = core::iter::Flatten<core::option::IntoIter<core::slice::Iter<'a, Value>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let items = match self {
+            Value::Array(items) | Value::Tuple(items) => Some(items.iter()),
+            _ => None,
+        };
+
+        items.into_iter().flatten()
+    }
+}
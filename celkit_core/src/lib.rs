@@ -2,15 +2,47 @@
 
 extern crate alloc;
 
+mod convert;
 mod core;
+#[cfg(feature = "decimal")]
+mod decimal;
+mod diff;
+mod display;
+mod filter;
+mod flatten;
+mod identifier;
 mod impls;
+mod index;
+mod iter;
+mod macros;
+mod merge;
+mod pointer;
+mod redact;
+mod sort;
+mod stats;
+mod walk;
 
-pub use core::{Deserialize, Serialize};
+pub use core::{
+    check_struct_name, field_or_default, from_value, from_value_with_options, struct_extras,
+    struct_field, to_value, Deserialize, DeserializeOptions, MissingFields, Serialize, Source,
+    UnknownFields,
+};
+pub use diff::diff;
+pub use identifier::{is_bare_identifier, is_identifier_continue, is_identifier_start, KEYWORDS};
+pub use impls::{FALSE_WORDS, TRUE_WORDS};
+pub use redact::{is_sensitive_key, SENSITIVE_KEYS};
 
 pub mod internal {
-    pub use crate::core::{Error, Number, Result, Value};
+    pub use crate::core::{Error, Number, NumberKind, Result, Value, ValueKind};
+    pub use crate::diff::{DiffEntry, DiffKind};
+    pub use crate::index::ValueIndex;
+    pub use crate::merge::{ArrayMerge, MergeStrategy, MismatchMerge};
+    pub use crate::pointer::split_path;
+    pub use crate::stats::ValueStats;
+    pub use crate::walk::{PathSegment, ValuePath, WalkControl};
 
     pub mod sys {
+        pub use alloc::boxed::Box;
         pub use alloc::collections::BTreeMap;
         pub use alloc::format;
         pub use alloc::string::{String, ToString};
@@ -0,0 +1,289 @@
+/// Builds a `Value` using celkit-like syntax.
+///
+/// Arrays are written as `[...]`, tuples as `(...)`, objects as `{"key": ...}`
+/// and structs as `@(field = ...)` or `@Name(field = ...)`. Anything else is
+/// treated as an expression and converted with `Value::from`, so a value that
+/// starts with a parenthesis is always read as a tuple.
+#[macro_export]
+macro_rules! value {
+    // ---------------------- Array and tuple items ----------------------- //
+
+    (@__items [$($items:expr,)*]) => {
+        $crate::internal::sys::Vec::from([$($items),*])
+    };
+    (@__items [$($items:expr,)*] null $($rest:tt)*) => {
+        $crate::value!(@__items_next [$($items,)* $crate::internal::Value::Null,] $($rest)*)
+    };
+    (@__items [$($items:expr,)*] [$($array:tt)*] $($rest:tt)*) => {
+        $crate::value!(@__items_next [$($items,)* $crate::value!([$($array)*]),] $($rest)*)
+    };
+    (@__items [$($items:expr,)*] {$($object:tt)*} $($rest:tt)*) => {
+        $crate::value!(@__items_next [$($items,)* $crate::value!({$($object)*}),] $($rest)*)
+    };
+    (@__items [$($items:expr,)*] @($($fields:tt)*) $($rest:tt)*) => {
+        $crate::value!(@__items_next [$($items,)* $crate::value!(@($($fields)*)),] $($rest)*)
+    };
+    (@__items [$($items:expr,)*] @$name:ident($($fields:tt)*) $($rest:tt)*) => {
+        $crate::value!(
+            @__items_next [$($items,)* $crate::value!(@$name($($fields)*)),] $($rest)*
+        )
+    };
+    (@__items [$($items:expr,)*] ($($members:tt)*) $($rest:tt)*) => {
+        $crate::value!(@__items_next [$($items,)* $crate::value!(($($members)*)),] $($rest)*)
+    };
+    (@__items [$($items:expr,)*] $next:expr, $($rest:tt)*) => {
+        $crate::value!(@__items [$($items,)* $crate::value!($next),] $($rest)*)
+    };
+    (@__items [$($items:expr,)*] $last:expr) => {
+        $crate::value!(@__items [$($items,)* $crate::value!($last),])
+    };
+    (@__items_next [$($items:expr,)*]) => {
+        $crate::value!(@__items [$($items,)*])
+    };
+    (@__items_next [$($items:expr,)*] , $($rest:tt)*) => {
+        $crate::value!(@__items [$($items,)*] $($rest)*)
+    };
+
+    // -------------------------- Object entries -------------------------- //
+
+    (@__entries $map:ident) => {};
+    (@__entries $map:ident $key:tt: null $($rest:tt)*) => {
+        $crate::value!(@__insert $map $key, $crate::internal::Value::Null);
+        $crate::value!(@__entries_next $map $($rest)*);
+    };
+    (@__entries $map:ident $key:tt: [$($array:tt)*] $($rest:tt)*) => {
+        $crate::value!(@__insert $map $key, $crate::value!([$($array)*]));
+        $crate::value!(@__entries_next $map $($rest)*);
+    };
+    (@__entries $map:ident $key:tt: {$($object:tt)*} $($rest:tt)*) => {
+        $crate::value!(@__insert $map $key, $crate::value!({$($object)*}));
+        $crate::value!(@__entries_next $map $($rest)*);
+    };
+    (@__entries $map:ident $key:tt: @($($fields:tt)*) $($rest:tt)*) => {
+        $crate::value!(@__insert $map $key, $crate::value!(@($($fields)*)));
+        $crate::value!(@__entries_next $map $($rest)*);
+    };
+    (@__entries $map:ident $key:tt: @$name:ident($($fields:tt)*) $($rest:tt)*) => {
+        $crate::value!(@__insert $map $key, $crate::value!(@$name($($fields)*)));
+        $crate::value!(@__entries_next $map $($rest)*);
+    };
+    (@__entries $map:ident $key:tt: ($($members:tt)*) $($rest:tt)*) => {
+        $crate::value!(@__insert $map $key, $crate::value!(($($members)*)));
+        $crate::value!(@__entries_next $map $($rest)*);
+    };
+    (@__entries $map:ident $key:tt: $value:expr, $($rest:tt)*) => {
+        $crate::value!(@__insert $map $key, $crate::value!($value));
+        $crate::value!(@__entries $map $($rest)*);
+    };
+    (@__entries $map:ident $key:tt: $value:expr) => {
+        $crate::value!(@__insert $map $key, $crate::value!($value));
+    };
+    (@__entries_next $map:ident) => {};
+    (@__entries_next $map:ident , $($rest:tt)*) => {
+        $crate::value!(@__entries $map $($rest)*);
+    };
+    (@__insert $map:ident $key:expr, $value:expr) => {
+        $map.insert($crate::internal::sys::ToString::to_string(&$key), $value);
+    };
+
+    // --------------------------- Struct fields -------------------------- //
+
+    (@__fields $map:ident) => {};
+    (@__fields $map:ident $field:ident = null $($rest:tt)*) => {
+        $crate::value!(@__insert $map stringify!($field), $crate::internal::Value::Null);
+        $crate::value!(@__fields_next $map $($rest)*);
+    };
+    (@__fields $map:ident $field:ident = [$($array:tt)*] $($rest:tt)*) => {
+        $crate::value!(@__insert $map stringify!($field), $crate::value!([$($array)*]));
+        $crate::value!(@__fields_next $map $($rest)*);
+    };
+    (@__fields $map:ident $field:ident = {$($object:tt)*} $($rest:tt)*) => {
+        $crate::value!(@__insert $map stringify!($field), $crate::value!({$($object)*}));
+        $crate::value!(@__fields_next $map $($rest)*);
+    };
+    (@__fields $map:ident $field:ident = @($($fields:tt)*) $($rest:tt)*) => {
+        $crate::value!(@__insert $map stringify!($field), $crate::value!(@($($fields)*)));
+        $crate::value!(@__fields_next $map $($rest)*);
+    };
+    (@__fields $map:ident $field:ident = @$name:ident($($fields:tt)*) $($rest:tt)*) => {
+        $crate::value!(
+            @__insert $map stringify!($field), $crate::value!(@$name($($fields)*))
+        );
+        $crate::value!(@__fields_next $map $($rest)*);
+    };
+    (@__fields $map:ident $field:ident = ($($members:tt)*) $($rest:tt)*) => {
+        $crate::value!(@__insert $map stringify!($field), $crate::value!(($($members)*)));
+        $crate::value!(@__fields_next $map $($rest)*);
+    };
+    (@__fields $map:ident $field:ident = $value:expr, $($rest:tt)*) => {
+        $crate::value!(@__insert $map stringify!($field), $crate::value!($value));
+        $crate::value!(@__fields $map $($rest)*);
+    };
+    (@__fields $map:ident $field:ident = $value:expr) => {
+        $crate::value!(@__insert $map stringify!($field), $crate::value!($value));
+    };
+    (@__fields_next $map:ident) => {};
+    (@__fields_next $map:ident , $($rest:tt)*) => {
+        $crate::value!(@__fields $map $($rest)*);
+    };
+
+    // ------------------------------ Values ------------------------------ //
+
+    (null) => {
+        $crate::internal::Value::Null
+    };
+    ([$($items:tt)*]) => {
+        $crate::internal::Value::Array($crate::value!(@__items [] $($items)*))
+    };
+    (($($members:tt)*)) => {
+        $crate::internal::Value::Tuple($crate::value!(@__items [] $($members)*))
+    };
+    ({$($entries:tt)*}) => {{
+        #[allow(unused_mut)]
+        let mut map = $crate::internal::sys::BTreeMap::new();
+
+        $crate::value!(@__entries map $($entries)*);
+
+        $crate::internal::Value::Object(map)
+    }};
+    (@($($fields:tt)*)) => {{
+        #[allow(unused_mut)]
+        let mut map = $crate::internal::sys::BTreeMap::new();
+
+        $crate::value!(@__fields map $($fields)*);
+
+        $crate::internal::Value::Struct($crate::internal::sys::String::new(), map)
+    }};
+    (@$name:ident($($fields:tt)*)) => {{
+        #[allow(unused_mut)]
+        let mut map = $crate::internal::sys::BTreeMap::new();
+
+        $crate::value!(@__fields map $($fields)*);
+
+        $crate::internal::Value::Struct(
+            $crate::internal::sys::ToString::to_string(stringify!($name)),
+            map,
+        )
+    }};
+    ($other:expr) => {
+        $crate::internal::Value::from($other)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::internal::sys::*;
+    use crate::internal::{Number, Value};
+
+    fn object(entries: &[(&str, Value)]) -> BTreeMap<String, Value> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn builds_the_same_tree_by_hand() {
+        let value = value!({
+            "host": "localhost",
+            "ports": [80, 443],
+            "tls": null,
+            "limits": (1.5, -2),
+            "client": @Client(retries = 3, tags = [], fallback = @(retries = 1)),
+        });
+        let expected = Value::Object(object(&[
+            ("host", Value::Text("localhost".to_string())),
+            (
+                "ports",
+                Value::Array(Vec::from([
+                    Value::Number(Number::I32(80)),
+                    Value::Number(Number::I32(443)),
+                ])),
+            ),
+            ("tls", Value::Null),
+            (
+                "limits",
+                Value::Tuple(Vec::from([
+                    Value::Number(Number::F64(1.5)),
+                    Value::Number(Number::I32(-2)),
+                ])),
+            ),
+            (
+                "client",
+                Value::Struct(
+                    "Client".to_string(),
+                    object(&[
+                        ("retries", Value::Number(Number::I32(3))),
+                        ("tags", Value::Array(Vec::new())),
+                        (
+                            "fallback",
+                            Value::Struct(
+                                String::new(),
+                                object(&[("retries", Value::Number(Number::I32(1)))]),
+                            ),
+                        ),
+                    ]),
+                ),
+            ),
+        ]));
+
+        assert_eq!(value, expected);
+
+        // Struct names aren't part of equality, so they're checked apart
+        let Value::Object(entries) = &value else {
+            unreachable!();
+        };
+
+        assert!(matches!(&entries["client"], Value::Struct(name, fields)
+            if name == "Client"
+                && matches!(&fields["fallback"], Value::Struct(name, _) if name.is_empty())));
+    }
+
+    #[test]
+    fn expressions_are_interpolated() {
+        let host = "example.com";
+        let port = 8080u16;
+        let tags = Vec::from(["a", "b"]);
+
+        assert_eq!(
+            value!(@(host = host, port = port + 1, tags = tags.clone(), none = None::<u8>)),
+            Value::Struct(
+                String::new(),
+                object(&[
+                    ("host", Value::from(host)),
+                    ("port", Value::Number(Number::U16(8081))),
+                    ("tags", Value::from(tags)),
+                    ("none", Value::Null),
+                ]),
+            )
+        );
+
+        let key = "dynamic";
+
+        assert_eq!(
+            value!({key: [port, -1, true], "ok": port > 80}),
+            Value::Object(object(&[
+                ("dynamic", value!([8080u16, -1, true])),
+                ("ok", Value::Boolean(true)),
+            ]))
+        );
+    }
+
+    #[test]
+    fn nesting_has_no_fixed_depth() {
+        let value = value!([[[[[[[[[[[[[[[[1]]]]]]]]]]]]]]]]);
+        let mut depth = 0;
+        let mut current = &value;
+
+        while let Value::Array(items) = current {
+            depth += 1;
+            current = &items[0];
+        }
+
+        assert_eq!((depth, current), (16, &Value::from(1)));
+        assert_eq!(value!([]), Value::Array(Vec::new()));
+        assert_eq!(value!(()), Value::Tuple(Vec::new()));
+        assert_eq!(value!({}), Value::Object(BTreeMap::new()));
+        assert_eq!(value!([1, 2,]), value!([1, 2]));
+    }
+}
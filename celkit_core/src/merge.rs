@@ -0,0 +1,301 @@
+use crate::internal::sys::*;
+use crate::internal::{Error, Result, Value};
+use crate::pointer::escape_segment;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArrayMerge {
+    #[default]
+    Replace,
+    Concat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MismatchMerge {
+    #[default]
+    Replace,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeStrategy {
+    /// What happens when both sides are arrays
+    pub arrays: ArrayMerge,
+    /// What happens when both sides are non-null values of incompatible kinds
+    pub mismatch: MismatchMerge,
+}
+
+impl MergeStrategy {
+    pub fn arrays(mut self, arrays: ArrayMerge) -> Self {
+        self.arrays = arrays;
+
+        self
+    }
+
+    pub fn mismatch(mut self, mismatch: MismatchMerge) -> Self {
+        self.mismatch = mismatch;
+
+        self
+    }
+}
+
+impl Value {
+    /// Deep merges `other` into `self` using the default strategy
+    pub fn merge(&mut self, other: Value) {
+        // The default strategy never errors, it always replaces on mismatch
+        let _ = self.merge_with(other, MergeStrategy::default());
+    }
+
+    /// Deep merges `other` into `self`, the right-hand side wins on conflicts.
+    ///
+    /// Objects and structs are both keyed maps and can be merged into each
+    /// other, the left-hand side keeps its kind (and struct name).
+    pub fn merge_with(&mut self, other: Value, strategy: MergeStrategy) -> Result<()> {
+        let mut path = String::new();
+
+        self.merge_at(other, strategy, &mut path)
+    }
+
+    /// Applies a merge patch in the spirit of RFC 7386: object and struct
+    /// entries of `patch` are merged into `self` recursively, `Null` entries
+    /// delete the key, and any other patch replaces `self` wholesale (arrays
+    /// included).
+    pub fn merge_patch(&mut self, patch: Value) {
+        let patch_map = match patch {
+            Value::Object(map) | Value::Struct(_, map) => map,
+            patch => {
+                *self = patch;
+
+                return;
+            }
+        };
+
+        if !matches!(self, Value::Object(_) | Value::Struct(_, _)) {
+            *self = Value::Object(BTreeMap::new());
+        }
+
+        let (Value::Object(target) | Value::Struct(_, target)) = self else {
+            unreachable!();
+        };
+
+        for (key, value) in patch_map {
+            if value.is_null() {
+                target.remove(&key);
+
+                continue;
+            }
+
+            target.entry(key).or_default().merge_patch(value);
+        }
+    }
+
+    fn merge_at(&mut self, other: Value, strategy: MergeStrategy, path: &mut String) -> Result<()> {
+        match (self, other) {
+            (
+                Value::Object(target) | Value::Struct(_, target),
+                Value::Object(source) | Value::Struct(_, source),
+            ) => {
+                for (key, value) in source {
+                    match target.get_mut(&key) {
+                        Some(existing) => {
+                            let path_len = path.len();
+
+                            path.push('/');
+                            path.push_str(&escape_segment(&key));
+
+                            existing.merge_at(value, strategy, path)?;
+
+                            path.truncate(path_len);
+                        }
+                        None => {
+                            target.insert(key, value);
+                        }
+                    }
+                }
+
+                Ok(())
+            }
+            (Value::Array(target), Value::Array(source)) => {
+                match strategy.arrays {
+                    ArrayMerge::Replace => *target = source,
+                    ArrayMerge::Concat => target.extend(source),
+                }
+
+                Ok(())
+            }
+            (target, source) => {
+                let compatible = target.is_null()
+                    || source.is_null()
+                    || core::mem::discriminant(target) == core::mem::discriminant(&source);
+
+                if !compatible && strategy.mismatch == MismatchMerge::Error {
+                    return Err(Error::new(format!(
+                        "Cannot merge {} into {} at `{}`",
+                        source.kind(),
+                        target.kind(),
+                        path
+                    )));
+                }
+
+                *target = source;
+
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value;
+
+    #[test]
+    fn nested_structs_merge_field_by_field() {
+        let mut base = value!(@Config(
+            name = "base",
+            server = @Server(host = "localhost", port = 80, tags = ["a"]),
+        ));
+
+        base.merge(value!({
+            "server": @(port = 8080, tags = ["b"], tls = true),
+            "debug": true,
+        }));
+
+        assert_eq!(
+            base,
+            value!(@Config(
+                name = "base",
+                server = @Server(host = "localhost", port = 8080, tags = ["b"], tls = true),
+                debug = true,
+            ))
+        );
+
+        // The left-hand side keeps its kind and struct name
+        let Value::Struct(name, fields) = &base else {
+            panic!("expected a struct, got {:?}", base);
+        };
+
+        assert_eq!(name, "Config");
+        assert!(matches!(&fields["server"], Value::Struct(name, _) if name == "Server"));
+    }
+
+    #[test]
+    fn arrays_concat_by_strategy() {
+        let mut base = value!({"tags": ["a"], "nested": {"tags": ["b"]}});
+        let strategy = MergeStrategy::default().arrays(ArrayMerge::Concat);
+
+        base.merge_with(value!({"tags": ["c"], "nested": {"tags": ["d"]}}), strategy)
+            .unwrap();
+
+        assert_eq!(
+            base,
+            value!({"tags": ["a", "c"], "nested": {"tags": ["b", "d"]}})
+        );
+    }
+
+    #[test]
+    fn null_overrides_a_value() {
+        let mut base = value!({"host": "localhost", "port": 80});
+
+        base.merge(value!({"port": null}));
+
+        assert_eq!(base, value!({"host": "localhost", "port": null}));
+
+        // Even under the strict strategy, null is compatible with anything
+        let strict = MergeStrategy::default().mismatch(MismatchMerge::Error);
+
+        base.merge_with(value!({"host": null, "port": 8080}), strict)
+            .unwrap();
+
+        assert_eq!(base, value!({"host": null, "port": 8080}));
+    }
+
+    #[test]
+    fn merging_into_null_gives_the_other_side() {
+        let mut base = Value::Null;
+
+        base.merge(value!(@Server(host = "a", ports = (80, 443))));
+
+        assert_eq!(base, value!(@Server(host = "a", ports = (80, 443))));
+
+        let mut base = value!({"server": null});
+
+        base.merge(value!({"server": {"host": "a"}}));
+
+        assert_eq!(base, value!({"server": {"host": "a"}}));
+    }
+
+    #[test]
+    fn mismatches_follow_the_strategy() {
+        let mut base = value!({"server": {"ports": [80]}});
+
+        base.merge(value!({"server": {"ports": "80"}}));
+
+        assert_eq!(base, value!({"server": {"ports": "80"}}));
+
+        let strict = MergeStrategy::default().mismatch(MismatchMerge::Error);
+        let error = base
+            .merge_with(value!({"server": {"ports": [80]}}), strict)
+            .unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Cannot merge array into text at `/server/ports`"
+        );
+    }
+
+    #[test]
+    fn patch_deletes_nested_keys() {
+        let mut base = value!(@Config(
+            server = @Server(host = "a", port = 80, tls = @(cert = "c", key = "k")),
+            debug = true,
+        ));
+
+        base.merge_patch(value!({
+            "server": {"port": null, "tls": {"key": null}},
+            "debug": null,
+        }));
+
+        assert_eq!(
+            base,
+            value!(@Config(server = @Server(host = "a", tls = @(cert = "c"))))
+        );
+    }
+
+    #[test]
+    fn patch_deleting_a_missing_key_is_a_no_op() {
+        let mut base = value!({"host": "a", "nested": {"port": 80}});
+
+        base.merge_patch(value!({"missing": null, "nested": {"missing": null}}));
+
+        assert_eq!(base, value!({"host": "a", "nested": {"port": 80}}));
+
+        // Like RFC 7386, a missing key is created for the patch to go into
+        base.merge_patch(value!({"other": {"missing": null}}));
+
+        assert_eq!(
+            base,
+            value!({"host": "a", "nested": {"port": 80}, "other": {}})
+        );
+    }
+
+    #[test]
+    fn patching_a_scalar_replaces_it() {
+        let mut base = value!({"port": 80, "tags": ["a", "b"]});
+
+        base.merge_patch(value!({"port": {"http": 80}, "tags": ["c"]}));
+
+        assert_eq!(base, value!({"port": {"http": 80}, "tags": ["c"]}));
+
+        let mut base = Value::from(80);
+
+        base.merge_patch(value!({"http": 80, "https": null}));
+
+        assert_eq!(base, value!({"http": 80}));
+
+        // A patch that isn't a map replaces the target wholesale
+        base.merge_patch(value!([1, 2]));
+
+        assert_eq!(base, value!([1, 2]));
+    }
+}
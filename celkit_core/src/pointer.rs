@@ -0,0 +1,155 @@
+use crate::internal::sys::*;
+use crate::internal::{Error, Result, Value};
+
+// Paths follow the JSON Pointer syntax: segments are separated by `/`, and
+// inside a segment `~1` stands for `/` and `~0` stands for `~`.
+
+pub(crate) fn escape_segment(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+pub(crate) fn unescape_segment(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
+
+/// Splits `path` into unescaped segments, `None` if the path is malformed
+pub fn split_path(path: &str) -> Option<Vec<String>> {
+    if path.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let rest = path.strip_prefix('/')?;
+
+    Some(rest.split('/').map(unescape_segment).collect())
+}
+
+impl Value {
+    /// Looks up a nested value by path, e.g. `/database/replicas/0/host`
+    pub fn pointer(&self, path: &str) -> Option<&Value> {
+        let mut target = self;
+
+        for segment in split_path(path)? {
+            target = match target {
+                Value::Object(map) | Value::Struct(_, map) => map.get(&segment)?,
+                Value::Array(items) | Value::Tuple(items) => {
+                    items.get(segment.parse::<usize>().ok()?)?
+                }
+                _ => return None,
+            };
+        }
+
+        Some(target)
+    }
+
+    pub fn pointer_mut(&mut self, path: &str) -> Option<&mut Value> {
+        let mut target = self;
+
+        for segment in split_path(path)? {
+            target = match target {
+                Value::Object(map) | Value::Struct(_, map) => map.get_mut(&segment)?,
+                Value::Array(items) | Value::Tuple(items) => {
+                    items.get_mut(segment.parse::<usize>().ok()?)?
+                }
+                _ => return None,
+            };
+        }
+
+        Some(target)
+    }
+
+    /// Sets the value at `path`, returning the previous one if it was replaced.
+    ///
+    /// Missing intermediate entries are created as empty objects, and an array
+    /// index one past the end appends to the array.
+    pub fn set(&mut self, path: &str, value: Value) -> Result<Option<Value>> {
+        let segments =
+            split_path(path).ok_or_else(|| Error::new(format!("Invalid path `{}`", path)))?;
+
+        let Some((last, parents)) = segments.split_last() else {
+            return Ok(Some(core::mem::replace(self, value)));
+        };
+
+        let mut target = self;
+
+        for segment in parents {
+            target = target.child_or_insert(segment, path)?;
+        }
+
+        let is_array = target.is_array();
+
+        match target {
+            Value::Null => {
+                let mut map = BTreeMap::new();
+
+                map.insert(last.clone(), value);
+
+                *target = Value::Object(map);
+
+                Ok(None)
+            }
+            Value::Object(map) | Value::Struct(_, map) => Ok(map.insert(last.clone(), value)),
+            Value::Array(items) | Value::Tuple(items) => {
+                let index = parse_index(last, path)?;
+
+                if index < items.len() {
+                    return Ok(Some(core::mem::replace(&mut items[index], value)));
+                }
+
+                if index == items.len() && is_array {
+                    items.push(value);
+
+                    return Ok(None);
+                }
+
+                Err(out_of_bounds(index, path))
+            }
+            _ => Err(crosses_scalar(last, path)),
+        }
+    }
+
+    fn child_or_insert(&mut self, segment: &str, path: &str) -> Result<&mut Value> {
+        if self.is_null() {
+            *self = Value::Object(BTreeMap::new());
+        }
+
+        let is_array = self.is_array();
+
+        match self {
+            Value::Object(map) | Value::Struct(_, map) => Ok(map
+                .entry(segment.to_string())
+                .or_insert_with(|| Value::Object(BTreeMap::new()))),
+            Value::Array(items) | Value::Tuple(items) => {
+                let index = parse_index(segment, path)?;
+
+                if index == items.len() && is_array {
+                    items.push(Value::Object(BTreeMap::new()));
+                }
+
+                items
+                    .get_mut(index)
+                    .ok_or_else(|| out_of_bounds(index, path))
+            }
+            _ => Err(crosses_scalar(segment, path)),
+        }
+    }
+}
+
+fn parse_index(segment: &str, path: &str) -> Result<usize> {
+    segment
+        .parse::<usize>()
+        .map_err(|_| Error::new(format!("Invalid index `{}` in path `{}`", segment, path)))
+}
+
+fn out_of_bounds(index: usize, path: &str) -> Error {
+    Error::new(format!(
+        "Index {} is out of bounds in path `{}`",
+        index, path
+    ))
+}
+
+fn crosses_scalar(segment: &str, path: &str) -> Error {
+    Error::new(format!(
+        "Cannot set `{}` in path `{}` because its parent is not a container",
+        segment, path
+    ))
+}
@@ -0,0 +1,58 @@
+use crate::internal::sys::*;
+use crate::internal::{PathSegment, Value, WalkControl};
+
+/// Fragments of key names that usually hold secrets
+pub const SENSITIVE_KEYS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "apikey",
+    "api_key",
+    "private_key",
+    "credential",
+];
+
+/// Whether `key` contains one of `SENSITIVE_KEYS`, ignoring ASCII case
+pub fn is_sensitive_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+
+    SENSITIVE_KEYS
+        .iter()
+        .any(|sensitive| key.contains(sensitive))
+}
+
+impl Value {
+    /// Replaces every object entry and struct field whose key matches
+    /// `predicate` with `replacement`, the whole subtree included.
+    pub fn redact<P>(&mut self, predicate: P, replacement: Value)
+    where
+        P: Fn(&str) -> bool,
+    {
+        self.walk_mut(|path, value| match path.last() {
+            Some(PathSegment::Key(key)) if predicate(key) => {
+                *value = replacement.clone();
+
+                WalkControl::SkipChildren
+            }
+            _ => WalkControl::Continue,
+        });
+    }
+
+    /// Same as `Value::redact`, but returns a redacted copy
+    pub fn redacted<P>(&self, predicate: P, replacement: Value) -> Value
+    where
+        P: Fn(&str) -> bool,
+    {
+        let mut value = self.clone();
+
+        value.redact(predicate, replacement);
+
+        value
+    }
+
+    /// Redacts keys matched by `is_sensitive_key` with `"[REDACTED]"`
+    pub fn redact_sensitive(&mut self) {
+        self.redact(is_sensitive_key, Value::Text("[REDACTED]".to_string()));
+    }
+}
@@ -0,0 +1,75 @@
+use crate::internal::Value;
+use core::cmp::Ordering;
+
+/// Rank of each kind when sorting values of different kinds
+fn kind_rank(value: &Value) -> u8 {
+    match value {
+        Value::Null => 0,
+        Value::Boolean(_) => 1,
+        Value::Number(_) => 2,
+        Value::Text(_) => 3,
+        Value::Array(_) => 4,
+        Value::Tuple(_) => 5,
+        Value::Object(_) => 6,
+        Value::Struct(_, _) => 7,
+    }
+}
+
+/// Orders scalars by value and everything else by kind only
+fn compare_keys(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
+        (Value::Number(a), Value::Number(b)) => a.total_cmp(b),
+        (Value::Text(a), Value::Text(b)) => a.cmp(b),
+        _ => kind_rank(a).cmp(&kind_rank(b)),
+    }
+}
+
+impl Value {
+    /// Recursively sorts every array with `compare`, nested arrays are sorted
+    /// before their parents. Tuples keep their order but their members are
+    /// still visited. Sorting is stable.
+    pub fn sort_arrays_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&Value, &Value) -> Ordering,
+    {
+        self.sort_arrays_with(&mut compare);
+    }
+
+    fn sort_arrays_with<F>(&mut self, compare: &mut F)
+    where
+        F: FnMut(&Value, &Value) -> Ordering,
+    {
+        match self {
+            Value::Array(items) => {
+                for item in items.iter_mut() {
+                    item.sort_arrays_with(compare);
+                }
+
+                items.sort_by(|a, b| compare(a, b));
+            }
+            Value::Tuple(members) => {
+                for member in members {
+                    member.sort_arrays_with(compare);
+                }
+            }
+            Value::Object(map) | Value::Struct(_, map) => {
+                for value in map.values_mut() {
+                    value.sort_arrays_with(compare);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Recursively sorts arrays of objects/structs by the value of their `key`
+    /// field. Items missing the key (or that aren't keyed maps) sort last.
+    pub fn sort_arrays_of_objects_by_key(&mut self, key: &str) {
+        self.sort_arrays_by(|a, b| match (a.get(key), b.get(key)) {
+            (Some(a), Some(b)) => compare_keys(a, b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+    }
+}
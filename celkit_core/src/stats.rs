@@ -0,0 +1,142 @@
+use crate::identifier::is_bare_identifier;
+use crate::internal::{Value, WalkControl};
+use core::fmt::{self, Write};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ValueStats {
+    pub nulls: usize,
+    pub booleans: usize,
+    pub numbers: usize,
+    pub texts: usize,
+    pub arrays: usize,
+    pub tuples: usize,
+    pub objects: usize,
+    pub structs: usize,
+    /// Deepest nesting level, a scalar root has a depth of `0`
+    pub max_depth: usize,
+    /// Bytes of all text values, object keys and field names excluded
+    pub text_bytes: usize,
+    /// Length of the minified encoding with the default options, computed
+    /// without encoding
+    pub encoded_size: usize,
+    /// Bytes of all struct names, which `encoded_size` leaves out like the
+    /// encoders do unless `struct_names` is enabled
+    pub struct_name_bytes: usize,
+}
+
+impl ValueStats {
+    /// Total number of values, containers included
+    pub fn nodes(&self) -> usize {
+        self.nulls
+            + self.booleans
+            + self.numbers
+            + self.texts
+            + self.arrays
+            + self.tuples
+            + self.objects
+            + self.structs
+    }
+}
+
+/// Counts the bytes written instead of storing them
+struct Counter(usize);
+
+impl Write for Counter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+
+        Ok(())
+    }
+}
+
+/// Length of `text` once escaped and quoted by the encoders
+fn quoted_len(text: &str) -> usize {
+    let mut length = 2; // Quotation marks
+
+    for c in text.chars() {
+        length += match c {
+            '\x08' | '\x0C' | '\n' | '\r' | '\t' | '\\' | '"' => 2,
+            c if c.is_control() => 6,
+            c => c.len_utf8(),
+        };
+    }
+
+    length
+}
+
+/// Number of separator commas between `count` elements
+fn separators(count: usize) -> usize {
+    count.saturating_sub(1)
+}
+
+impl Value {
+    /// Collects statistics about the value, it's computed iteratively so it
+    /// also works on values nested too deep to recurse over.
+    pub fn stats(&self) -> ValueStats {
+        let mut stats = ValueStats::default();
+
+        self.walk(|path, value| {
+            stats.max_depth = stats.max_depth.max(path.depth());
+
+            match value {
+                Value::Null => {
+                    stats.nulls += 1;
+                    stats.encoded_size += 4;
+                }
+                Value::Boolean(boolean) => {
+                    stats.booleans += 1;
+                    stats.encoded_size += if *boolean { 4 } else { 5 };
+                }
+                Value::Number(number) => {
+                    let mut counter = Counter(0);
+
+                    let _ = write!(counter, "{}", number);
+
+                    stats.numbers += 1;
+                    stats.encoded_size += counter.0;
+                }
+                Value::Text(text) => {
+                    stats.texts += 1;
+                    stats.text_bytes += text.len();
+                    stats.encoded_size += quoted_len(text);
+                }
+                Value::Array(items) | Value::Tuple(items) => {
+                    if value.is_array() {
+                        stats.arrays += 1;
+                    } else {
+                        stats.tuples += 1;
+                    }
+
+                    stats.encoded_size += 2 + separators(items.len());
+                }
+                Value::Object(map) => {
+                    stats.objects += 1;
+                    stats.encoded_size += 2 + separators(map.len());
+
+                    for key in map.keys() {
+                        stats.encoded_size += quoted_len(key) + 1; // Key and ":"
+                    }
+                }
+                Value::Struct(name, map) => {
+                    stats.structs += 1;
+                    stats.struct_name_bytes += name.len();
+                    stats.encoded_size += 3 + separators(map.len()); // "@(" and ")"
+
+                    // Field names are bare unless they aren't identifiers
+                    for name in map.keys() {
+                        stats.encoded_size += if is_bare_identifier(name) {
+                            name.len()
+                        } else {
+                            quoted_len(name)
+                        };
+                        stats.encoded_size += 1; // "="
+                    }
+                }
+            }
+
+            WalkControl::Continue
+        });
+
+        stats
+    }
+}
@@ -0,0 +1,338 @@
+use crate::internal::sys::*;
+use crate::internal::Value;
+use crate::pointer::escape_segment;
+use alloc::collections::btree_map;
+use core::fmt;
+use core::iter::Enumerate;
+use core::slice;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkControl {
+    /// Keep walking, including the children of the current value
+    Continue,
+    /// Keep walking, but don't descend into the current value
+    SkipChildren,
+    /// Stop the walk entirely
+    Stop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathSegment<'a> {
+    /// Key of an object entry or name of a struct field
+    Key(&'a str),
+    /// Position inside an array or tuple
+    Index(usize),
+}
+
+/// Location of a value visited by `Value::walk`, relative to the root
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValuePath<'a> {
+    segments: Vec<PathSegment<'a>>,
+}
+
+impl<'a> ValuePath<'a> {
+    pub fn segments(&self) -> &[PathSegment<'a>] {
+        &self.segments
+    }
+
+    /// Nesting depth, the root value has a depth of `0`
+    pub fn depth(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn last(&self) -> Option<&PathSegment<'a>> {
+        self.segments.last()
+    }
+}
+
+/// Formats the path with the same syntax as `Value::pointer`
+impl fmt::Display for ValuePath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Key(key) => write!(f, "/{}", escape_segment(key))?,
+                PathSegment::Index(index) => write!(f, "/{}", index)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+enum Children<'a> {
+    Items(Enumerate<slice::Iter<'a, Value>>),
+    Entries(btree_map::Iter<'a, String, Value>),
+}
+
+impl<'a> Children<'a> {
+    fn of(value: &'a Value) -> Option<Self> {
+        match value {
+            Value::Array(items) | Value::Tuple(items) => {
+                Some(Children::Items(items.iter().enumerate()))
+            }
+            Value::Object(map) | Value::Struct(_, map) => Some(Children::Entries(map.iter())),
+            _ => None,
+        }
+    }
+
+    fn next(&mut self) -> Option<(PathSegment<'a>, &'a Value)> {
+        match self {
+            Children::Items(items) => items.next().map(|(i, item)| (PathSegment::Index(i), item)),
+            Children::Entries(entries) => entries
+                .next()
+                .map(|(key, value)| (PathSegment::Key(key), value)),
+        }
+    }
+}
+
+enum ChildrenMut<'a> {
+    Items(Enumerate<slice::IterMut<'a, Value>>),
+    Entries(btree_map::IterMut<'a, String, Value>),
+}
+
+impl<'a> ChildrenMut<'a> {
+    fn of(value: &'a mut Value) -> Option<Self> {
+        match value {
+            Value::Array(items) | Value::Tuple(items) => {
+                Some(ChildrenMut::Items(items.iter_mut().enumerate()))
+            }
+            Value::Object(map) | Value::Struct(_, map) => {
+                Some(ChildrenMut::Entries(map.iter_mut()))
+            }
+            _ => None,
+        }
+    }
+
+    fn next(&mut self) -> Option<(PathSegment<'a>, &'a mut Value)> {
+        match self {
+            ChildrenMut::Items(items) => {
+                items.next().map(|(i, item)| (PathSegment::Index(i), item))
+            }
+            ChildrenMut::Entries(entries) => entries
+                .next()
+                .map(|(key, value)| (PathSegment::Key(key), value)),
+        }
+    }
+}
+
+impl Value {
+    /// Visits the value and all of its descendants in depth-first pre-order,
+    /// arrays by position and keyed maps by key order.
+    ///
+    /// The walk uses an explicit stack, so it works on values nested too deep
+    /// to recurse over.
+    pub fn walk<'a, F>(&'a self, mut f: F)
+    where
+        F: FnMut(&ValuePath<'a>, &'a Value) -> WalkControl,
+    {
+        let mut path = ValuePath::default();
+
+        if f(&path, self) != WalkControl::Continue {
+            return;
+        }
+
+        let mut stack = Vec::from_iter(Children::of(self));
+
+        while let Some(children) = stack.last_mut() {
+            let Some((segment, child)) = children.next() else {
+                stack.pop();
+
+                // The root has no segment of its own
+                if !stack.is_empty() {
+                    path.segments.pop();
+                }
+
+                continue;
+            };
+
+            path.segments.push(segment);
+
+            match f(&path, child) {
+                WalkControl::Stop => return,
+                WalkControl::SkipChildren => {
+                    path.segments.pop();
+                }
+                WalkControl::Continue => match Children::of(child) {
+                    Some(children) => stack.push(children),
+                    None => {
+                        path.segments.pop();
+                    }
+                },
+            }
+        }
+    }
+
+    /// Same as `Value::walk`, but gives mutable access to every value. The
+    /// children of a value are visited after it was modified.
+    pub fn walk_mut<F>(&mut self, mut f: F)
+    where
+        F: for<'p> FnMut(&ValuePath<'p>, &mut Value) -> WalkControl,
+    {
+        let mut path = ValuePath::default();
+
+        if f(&path, self) != WalkControl::Continue {
+            return;
+        }
+
+        let mut stack = Vec::from_iter(ChildrenMut::of(self));
+
+        while let Some(children) = stack.last_mut() {
+            let Some((segment, child)) = children.next() else {
+                stack.pop();
+
+                if !stack.is_empty() {
+                    path.segments.pop();
+                }
+
+                continue;
+            };
+
+            path.segments.push(segment);
+
+            match f(&path, child) {
+                WalkControl::Stop => return,
+                WalkControl::SkipChildren => {
+                    path.segments.pop();
+                }
+                WalkControl::Continue => match ChildrenMut::of(child) {
+                    Some(children) => stack.push(children),
+                    None => {
+                        path.segments.pop();
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    /// `depth` arrays nested in one another around a `0`
+    fn nested_arrays(depth: usize) -> Value {
+        let mut value = value!(0);
+
+        for _ in 0..depth {
+            value = Value::Array(vec![value]);
+        }
+
+        value
+    }
+
+    /// Drops `value` a level at a time, as dropping it outright recurses
+    fn drop_nested(value: Value) {
+        let mut stack = vec![value];
+
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::Array(items) | Value::Tuple(items) => stack.extend(items),
+                Value::Object(map) | Value::Struct(_, map) => stack.extend(map.into_values()),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn very_deep_values_are_walked() {
+        let depth = 50_000;
+        let mut value = nested_arrays(depth);
+        let mut visits = 0;
+        let mut deepest = 0;
+
+        value.walk(|path, value| {
+            visits += 1;
+            deepest = deepest.max(path.depth());
+
+            if let Value::Number(_) = value {
+                assert!(path.segments().iter().all(|s| *s == PathSegment::Index(0)));
+            }
+
+            WalkControl::Continue
+        });
+
+        assert_eq!(visits, depth + 1);
+        assert_eq!(deepest, depth);
+
+        value.walk_mut(|path, value| {
+            if path.depth() == depth {
+                *value = value!("leaf");
+            }
+
+            WalkControl::Continue
+        });
+
+        let mut leaf = None;
+
+        value.walk(|path, value| {
+            if path.depth() == depth {
+                leaf = Some(value.clone());
+            }
+
+            WalkControl::Continue
+        });
+
+        assert_eq!(leaf, Some(value!("leaf")));
+
+        // Stopping half way leaves the rest unvisited
+        let mut visits = 0;
+
+        value.walk(|path, _| {
+            visits += 1;
+
+            if path.depth() == depth / 2 {
+                WalkControl::Stop
+            } else {
+                WalkControl::Continue
+            }
+        });
+
+        assert_eq!(visits, depth / 2 + 1);
+        drop_nested(value);
+    }
+
+    #[test]
+    fn visits_in_order_with_paths() {
+        let value = value!({
+            "b": @Point(tags = ["x", "y"], x = 1),
+            "a": [true],
+        });
+        let mut visits = Vec::new();
+
+        value.walk(|path, value| {
+            let label = match value {
+                Value::Text(text) => text.clone(),
+                value => value.kind().to_string(),
+            };
+
+            visits.push((path.to_string(), label));
+
+            match path.last() {
+                Some(PathSegment::Key("a")) => WalkControl::SkipChildren,
+                _ => WalkControl::Continue,
+            }
+        });
+
+        let expected = [
+            ("", "object"),
+            ("/a", "array"),
+            ("/b", "struct"),
+            ("/b/tags", "array"),
+            ("/b/tags/0", "x"),
+            ("/b/tags/1", "y"),
+            ("/b/x", "number"),
+        ];
+
+        assert_eq!(visits.len(), expected.len());
+
+        for ((path, label), (expected_path, expected_label)) in visits.iter().zip(expected) {
+            assert_eq!(
+                (path.as_str(), label.as_str()),
+                (expected_path, expected_label)
+            );
+        }
+    }
+}
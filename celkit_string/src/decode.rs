@@ -1,3 +1,2820 @@
-pub fn from_string() {
-    assert!(false, "`from_string()` is not implemented yet!")
+use crate::spanned::{Position, Span, Spanned, SpannedEntry, SpannedValue};
+use crate::DEFAULT_MAX_DEPTH;
+use celkit_core::internal::sys::*;
+use celkit_core::internal::{split_path, Error, Number, Result, Value};
+use celkit_core::{
+    is_identifier_continue, is_identifier_start, Deserialize, DeserializeOptions, MissingFields,
+    UnknownFields,
+};
+
+/// What the parser does when an object or struct repeats a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    /// Fail at the repeated key
+    #[default]
+    Error,
+    /// Keep the value of the first occurrence
+    FirstWins,
+    /// Keep the value of the last occurrence
+    LastWins,
+}
+
+#[derive(Debug, Clone)]
+pub struct DecodeOptions {
+    preserve_precision: bool,
+    hash_comments: bool,
+    duplicate_keys: DuplicateKeys,
+    max_depth: usize,
+    lenient_escapes: bool,
+    replace_lone_surrogates: bool,
+    deserialize: DeserializeOptions, // Used once the input is parsed
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            preserve_precision: true,
+            hash_comments: false,
+            duplicate_keys: DuplicateKeys::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            lenient_escapes: false,
+            replace_lone_surrogates: false,
+            deserialize: DeserializeOptions::default(),
+        }
+    }
+}
+
+impl DecodeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps numbers that would overflow `u128` or lose digits or trailing
+    /// zeros as an `f64` verbatim in `Number::Big`, so `1.230` keeps its
+    /// scale. On by default, turning it off rounds them to an `f64`.
+    pub fn preserve_precision(mut self, enabled: bool) -> Self {
+        self.preserve_precision = enabled;
+
+        self
+    }
+
+    /// Also accepts `#` line comments, besides the `//` and `/* */` ones
+    pub fn hash_comments(mut self, enabled: bool) -> Self {
+        self.hash_comments = enabled;
+
+        self
+    }
+
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicate_keys = policy;
+
+        self
+    }
+
+    /// Limit on nested arrays, tuples, objects and structs, deeper input is
+    /// rejected instead of exhausting the stack
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+
+        self
+    }
+
+    /// Keeps unknown escape sequences like `\q` as written instead of
+    /// rejecting them, for reading hand-edited legacy files. Malformed `\u`
+    /// escapes are still errors.
+    pub fn lenient_escapes(mut self, enabled: bool) -> Self {
+        self.lenient_escapes = enabled;
+
+        self
+    }
+
+    /// Decodes `\uXXXX` surrogates that aren't part of a valid pair as
+    /// U+FFFD, instead of rejecting them
+    pub fn replace_lone_surrogates(mut self, enabled: bool) -> Self {
+        self.replace_lone_surrogates = enabled;
+
+        self
+    }
+
+    /// Rejects `@Name(...)` whose name isn't the one the target struct is
+    /// declared with, at any depth. Unnamed `@(...)` structs are still
+    /// accepted.
+    pub fn strict_struct_names(mut self, enabled: bool) -> Self {
+        self.deserialize.strict_struct_names = enabled;
+
+        self
+    }
+
+    /// Lets object syntax stand in for a struct and struct syntax for a map
+    pub fn coerce_struct_object(mut self, enabled: bool) -> Self {
+        self.deserialize.coerce_struct_object = enabled;
+
+        self
+    }
+
+    /// What deserializing does about struct fields the input leaves out
+    pub fn missing_fields(mut self, policy: MissingFields) -> Self {
+        self.deserialize.missing_fields = policy;
+
+        self
+    }
+
+    /// What deserializing does about struct fields the type doesn't declare
+    pub fn unknown_fields(mut self, policy: UnknownFields) -> Self {
+        self.deserialize.unknown_fields = policy;
+
+        self
+    }
+
+    /// Matches struct fields ignoring ASCII case, e.g. `Host` or `HOST` for
+    /// `host`. Keys that only differ in case are an error whatever the
+    /// `duplicate_keys` policy, since which one came first is lost by then.
+    pub fn case_insensitive_fields(mut self, enabled: bool) -> Self {
+        self.deserialize.case_insensitive_fields = enabled;
+
+        self
+    }
+
+    /// Lets text holding a decimal number stand in for a number, e.g.
+    /// `"8080"` for a `u16`, checked against the target type like a number
+    /// value. Surrounding whitespace and a leading `+` are fine, digit
+    /// separators and `nan` or `inf` aren't.
+    pub fn coerce_numbers_from_text(mut self, enabled: bool) -> Self {
+        self.deserialize.coerce_numbers_from_text = enabled;
+
+        self
+    }
+
+    /// Lets `1` and `0` and the texts in `TRUE_WORDS` and `FALSE_WORDS` stand
+    /// in for a boolean, ignoring ASCII case
+    pub fn coerce_booleans(mut self, enabled: bool) -> Self {
+        self.deserialize.coerce_booleans = enabled;
+
+        self
+    }
+}
+
+const BOM: char = '\u{FEFF}';
+
+/// Position in the input, where a token starts
+#[derive(Clone, Copy)]
+struct Mark {
+    position: usize,
+    line: usize,
+    column: usize,
+}
+
+/// Tree the decoder builds, plain `Value`s or ones that keep their spans
+trait Node: Sized {
+    type Entry;
+
+    /// Null, boolean, number or text
+    fn scalar(value: Value, span: Span) -> Self;
+    fn array(items: Vec<Self>, span: Span) -> Self;
+    fn tuple(items: Vec<Self>, span: Span) -> Self;
+    fn object(entries: BTreeMap<String, Self::Entry>, span: Span) -> Self;
+    fn structure(name: String, fields: BTreeMap<String, Self::Entry>, span: Span) -> Self;
+    fn entry(key: Span, value: Self) -> Self::Entry;
+}
+
+impl Node for Value {
+    type Entry = Value;
+
+    fn scalar(value: Value, _: Span) -> Self {
+        value
+    }
+
+    fn array(items: Vec<Self>, _: Span) -> Self {
+        Value::Array(items)
+    }
+
+    fn tuple(items: Vec<Self>, _: Span) -> Self {
+        Value::Tuple(items)
+    }
+
+    fn object(entries: BTreeMap<String, Value>, _: Span) -> Self {
+        Value::Object(entries)
+    }
+
+    fn structure(name: String, fields: BTreeMap<String, Value>, _: Span) -> Self {
+        Value::Struct(name, fields)
+    }
+
+    fn entry(_: Span, value: Self) -> Value {
+        value
+    }
+}
+
+impl Node for Spanned<SpannedValue> {
+    type Entry = SpannedEntry;
+
+    fn scalar(value: Value, span: Span) -> Self {
+        let value = match value {
+            Value::Null => SpannedValue::Null,
+            Value::Boolean(b) => SpannedValue::Boolean(b),
+            Value::Number(n) => SpannedValue::Number(n),
+            Value::Text(s) => SpannedValue::Text(s),
+            _ => unreachable!(),
+        };
+
+        Spanned { value, span }
+    }
+
+    fn array(items: Vec<Self>, span: Span) -> Self {
+        Spanned {
+            value: SpannedValue::Array(items),
+            span,
+        }
+    }
+
+    fn tuple(items: Vec<Self>, span: Span) -> Self {
+        Spanned {
+            value: SpannedValue::Tuple(items),
+            span,
+        }
+    }
+
+    fn object(entries: BTreeMap<String, SpannedEntry>, span: Span) -> Self {
+        Spanned {
+            value: SpannedValue::Object(entries),
+            span,
+        }
+    }
+
+    fn structure(name: String, fields: BTreeMap<String, SpannedEntry>, span: Span) -> Self {
+        Spanned {
+            value: SpannedValue::Struct(name, fields),
+            span,
+        }
+    }
+
+    fn entry(key: Span, value: Self) -> SpannedEntry {
+        SpannedEntry { key, value }
+    }
+}
+
+/// Container the decoder is inside of, with what it decoded so far
+struct Frame<N: Node> {
+    container: Container,
+    start: Mark, // Opening character
+    item: Mark,  // Start of the current item
+    items: Vec<N>,
+    entries: BTreeMap<String, N::Entry>,
+    key: Option<(String, Span)>, // Key of the entry being decoded
+}
+
+enum Container {
+    Array,
+    Tuple,
+    Object,
+    Struct(String),
+}
+
+impl Container {
+    fn close(&self) -> char {
+        match self {
+            Container::Array => ']',
+            Container::Tuple | Container::Struct(_) => ')',
+            Container::Object => '}',
+        }
+    }
+}
+
+impl<N: Node> Frame<N> {
+    fn finish(self, span: Span) -> N {
+        match self.container {
+            Container::Array => N::array(self.items, span),
+            Container::Tuple => N::tuple(self.items, span),
+            Container::Object => N::object(self.entries, span),
+            Container::Struct(name) => N::structure(name, self.entries, span),
+        }
+    }
+}
+
+/// Value whose decoding started, which is either done or a container that
+/// was just opened
+enum Opened<N: Node> {
+    Value(N),
+    Container(Frame<N>),
+}
+
+/// How much of the input `Decoder::decode_into` decodes
+#[derive(Debug, Clone, Copy)]
+enum Extent {
+    /// All of it, which must hold a single document
+    Document,
+    /// The document at the current position and the whitespace and comments
+    /// after it
+    Prefix,
+    /// The value at the current position
+    Value,
+}
+
+struct Decoder<'a> {
+    input: &'a str,
+    options: &'a DecodeOptions,
+    position: usize, // Byte offset into `input`
+    line: usize,
+    column: usize,
+    errors: Option<Vec<Error>>, // Errors recovered from, when collecting them
+}
+
+impl<'a> Decoder<'a> {
+    fn new(input: &'a str, options: &'a DecodeOptions) -> Self {
+        Self {
+            input,
+            options,
+            // A byte order mark is skipped, it doesn't count as a column
+            position: if input.starts_with(BOM) {
+                BOM.len_utf8()
+            } else {
+                0
+            },
+            line: 1,
+            column: 1,
+            errors: None,
+        }
+    }
+
+    fn decode<N: Node>(&mut self) -> Result<N> {
+        let value = self.decode_prefix()?;
+
+        if let Some(c) = self.peek() {
+            let error = self.error(format!("Trailing characters after document, found `{}`", c));
+            let mark = self.mark();
+
+            self.recover(error, mark)?;
+        }
+
+        Ok(value)
+    }
+
+    fn decode_extent<N: Node>(&mut self, extent: Extent) -> Result<N> {
+        match extent {
+            Extent::Document => self.decode(),
+            Extent::Prefix => self.decode_prefix(),
+            Extent::Value => self.decode_value(),
+        }
+    }
+
+    /// Decodes `extent` of the input into `T`. Types that keep their source
+    /// text, like `RawValue`, are given the span of every value as well.
+    fn decode_into<T: Deserialize>(&mut self, extent: Extent) -> Result<T> {
+        let start = self.mark();
+
+        self.deserialize_extent(extent)
+            .map_err(|error| self.locate(error, start))
+    }
+
+    fn deserialize_extent<T: Deserialize>(&mut self, extent: Extent) -> Result<T> {
+        let options = &self.options.deserialize;
+
+        if !T::captures_source() {
+            let value = self.decode_extent(extent)?;
+
+            return T::deserialize_with(value, options);
+        }
+
+        let spanned: Spanned<SpannedValue> = self.decode_extent(extent)?;
+        let options = DeserializeOptions {
+            source: Some(spanned.source(self.input)),
+            ..options.clone()
+        };
+
+        T::deserialize_with(spanned.into_value(), &options)
+    }
+
+    /// Points an error from deserializing the extent starting at `start` at
+    /// the value it came from, found again by the error's path. Errors that
+    /// already have a position are left alone.
+    fn locate(&mut self, mut error: Error, start: Mark) -> Error {
+        let segments = match error.path() {
+            _ if error.line.is_some() => return error,
+            Some(path) => split_path(path),
+            None => Some(Vec::new()),
+        };
+
+        let Some(segments) = segments else {
+            return error;
+        };
+
+        self.position = start.position;
+        self.line = start.line;
+        self.column = start.column;
+
+        if !matches!(self.seek(&segments), Ok(true)) {
+            return error;
+        }
+
+        let mark = self.mark();
+
+        if self.decode_value::<Value>().is_err() {
+            return error;
+        }
+
+        error.line = Some(mark.line);
+        error.column = Some(mark.column);
+
+        error.with_span(mark.position, self.position - mark.position)
+    }
+
+    /// Decodes the value at the start of the rest of the input, along with the
+    /// whitespace and comments around it
+    fn decode_prefix<N: Node>(&mut self) -> Result<N> {
+        self.skip_whitespace()?;
+
+        let value = self.decode_value()?;
+
+        self.skip_whitespace()?;
+
+        Ok(value)
+    }
+
+    /// Moves to the value at `segments` below the one at the current position,
+    /// returns `false` if there's none. Values off the path are skipped by
+    /// matching delimiters only, without being validated.
+    fn seek(&mut self, segments: &[String]) -> Result<bool> {
+        for segment in segments {
+            self.skip_whitespace()?;
+
+            let found = match self.peek() {
+                Some('{') => {
+                    self.next();
+                    self.seek_entry(segment, '}', ':')?
+                }
+                Some('@') => {
+                    self.next();
+
+                    if self.peek().is_some_and(is_identifier_start) {
+                        self.decode_identifier()?;
+                    }
+
+                    self.expect('(')?;
+                    self.seek_entry(segment, ')', '=')?
+                }
+                Some(open @ ('[' | '(')) => {
+                    self.next();
+
+                    let close = if open == '[' { ']' } else { ')' };
+
+                    match segment.parse::<usize>() {
+                        Ok(index) => self.seek_item(index, close)?,
+                        Err(_) => false,
+                    }
+                }
+                _ => false,
+            };
+
+            if !found {
+                return Ok(false);
+            }
+        }
+
+        self.skip_whitespace()?;
+
+        Ok(true)
+    }
+
+    /// Moves to the value of the first entry named `key`, past the ones
+    /// before it
+    fn seek_entry(&mut self, key: &str, close: char, assign: char) -> Result<bool> {
+        self.skip_whitespace()?;
+
+        if self.peek() == Some(close) {
+            return Ok(false);
+        }
+
+        loop {
+            self.skip_whitespace()?;
+
+            let name = match self.peek() {
+                Some(c) if is_identifier_start(c) => self.decode_identifier()?.to_string(),
+                Some('"' | '\'') => self.decode_text()?,
+                Some(c) => return Err(self.error(format!("Expected a key, found `{}`", c))),
+                None => return Err(self.error("Expected a key, found end of input")),
+            };
+
+            self.skip_whitespace()?;
+            self.expect(assign)?;
+            self.skip_whitespace()?;
+
+            if name == key {
+                return Ok(true);
+            }
+
+            self.skip_to_separator();
+
+            if self.decode_separator(close)? {
+                return Ok(false);
+            }
+        }
+    }
+
+    /// Moves to the item at `index`, past the ones before it
+    fn seek_item(&mut self, index: usize, close: char) -> Result<bool> {
+        self.skip_whitespace()?;
+
+        if self.peek() == Some(close) {
+            return Ok(false);
+        }
+
+        for _ in 0..index {
+            self.skip_to_separator();
+
+            if self.decode_separator(close)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Decodes the whole input, going on after errors within containers
+    fn decode_recovering(mut self) -> (Option<Value>, Vec<Error>) {
+        self.errors = Some(Vec::new());
+
+        let value = self.decode();
+        let mut errors = self.errors.take().unwrap_or_default();
+
+        match value {
+            Ok(value) => (Some(value), errors),
+            Err(error) => {
+                errors.push(error);
+
+                (None, errors)
+            }
+        }
+    }
+
+    /// Records `error` when collecting errors and skips from `mark` to where
+    /// the container can go on, otherwise hands the error back. An error
+    /// starting within the previous one is a knock-on of it and is dropped.
+    fn recover(&mut self, error: Error, mark: Mark) -> Result<()> {
+        let Some(errors) = &mut self.errors else {
+            return Err(error);
+        };
+
+        let previous_end = errors
+            .last()
+            .and_then(|last| Some(last.offset? + last.len?));
+
+        match (error.offset, previous_end) {
+            (Some(start), Some(end)) if start <= end => {}
+            _ => errors.push(error),
+        }
+
+        self.position = mark.position;
+        self.line = mark.line;
+        self.column = mark.column;
+
+        self.skip_to_separator();
+
+        Ok(())
+    }
+
+    /// Skips to the next `,` or closing character outside of nested
+    /// containers, text and comments
+    fn skip_to_separator(&mut self) {
+        let mut depth = 0usize;
+
+        loop {
+            self.skip_until(|byte| {
+                matches!(
+                    byte,
+                    b',' | b'(' | b')' | b'[' | b']' | b'{' | b'}' | b'"' | b'\'' | b'/' | b'#'
+                )
+            });
+
+            let Some(c) = self.peek() else {
+                return;
+            };
+
+            let rest = &self.input[self.position..];
+
+            match c {
+                ',' | ')' | ']' | '}' if depth == 0 => return,
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                '"' | '\'' => {
+                    self.skip_text();
+                    continue;
+                }
+                '#' if self.options.hash_comments => self.skip_line(),
+                '/' if rest.starts_with("//") => self.skip_line(),
+                '/' if rest.starts_with("/*") => {
+                    if self.skip_block_comment().is_err() {
+                        return;
+                    }
+
+                    continue;
+                }
+                _ => {}
+            }
+
+            self.next();
+        }
+    }
+
+    /// Skips over text without decoding it
+    fn skip_text(&mut self) {
+        if self.input[self.position..].starts_with("\"\"\"") {
+            for _ in 0..3 {
+                self.next();
+            }
+
+            loop {
+                self.skip_until(|byte| byte == b'"');
+
+                if self.input[self.position..].starts_with("\"\"\"") {
+                    break;
+                }
+
+                if self.next().is_none() {
+                    return;
+                }
+            }
+
+            for _ in 0..3 {
+                self.next();
+            }
+
+            return;
+        }
+
+        let quote = self.next();
+
+        loop {
+            self.skip_until(|byte| byte == b'\\' || Some(byte as char) == quote);
+
+            match self.next() {
+                Some('\\') => {
+                    self.next();
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Skips ahead to the first byte that `stop` matches, which must only
+    /// match ASCII bytes so the position stays on a character boundary
+    fn skip_until(&mut self, stop: impl Fn(u8) -> bool) {
+        let bytes = self.input.as_bytes();
+
+        while let Some(&byte) = bytes.get(self.position) {
+            if stop(byte) {
+                return;
+            }
+
+            if byte == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else if byte & 0xC0 != 0x80 {
+                // Continuation bytes are part of the character before them
+                self.column += 1;
+            }
+
+            self.position += 1;
+        }
+    }
+
+    /// Error at the current character
+    fn error(&self, message: impl Into<String>) -> Error {
+        let len = self.peek().map_or(0, char::len_utf8);
+
+        Error::with_position(message, self.line, self.column).with_span(self.position, len)
+    }
+
+    fn mark(&self) -> Mark {
+        Mark {
+            position: self.position,
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    /// Span of the input from `mark` up to the current position
+    fn span_from(&self, mark: Mark) -> Span {
+        Span {
+            start: Position {
+                offset: mark.position,
+                line: mark.line,
+                column: mark.column,
+            },
+            end: Position {
+                offset: self.position,
+                line: self.line,
+                column: self.column,
+            },
+        }
+    }
+
+    /// Error spanning the input from `mark` up to the current position
+    fn error_from(&self, message: impl Into<String>, mark: Mark) -> Error {
+        Error::with_position(message, mark.line, mark.column)
+            .with_span(mark.position, self.position - mark.position)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+
+        self.position += c.len_utf8();
+
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.next();
+
+                Ok(())
+            }
+            Some(c) => Err(self.error(format!("Expected `{}`, found `{}`", expected, c))),
+            None => Err(self.error(format!("Expected `{}`, found end of input", expected))),
+        }
+    }
+
+    /// Skips whitespace and comments: `// line`, `/* block */` and, with
+    /// `hash_comments`, `# line`. Block comments don't nest, the first `*/`
+    /// ends them.
+    fn skip_whitespace(&mut self) -> Result<()> {
+        loop {
+            let rest = &self.input[self.position..];
+
+            match self.peek() {
+                Some(' ' | '\t' | '\n' | '\r') => {
+                    self.next();
+                }
+                Some('#') if self.options.hash_comments => self.skip_line(),
+                Some('/') if rest.starts_with("//") => self.skip_line(),
+                Some('/') if rest.starts_with("/*") => self.skip_block_comment()?,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Skips a line comment up to, but not including, the line break
+    fn skip_line(&mut self) {
+        while self.peek().is_some_and(|c| c != '\n') {
+            self.next();
+        }
+    }
+
+    fn skip_block_comment(&mut self) -> Result<()> {
+        let mark = self.mark();
+
+        self.next();
+        self.next();
+
+        while !self.input[self.position..].starts_with("*/") {
+            if self.next().is_none() {
+                return Err(self.error_from("Unterminated block comment", mark));
+            }
+        }
+
+        self.next();
+        self.next();
+
+        Ok(())
+    }
+
+    fn decode_identifier(&mut self) -> Result<&'a str> {
+        let start = self.position;
+
+        match self.peek() {
+            Some(c) if is_identifier_start(c) => {}
+            Some(c) => return Err(self.error(format!("Expected identifier, found `{}`", c))),
+            None => return Err(self.error("Expected identifier, found end of input")),
+        }
+
+        while let Some(c) = self.peek() {
+            if !is_identifier_continue(c) {
+                break;
+            }
+
+            self.next();
+        }
+
+        Ok(&self.input[start..self.position])
+    }
+
+    fn decode_keyword(&mut self) -> Result<Value> {
+        let mark = self.mark();
+
+        match self.decode_identifier()? {
+            "null" => Ok(Value::Null),
+            "true" => Ok(Value::Boolean(true)),
+            "false" => Ok(Value::Boolean(false)),
+            "nan" => Ok(Value::Number(Number::F64(f64::NAN))),
+            "inf" => Ok(Value::Number(Number::F64(f64::INFINITY))),
+            identifier => {
+                Err(self.error_from(format!("Unexpected identifier `{}`", identifier), mark))
+            }
+        }
+    }
+
+    fn decode_number(&mut self) -> Result<Number> {
+        let start = self.position;
+        let mark = self.mark();
+        let mut is_float = false;
+
+        if self.is_radix_prefix() {
+            return self.decode_radix_integer();
+        }
+
+        if self.peek() == Some('-') {
+            self.next();
+
+            if self.is_radix_prefix() {
+                return Err(
+                    self.error_from("Hex, octal and binary literals can't be negative", mark)
+                );
+            }
+
+            // Negative infinity is the only signed keyword
+            if self.peek() == Some('i') {
+                return match self.decode_identifier()? {
+                    "inf" => Ok(Number::F64(f64::NEG_INFINITY)),
+                    identifier => {
+                        Err(self.error_from(format!("Invalid number `-{}`", identifier), mark))
+                    }
+                };
+            }
+        }
+
+        self.decode_digits(10)?;
+
+        if self.peek() == Some('.') {
+            self.next();
+            self.decode_digits(10)?;
+
+            is_float = true;
+        }
+
+        // Literals with an exponent always decode to `F64`, even when integral
+        if let Some('e' | 'E') = self.peek() {
+            self.next();
+
+            if let Some('+' | '-') = self.peek() {
+                self.next();
+            }
+
+            self.decode_digits(10)?;
+
+            is_float = true;
+        }
+
+        let raw = &self.input[start..self.position];
+        let literal = strip_separators(raw);
+
+        if !is_float {
+            // Pick the first variant that can hold the integer
+            if let Ok(n) = literal.parse::<i64>() {
+                return Ok(Number::from(n));
+            }
+
+            if let Ok(n) = literal.parse::<u64>() {
+                return Ok(Number::from(n));
+            }
+
+            if let Ok(n) = literal.parse::<i128>() {
+                return Ok(Number::from(n));
+            }
+
+            if let Ok(n) = literal.parse::<u128>() {
+                return Ok(Number::from(n));
+            }
+        }
+
+        // Floats, and integers too large for any integer variant
+        let value = literal
+            .parse::<f64>()
+            .map_err(|_| self.error_from(format!("Invalid number `{}`", raw), mark))?;
+
+        // Integers this large keep their digits rather than the nearest `f64`
+        if self.options.preserve_precision && (!is_float || !is_exact(&literal, value)) {
+            return Ok(Number::Big(literal));
+        }
+
+        Ok(Number::from(value))
+    }
+
+    fn is_radix_prefix(&self) -> bool {
+        let mut chars = self.input[self.position..].chars();
+
+        chars.next() == Some('0') && matches!(chars.next(), Some('x' | 'X' | 'o' | 'O' | 'b' | 'B'))
+    }
+
+    /// Decodes `0x`, `0o` and `0b` literals into the smallest unsigned variant
+    /// that holds them
+    fn decode_radix_integer(&mut self) -> Result<Number> {
+        let start = self.position;
+        let mark = self.mark();
+
+        self.next(); // Leading `0`
+
+        let radix = match self.next() {
+            Some('x' | 'X') => 16,
+            Some('o' | 'O') => 8,
+            _ => 2,
+        };
+
+        let digits_start = self.position;
+
+        self.decode_digits(radix)?;
+
+        if let Some(c) = self.peek().filter(char::is_ascii_alphanumeric) {
+            return Err(self.error(format!("Invalid {} `{}`", digit_name(radix), c)));
+        }
+
+        let literal = &self.input[start..self.position];
+        let digits = strip_separators(&self.input[digits_start..self.position]);
+
+        let value = u128::from_str_radix(&digits, radix).map_err(|_| {
+            self.error_from(
+                format!("Integer literal `{}` is too large for u128", literal),
+                mark,
+            )
+        })?;
+
+        let number = if let Ok(n) = u8::try_from(value) {
+            Number::from(n)
+        } else if let Ok(n) = u16::try_from(value) {
+            Number::from(n)
+        } else if let Ok(n) = u32::try_from(value) {
+            Number::from(n)
+        } else if let Ok(n) = u64::try_from(value) {
+            Number::from(n)
+        } else {
+            Number::from(value)
+        };
+
+        Ok(number)
+    }
+
+    /// Decodes a run of digits, allowing single `_` separators between them
+    fn decode_digits(&mut self, radix: u32) -> Result<()> {
+        let name = digit_name(radix);
+
+        match self.peek() {
+            Some(c) if c.is_digit(radix) => {}
+            Some(c) => return Err(self.error(format!("Expected {}, found `{}`", name, c))),
+            None => return Err(self.error(format!("Expected {}, found end of input", name))),
+        }
+
+        while let Some(c) = self.peek() {
+            if c == '_' {
+                let mark = self.mark();
+
+                self.next();
+
+                if !self.peek().is_some_and(|c| c.is_digit(radix)) {
+                    return Err(self.error_from(
+                        "Digit separator `_` must be placed between two digits",
+                        mark,
+                    ));
+                }
+
+                continue;
+            }
+
+            if !c.is_digit(radix) {
+                break;
+            }
+
+            self.next();
+        }
+
+        Ok(())
+    }
+
+    fn decode_text(&mut self) -> Result<String> {
+        if self.input[self.position..].starts_with("\"\"\"") {
+            return self.decode_block_text();
+        }
+
+        let mark = self.mark();
+        let mut output = String::new();
+
+        // Text is delimited by either `"` or `'`, the other one needs no escape
+        let quote = match self.peek() {
+            Some('\'') => '\'',
+            _ => '"',
+        };
+
+        self.expect(quote)?;
+
+        loop {
+            let escape = self.mark();
+
+            let c = match self.next() {
+                Some(c) => c,
+                None => {
+                    return Err(self.error_from("Unterminated text", mark));
+                }
+            };
+
+            match c {
+                c if c == quote => break,
+                '\\' => {
+                    let escaped = match self.next() {
+                        Some('b') => '\x08',
+                        Some('f') => '\x0C',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('\\') => '\\',
+                        Some('"') => '"',
+                        Some('\'') => '\'',
+                        Some('u') => self.decode_unicode_escape(escape)?,
+                        Some(c) if self.options.lenient_escapes => {
+                            output.push('\\');
+                            c
+                        }
+                        Some(c) => {
+                            return Err(self
+                                .error_from(format!("Unknown escape sequence `\\{}`", c), escape));
+                        }
+                        None => {
+                            return Err(self.error_from("Unterminated text", mark));
+                        }
+                    };
+
+                    output.push(escaped);
+                }
+                c => output.push(c),
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Decodes a `"""` block. The line break after the opening delimiter is
+    /// skipped and everything up to the closing delimiter is kept verbatim,
+    /// there are no escape sequences in blocks.
+    fn decode_block_text(&mut self) -> Result<String> {
+        let mark = self.mark();
+
+        for _ in 0..3 {
+            self.next();
+        }
+
+        if self.peek() == Some('\r') {
+            self.next();
+        }
+
+        if self.peek() != Some('\n') {
+            return Err(self.error("Expected a line break after `\"\"\"`"));
+        }
+
+        self.next();
+
+        let start = self.position;
+
+        loop {
+            if self.input[self.position..].starts_with("\"\"\"") {
+                let text = self.input[start..self.position].to_string();
+
+                for _ in 0..3 {
+                    self.next();
+                }
+
+                return Ok(text);
+            }
+
+            if self.next().is_none() {
+                return Err(self.error_from("Unterminated text block", mark));
+            }
+        }
+    }
+
+    /// Decodes the digits of a `\uXXXX` escape, combining a high surrogate
+    /// with the `\uXXXX` low surrogate that must follow it. The braced
+    /// `\u{1F600}` form takes up to six digits and needs no pairs.
+    fn decode_unicode_escape(&mut self, mark: Mark) -> Result<char> {
+        if self.peek() == Some('{') {
+            return self.decode_braced_escape(mark);
+        }
+
+        let mut code = self.decode_hex_code(mark)?;
+
+        if (0xD800..0xDC00).contains(&code) {
+            match self.low_surrogate() {
+                Some(low) => {
+                    for _ in 0..6 {
+                        self.next();
+                    }
+
+                    code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                }
+                None if self.options.replace_lone_surrogates => return Ok('\u{FFFD}'),
+                None => {
+                    return Err(
+                        self.error_from(format!("Unpaired surrogate `\\u{:04x}`", code), mark)
+                    );
+                }
+            }
+        } else if (0xDC00..0xE000).contains(&code) {
+            if self.options.replace_lone_surrogates {
+                return Ok('\u{FFFD}');
+            }
+
+            return Err(self.error_from(format!("Unpaired surrogate `\\u{:04x}`", code), mark));
+        }
+
+        self.code_point(code, mark)
+    }
+
+    /// The low surrogate of a `\uXXXX` escape right at the current position,
+    /// without consuming it
+    fn low_surrogate(&self) -> Option<u32> {
+        let digits = self.input[self.position..].strip_prefix("\\u")?.get(..4)?;
+
+        if !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        u32::from_str_radix(digits, 16)
+            .ok()
+            .filter(|low| (0xDC00..0xE000).contains(low))
+    }
+
+    fn decode_braced_escape(&mut self, mark: Mark) -> Result<char> {
+        self.next();
+
+        let mut code: u32 = 0;
+        let mut digits = 0;
+
+        loop {
+            match self.next() {
+                Some('}') if digits > 0 => break,
+                Some(c) if digits < 6 && c.is_ascii_hexdigit() => {
+                    code = code * 16 + c.to_digit(16).unwrap_or(0);
+                    digits += 1;
+                }
+                _ => {
+                    let escape = &self.input[mark.position..self.position];
+                    return Err(self.error_from(
+                        format!("Invalid unicode escape sequence `{}`", escape),
+                        mark,
+                    ));
+                }
+            }
+        }
+
+        if (0xD800..0xE000).contains(&code) && self.options.replace_lone_surrogates {
+            return Ok('\u{FFFD}');
+        }
+
+        self.code_point(code, mark)
+    }
+
+    fn code_point(&self, code: u32, mark: Mark) -> Result<char> {
+        char::from_u32(code).ok_or_else(|| {
+            self.error_from(
+                format!("Invalid unicode code point `\\u{{{:x}}}`", code),
+                mark,
+            )
+        })
+    }
+
+    fn decode_hex_code(&mut self, mark: Mark) -> Result<u32> {
+        let mut code = 0;
+
+        for _ in 0..4 {
+            let digit = self.next().and_then(|c| c.to_digit(16)).ok_or_else(|| {
+                let escape = &self.input[mark.position..self.position];
+                self.error_from(
+                    format!("Invalid unicode escape sequence `{}`", escape),
+                    mark,
+                )
+            })?;
+
+            code = code * 16 + digit;
+        }
+
+        Ok(code)
+    }
+
+    /// Decodes a value. The containers it's made of are kept on a stack of
+    /// their own, so nesting is only limited by `max_depth`.
+    fn decode_value<N: Node>(&mut self) -> Result<N> {
+        let mut frame = match self.open_value(0)? {
+            Opened::Value(node) => return Ok(node),
+            Opened::Container(frame) => frame,
+        };
+
+        let mut parents: Vec<Frame<N>> = Vec::new();
+
+        loop {
+            match self.decode_item(&mut frame, parents.len() + 1) {
+                Ok(Opened::Value(node)) => self.push_item(&mut frame, node),
+                Ok(Opened::Container(inner)) => {
+                    parents.push(core::mem::replace(&mut frame, inner));
+
+                    continue;
+                }
+                Err(error) => self.recover_item(error, &mut frame)?,
+            }
+
+            // Close every container that ends here
+            loop {
+                match self.decode_next(frame.container.close()) {
+                    Ok(false) => break,
+                    Ok(true) => {
+                        let span = self.span_from(frame.start);
+
+                        let node = match parents.pop() {
+                            Some(parent) => core::mem::replace(&mut frame, parent).finish(span),
+                            None => return Ok(frame.finish(span)),
+                        };
+
+                        self.push_item(&mut frame, node);
+                    }
+                    // An error the container couldn't recover from fails the
+                    // item it is in
+                    Err(error) => match parents.pop() {
+                        Some(parent) => {
+                            frame = parent;
+
+                            self.recover_item(error, &mut frame)?;
+                        }
+                        None => return Err(error),
+                    },
+                }
+            }
+        }
+    }
+
+    /// Starts decoding the value at the current position, within `depth`
+    /// open containers
+    fn open_value<N: Node>(&mut self, depth: usize) -> Result<Opened<N>> {
+        let start = self.mark();
+
+        let container = match self.peek() {
+            Some('"' | '\'') => {
+                let value = Value::Text(self.decode_text()?);
+
+                return Ok(Opened::Value(N::scalar(value, self.span_from(start))));
+            }
+            Some('[') => Container::Array,
+            Some('(') => Container::Tuple,
+            Some('{') => Container::Object,
+            Some('@') => Container::Struct(String::new()),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let value = Value::Number(self.decode_number()?);
+
+                return Ok(Opened::Value(N::scalar(value, self.span_from(start))));
+            }
+            Some(c) if is_identifier_start(c) => {
+                let value = self.decode_keyword()?;
+
+                return Ok(Opened::Value(N::scalar(value, self.span_from(start))));
+            }
+            Some(c) => return Err(self.error(format!("Unexpected character `{}`", c))),
+            None => return Err(self.error("Unexpected end of input")),
+        };
+
+        if depth >= self.options.max_depth {
+            return Err(self.error(format!(
+                "Cannot decode value nested deeper than {} levels",
+                self.options.max_depth
+            )));
+        }
+
+        self.next();
+
+        let container = match container {
+            // The name in `@Name(...)` is optional
+            Container::Struct(_) => {
+                let name = match self.peek() {
+                    Some(c) if is_identifier_start(c) => self.decode_identifier()?.to_string(),
+                    _ => String::new(),
+                };
+
+                self.expect('(')?;
+
+                Container::Struct(name)
+            }
+            container => container,
+        };
+
+        self.skip_whitespace()?;
+
+        let frame = Frame {
+            container,
+            start,
+            item: start,
+            items: Vec::new(),
+            entries: BTreeMap::new(),
+            key: None,
+        };
+
+        if self.peek() == Some(frame.container.close()) {
+            self.next();
+
+            let span = self.span_from(start);
+
+            return Ok(Opened::Value(frame.finish(span)));
+        }
+
+        Ok(Opened::Container(frame))
+    }
+
+    /// Starts the next item of `frame`, with the key or field name that comes
+    /// first in objects and structs
+    fn decode_item<N: Node>(&mut self, frame: &mut Frame<N>, depth: usize) -> Result<Opened<N>> {
+        frame.item = self.mark();
+
+        self.skip_whitespace()?;
+
+        let mark = self.mark();
+
+        match frame.container {
+            Container::Array | Container::Tuple => {}
+            Container::Object => {
+                // Keys that are plain identifiers may be written without quotes.
+                // Keywords are keys too here, `{true: 1}` has the key `"true"`.
+                let key = match self.peek() {
+                    Some(c) if is_identifier_start(c) => self.decode_identifier()?.to_string(),
+                    Some('"' | '\'') => self.decode_text()?,
+                    Some(c) => return Err(self.error(format!("Expected a key, found `{}`", c))),
+                    None => return Err(self.error("Expected a key, found end of input")),
+                };
+
+                if self.options.duplicate_keys == DuplicateKeys::Error
+                    && frame.entries.contains_key(&key)
+                {
+                    return Err(self.error_from(format!("Duplicate key `{}`", key), mark));
+                }
+
+                frame.key = Some((key, self.span_from(mark)));
+
+                self.skip_whitespace()?;
+                self.expect(':')?;
+                self.skip_whitespace()?;
+            }
+            Container::Struct(_) => {
+                // Names that aren't identifiers are written as quoted text
+                let name = match self.peek() {
+                    Some('"' | '\'') => self.decode_text()?,
+                    _ => self.decode_identifier()?.to_string(),
+                };
+
+                if self.options.duplicate_keys == DuplicateKeys::Error
+                    && frame.entries.contains_key(&name)
+                {
+                    return Err(self.error_from(format!("Duplicate field `{}`", name), mark));
+                }
+
+                frame.key = Some((name, self.span_from(mark)));
+
+                self.skip_whitespace()?;
+                self.expect('=')?;
+                self.skip_whitespace()?;
+            }
+        }
+
+        self.open_value(depth)
+    }
+
+    /// Adds a decoded item to `frame`, as an entry if its key came before it
+    fn push_item<N: Node>(&self, frame: &mut Frame<N>, node: N) {
+        match frame.key.take() {
+            Some((key, span)) => self.insert_entry(&mut frame.entries, key, N::entry(span, node)),
+            None => frame.items.push(node),
+        }
+    }
+
+    /// Hands an error within an item of `frame` to `recover`. An array or
+    /// tuple item that failed is left as `Null` to keep the indices of the
+    /// ones that follow, a failed entry or field is left out.
+    fn recover_item<N: Node>(&mut self, error: Error, frame: &mut Frame<N>) -> Result<()> {
+        self.recover(error, frame.item)?;
+
+        frame.key = None;
+
+        if let Container::Array | Container::Tuple = frame.container {
+            let span = self.span_from(frame.item);
+
+            frame.items.push(N::scalar(Value::Null, span));
+        }
+
+        Ok(())
+    }
+
+    /// `decode_separator`, which when collecting errors skips a bad separator
+    /// up to the next one. Returns `true` if the container is done, which
+    /// also happens at a mismatched closing character or the end of input.
+    fn decode_next(&mut self, close: char) -> Result<bool> {
+        let error = match self.decode_separator(close) {
+            Ok(closed) => return Ok(closed),
+            Err(error) => error,
+        };
+
+        let mark = self.mark();
+
+        self.recover(error, mark)?;
+
+        match self.peek() {
+            Some(c) if c == ',' || c == close => self.decode_separator(close),
+            _ => Ok(true),
+        }
+    }
+
+    /// Consumes a separator comma and/or the closing character, returns `true`
+    /// if the container was closed
+    fn decode_separator(&mut self, close: char) -> Result<bool> {
+        self.skip_whitespace()?;
+
+        match self.peek() {
+            Some(',') => {
+                self.next();
+                self.skip_whitespace()?;
+
+                if self.peek() == Some(close) {
+                    self.next();
+
+                    return Ok(true);
+                }
+
+                Ok(false)
+            }
+            Some(c) if c == close => {
+                self.next();
+
+                Ok(true)
+            }
+            Some(c) => Err(self.error(format!("Expected `,` or `{}`, found `{}`", close, c))),
+            None => Err(self.error(format!("Expected `,` or `{}`, found end of input", close))),
+        }
+    }
+
+    /// Adds an object entry or struct field, a repeated key was already
+    /// rejected unless `duplicate_keys` lets one of the values win
+    fn insert_entry<E>(&self, entries: &mut BTreeMap<String, E>, key: String, value: E) {
+        match self.options.duplicate_keys {
+            DuplicateKeys::FirstWins => {
+                entries.entry(key).or_insert(value);
+            }
+            _ => {
+                entries.insert(key, value);
+            }
+        }
+    }
+}
+
+fn digit_name(radix: u32) -> &'static str {
+    match radix {
+        16 => "hexadecimal digit",
+        8 => "octal digit",
+        2 => "binary digit",
+        _ => "digit",
+    }
+}
+
+/// Removes `_` digit separators so the literal can be handed to `str::parse`
+fn strip_separators(literal: &str) -> String {
+    literal.chars().filter(|c| *c != '_').collect()
+}
+
+/// Whether `value` keeps every digit written in `literal`, including trailing
+/// zeros past the single one of `1.0` that an `f64` is written with anyway
+fn is_exact(literal: &str, value: f64) -> bool {
+    if !value.is_finite() {
+        return false;
+    }
+
+    let mantissa = literal.split(['e', 'E']).next().unwrap_or_default();
+
+    if let Some((_, fraction)) = mantissa.split_once('.') {
+        if fraction.len() > 1 && fraction.ends_with('0') {
+            return false;
+        }
+    }
+
+    let shortest = format!("{:e}", value);
+
+    significant_digits(literal) == significant_digits(&shortest)
+}
+
+/// Mantissa digits without the sign, decimal point, exponent and the leading
+/// and trailing zeros
+fn significant_digits(literal: &str) -> String {
+    let mantissa = literal.split(['e', 'E']).next().unwrap_or_default();
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+
+    digits.trim_matches('0').to_string()
+}
+
+/// Parser fed the input in chunks, as it arrives. Chunks may split the input
+/// anywhere, even inside a character.
+///
+/// This isn't an incremental parser: the input is buffered, and a light scan
+/// over each chunk only tells when a whole top-level value has arrived. The
+/// value is then decoded in one pass over its bytes by `take`, which keeps
+/// whatever followed it for the next value, or by `finish` at the end of the
+/// input. Error positions are relative to all the input fed, across values.
+#[derive(Debug, Clone, Default)]
+pub struct StreamParser {
+    options: DecodeOptions,
+    buffer: Vec<u8>,
+    valid: usize,   // Bytes checked to be UTF-8
+    scanned: usize, // Bytes the scan went through
+    scan: Scan,
+    depth: usize,
+    complete: bool,
+    consumed: Consumed,
+}
+
+/// Input `StreamParser::take` already dropped from the buffer, to keep error
+/// positions global
+#[derive(Debug, Clone, Default)]
+struct Consumed {
+    bytes: usize,
+    lines: usize,
+    /// Characters on the last line
+    columns: usize,
+    /// End of the last line, enough of it to show as error context
+    tail: String,
+}
+
+/// Characters of `Consumed::tail` kept, more than errors show of a line
+const TAIL_CHARS: usize = 64;
+
+/// Where the scan of a `StreamParser` is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Scan {
+    /// Between tokens, or inside a token within a container
+    #[default]
+    Between,
+    /// Inside a number, keyword or struct name at the top level
+    Token,
+    Text {
+        quote: u8,
+        escaped: bool,
+    },
+    Block,
+    LineComment,
+    BlockComment,
+}
+
+impl StreamParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: DecodeOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Appends the next chunk of input, failing early on invalid UTF-8
+    pub fn feed(&mut self, input: impl AsRef<[u8]>) -> Result<()> {
+        self.buffer.extend_from_slice(input.as_ref());
+
+        if let Err(error) = core::str::from_utf8(&self.buffer[self.valid..]) {
+            self.valid += error.valid_up_to();
+
+            // Anything but a character cut off by the end of the chunk
+            if error.error_len().is_some() {
+                return Err(self.utf8_error());
+            }
+        } else {
+            self.valid = self.buffer.len();
+        }
+
+        self.scan();
+
+        Ok(())
+    }
+
+    /// Whether a whole top-level value was fed. A value that isn't followed
+    /// by anything, like `12` which could go on as `123`, only counts once
+    /// whitespace follows it.
+    pub fn poll_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Decodes the top-level value `poll_complete` saw and drops it from the
+    /// buffer. Whatever was fed after it stays, and is scanned as the start
+    /// of the next value, so framed values can be taken one by one.
+    pub fn take<T: celkit_core::Deserialize>(&mut self) -> Result<T> {
+        if !self.complete {
+            return Err(Error::new("No complete value to take"));
+        }
+
+        let end = self.scanned;
+        let result = match core::str::from_utf8(&self.buffer[..end]) {
+            Ok(input) => self.decode(input),
+            Err(_) => Err(self.utf8_error()),
+        };
+
+        let taken: Vec<u8> = self.buffer.drain(..end).collect();
+
+        self.consume(core::str::from_utf8(&taken).unwrap_or_default());
+        self.valid -= end;
+        self.scanned = 0;
+        self.scan = Scan::Between;
+        self.depth = 0;
+        self.complete = false;
+        self.scan();
+
+        result
+    }
+
+    /// Decodes the rest of the input fed, which must hold exactly one value
+    pub fn finish<T: celkit_core::Deserialize>(self) -> Result<T> {
+        if self.valid < self.buffer.len() {
+            return Err(self.utf8_error());
+        }
+
+        let input = core::str::from_utf8(&self.buffer).map_err(|_| self.utf8_error())?;
+
+        self.decode(input)
+    }
+
+    fn decode<T: celkit_core::Deserialize>(&self, input: &str) -> Result<T> {
+        Decoder::new(input, &self.options)
+            .decode_into(Extent::Document)
+            .map_err(|error| self.global_error(error, input))
+    }
+
+    /// Moves the position of an error in `input`, the start of the buffer,
+    /// past the input already taken, and fills in its context
+    fn global_error(&self, mut error: Error, input: &str) -> Error {
+        let consumed = &self.consumed;
+
+        error.offset = error.offset.map(|offset| offset + consumed.bytes);
+
+        let (Some(line), Some(column)) = (error.line, error.column) else {
+            return error;
+        };
+
+        if line > 1 || consumed.columns == 0 {
+            error = error.with_source(input);
+            error.line = Some(line + consumed.lines);
+
+            return error;
+        }
+
+        // The first line starts on the last line taken, so the context is
+        // taken from the two joined, counting only the part of it kept
+        let tail_chars = consumed.tail.chars().count();
+        let mut source = consumed.tail.clone();
+
+        source.push_str(input);
+        error.column = Some(column + tail_chars);
+        error = error.with_source(&source);
+        error.line = Some(1 + consumed.lines);
+        error.column = Some(column + consumed.columns);
+
+        error
+    }
+
+    /// Records `taken`, just dropped from the start of the buffer
+    fn consume(&mut self, taken: &str) {
+        let consumed = &mut self.consumed;
+
+        // Columns don't count a leading byte order mark
+        let start = consumed.bytes == 0;
+        consumed.bytes += taken.len();
+
+        let taken = if start {
+            taken.strip_prefix('\u{FEFF}').unwrap_or(taken)
+        } else {
+            taken
+        };
+
+        let last = match taken.rfind('\n') {
+            Some(i) => {
+                consumed.lines += taken.matches('\n').count();
+                consumed.columns = 0;
+                consumed.tail.clear();
+
+                &taken[i + 1..]
+            }
+            None => taken,
+        };
+
+        consumed.columns += last.chars().count();
+        consumed.tail.push_str(last);
+
+        let extra = consumed.tail.chars().count().saturating_sub(TAIL_CHARS);
+
+        if let Some((i, _)) = consumed.tail.char_indices().nth(extra) {
+            consumed.tail.drain(..i);
+        }
+    }
+
+    fn utf8_error(&self) -> Error {
+        // Everything up to `valid` is known to be UTF-8
+        let before = core::str::from_utf8(&self.buffer[..self.valid]).unwrap_or_default();
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let consumed = &self.consumed;
+        let (line, column) = match before.matches('\n').count() {
+            0 => (
+                1 + consumed.lines,
+                consumed.columns + before.chars().count() + 1,
+            ),
+            lines => (
+                lines + 1 + consumed.lines,
+                before[line_start..].chars().count() + 1,
+            ),
+        };
+
+        Error::with_position("Invalid UTF-8", line, column)
+            .with_span(consumed.bytes + self.valid, 1)
+    }
+
+    fn at_start(&self) -> bool {
+        self.scanned == 0 && self.consumed.bytes == 0
+    }
+
+    /// Advances through the fed input until the top-level value is complete,
+    /// stopping early where telling what comes next needs more input
+    fn scan(&mut self) {
+        while !self.complete && self.scanned < self.buffer.len() {
+            let rest = &self.buffer[self.scanned..];
+            let byte = rest[0];
+
+            let step = match self.scan {
+                Scan::Between => match byte {
+                    // A byte order mark at the very start is skipped
+                    0xEF if self.at_start() && rest.len() < 3 => return,
+                    0xEF if self.at_start() && rest.starts_with("\u{FEFF}".as_bytes()) => 3,
+                    // `""` may be an empty text or the start of a `"""` block
+                    b'"' if rest.len() < 3 => return,
+                    b'"' if rest.starts_with(b"\"\"\"") => {
+                        self.scan = Scan::Block;
+
+                        3
+                    }
+                    b'"' | b'\'' => {
+                        self.scan = Scan::Text {
+                            quote: byte,
+                            escaped: false,
+                        };
+
+                        1
+                    }
+                    b'/' if rest.len() < 2 => return,
+                    b'/' if rest[1] == b'/' => {
+                        self.scan = Scan::LineComment;
+
+                        2
+                    }
+                    b'/' if rest[1] == b'*' => {
+                        self.scan = Scan::BlockComment;
+
+                        2
+                    }
+                    b'#' if self.options.hash_comments => {
+                        self.scan = Scan::LineComment;
+
+                        1
+                    }
+                    b'[' | b'(' | b'{' => {
+                        self.depth += 1;
+
+                        1
+                    }
+                    b']' | b')' | b'}' => {
+                        self.depth = self.depth.saturating_sub(1);
+                        self.complete = self.depth == 0;
+
+                        1
+                    }
+                    b' ' | b'\t' | b'\n' | b'\r' => 1,
+                    _ if self.depth == 0 => {
+                        self.scan = Scan::Token;
+
+                        1
+                    }
+                    _ => 1,
+                },
+                Scan::Token => match byte {
+                    // The opening of a named struct, as in `@Name(`
+                    b'[' | b'(' | b'{' => {
+                        self.scan = Scan::Between;
+                        self.depth += 1;
+
+                        1
+                    }
+                    b' ' | b'\t' | b'\n' | b'\r' | b'/' | b'#' | b']' | b')' | b'}' | b',' => {
+                        self.complete = true;
+
+                        0
+                    }
+                    _ => 1,
+                },
+                Scan::Text { quote, escaped } => {
+                    if escaped {
+                        self.scan = Scan::Text {
+                            quote,
+                            escaped: false,
+                        };
+                    } else if byte == b'\\' {
+                        self.scan = Scan::Text {
+                            quote,
+                            escaped: true,
+                        };
+                    } else if byte == quote {
+                        self.scan = Scan::Between;
+                        self.complete = self.depth == 0;
+                    }
+
+                    1
+                }
+                Scan::Block if rest.starts_with(b"\"\"\"") => {
+                    self.scan = Scan::Between;
+                    self.complete = self.depth == 0;
+
+                    3
+                }
+                Scan::Block if byte == b'"' && rest.len() < 3 => return,
+                Scan::LineComment if byte == b'\n' => {
+                    self.scan = Scan::Between;
+
+                    1
+                }
+                Scan::BlockComment if rest.starts_with(b"*/") => {
+                    self.scan = Scan::Between;
+
+                    2
+                }
+                Scan::BlockComment if byte == b'*' && rest.len() < 2 => return,
+                Scan::Block | Scan::LineComment | Scan::BlockComment => 1,
+            };
+
+            self.scanned += step;
+        }
+    }
+}
+
+/// Parses `input` into a `Value` without any typed decoding, the same as
+/// `from_string::<Value>`
+pub fn parse(input: &str) -> Result<Value> {
+    parse_with_options(input, &DecodeOptions::default())
+}
+
+pub fn parse_with_options(input: &str, options: &DecodeOptions) -> Result<Value> {
+    Decoder::new(input, options)
+        .decode()
+        .map_err(|error| error.with_source(input))
+}
+
+/// Parses `input` keeping the span of every value, key and field name, for
+/// tools that point back into the source
+pub fn parse_spanned(input: &str) -> Result<Spanned<SpannedValue>> {
+    parse_spanned_with_options(input, &DecodeOptions::default())
+}
+
+pub fn parse_spanned_with_options(
+    input: &str,
+    options: &DecodeOptions,
+) -> Result<Spanned<SpannedValue>> {
+    Decoder::new(input, options)
+        .decode()
+        .map_err(|error| error.with_source(input))
+}
+
+/// Parses `input` going on after syntax errors inside containers, to report
+/// all of them at once. Parsing picks up again at the next `,` or closing
+/// character, array items that failed are left as `Null` and object entries
+/// and struct fields that failed are left out. The value is `None` when the
+/// input as a whole couldn't be parsed.
+pub fn parse_all_errors(input: &str) -> (Option<Value>, Vec<Error>) {
+    parse_all_errors_with_options(input, &DecodeOptions::default())
+}
+
+pub fn parse_all_errors_with_options(
+    input: &str,
+    options: &DecodeOptions,
+) -> (Option<Value>, Vec<Error>) {
+    let (value, errors) = Decoder::new(input, options).decode_recovering();
+
+    let errors = errors
+        .into_iter()
+        .map(|error| error.with_source(input))
+        .collect();
+
+    (value, errors)
+}
+
+pub fn get_path<T: celkit_core::Deserialize>(input: &str, path: &str) -> Result<Option<T>> {
+    get_path_with_options(input, path, &DecodeOptions::default())
+}
+
+/// Decodes only the value at `path`, like `/servers/0/host`, which uses the
+/// same syntax as `Value::pointer`. Values off the path are skipped without
+/// being built or validated, and so is everything after the target, so a
+/// malformed document may still give a result. The first entry with a key
+/// is the one followed.
+pub fn get_path_with_options<T: celkit_core::Deserialize>(
+    input: &str,
+    path: &str,
+    options: &DecodeOptions,
+) -> Result<Option<T>> {
+    let segments =
+        split_path(path).ok_or_else(|| Error::new(format!("Invalid path `{}`", path)))?;
+
+    let mut decoder = Decoder::new(input, options);
+
+    let value = match decoder.seek(&segments) {
+        Ok(true) => decoder.decode_into(Extent::Value),
+        Ok(false) => return Ok(None),
+        Err(error) => Err(error),
+    };
+
+    value.map(Some).map_err(|error| error.with_source(input))
+}
+
+pub fn from_string<T: celkit_core::Deserialize>(input: &str) -> Result<T> {
+    from_string_with_options(input, &DecodeOptions::default())
+}
+
+pub fn from_string_with_options<T: celkit_core::Deserialize>(
+    input: &str,
+    options: &DecodeOptions,
+) -> Result<T> {
+    Decoder::new(input, options)
+        .decode_into(Extent::Document)
+        .map_err(|error| error.with_source(input))
+}
+
+pub fn from_string_prefix<T: celkit_core::Deserialize>(input: &str) -> Result<(T, &str)> {
+    from_string_prefix_with_options(input, &DecodeOptions::default())
+}
+
+/// Decodes the document at the start of `input` and returns what follows it,
+/// past the whitespace and comments after the document
+pub fn from_string_prefix_with_options<'a, T: celkit_core::Deserialize>(
+    input: &'a str,
+    options: &DecodeOptions,
+) -> Result<(T, &'a str)> {
+    let mut decoder = Decoder::new(input, options);
+
+    let value = decoder
+        .decode_into(Extent::Prefix)
+        .map_err(|error| error.with_source(input))?;
+
+    Ok((value, &input[decoder.position..]))
+}
+
+#[cfg(feature = "std")]
+pub fn from_reader<R: std::io::Read, T: celkit_core::Deserialize>(reader: R) -> Result<T> {
+    from_reader_with_options(reader, &DecodeOptions::default())
+}
+
+/// Reads `reader` to the end in chunks, feeding them to a `StreamParser`
+#[cfg(feature = "std")]
+pub fn from_reader_with_options<R: std::io::Read, T: celkit_core::Deserialize>(
+    mut reader: R,
+    options: &DecodeOptions,
+) -> Result<T> {
+    let mut parser = StreamParser::with_options(options.clone());
+    let mut chunk = [0; 8 * 1024];
+    let mut read = 0;
+
+    loop {
+        let length = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(length) => length,
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(error) => {
+                return Err(Error::new(format!(
+                    "Failed to read input after {} bytes: {}",
+                    read, error
+                )));
+            }
+        };
+
+        parser.feed(&chunk[..length])?;
+        read += length;
+    }
+
+    parser.finish()
+}
+
+#[cfg(feature = "std")]
+pub fn from_file<T: celkit_core::Deserialize>(path: impl AsRef<std::path::Path>) -> Result<T> {
+    let path = path.as_ref();
+    let file = std::fs::File::open(path)
+        .map_err(|error| Error::new(format!("Failed to open `{}`: {}", path.display(), error)))?;
+
+    from_reader(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(input: &str) -> Number {
+        match parse(input).unwrap() {
+            Value::Number(number) => number,
+            value => panic!("Expected number, found {:?}", value),
+        }
+    }
+
+    fn is_big(number: &Number, digits: &str) -> bool {
+        matches!(number, Number::Big(big) if big == digits)
+    }
+
+    #[test]
+    fn inexact_literals_keep_their_digits() {
+        assert!(is_big(&number("1.230"), "1.230"));
+        assert!(is_big(&number("0.10"), "0.10"));
+        assert!(is_big(
+            &number("1.2345678901234567890123456789"),
+            "1.2345678901234567890123456789"
+        ));
+
+        let integer = "123456789012345678901234567890123456789012345678901234567890";
+
+        assert!(is_big(&number(integer), integer));
+        assert!(is_big(
+            &number("1000000000000000000000000000000000000000"),
+            "1000000000000000000000000000000000000000"
+        ));
+    }
+
+    #[test]
+    fn exact_literals_stay_floats() {
+        assert!(matches!(number("1.0"), Number::F64(n) if n == 1.0));
+        assert!(matches!(number("0.1"), Number::F64(n) if n == 0.1));
+        assert!(matches!(number("1.5e3"), Number::F64(n) if n == 1500.0));
+        assert!(matches!(number("1_000.5"), Number::F64(n) if n == 1000.5));
+    }
+
+    #[test]
+    fn big_digits_round_trip_byte_for_byte() {
+        let input =
+            "[1.230,-0.000100,123456789012345678901234567890123456789012345678901234567890]";
+        let value = parse(input).unwrap();
+
+        assert_eq!(crate::to_mini_string(&value).unwrap(), input);
+    }
+
+    #[test]
+    fn precision_can_be_given_up() {
+        let options = DecodeOptions::new().preserve_precision(false);
+        let value = parse_with_options("1.230", &options).unwrap();
+
+        assert!(matches!(value, Value::Number(Number::F64(n)) if n == 1.23));
+    }
+
+    use alloc::vec;
+
+    fn feed_bytes(parser: &mut StreamParser, input: &str) {
+        // One byte at a time, splitting characters, escapes and comments
+        for byte in input.as_bytes() {
+            parser.feed([*byte]).unwrap();
+        }
+    }
+
+    #[test]
+    fn stream_takes_framed_values() {
+        let mut parser = StreamParser::new();
+        let mut taken = Vec::new();
+
+        for byte in "{\"a\":\"\\\"é\"} /* ] */ [1, 2]\n'x' 12".as_bytes() {
+            parser.feed([*byte]).unwrap();
+
+            while parser.poll_complete() {
+                taken.push(parser.take::<Value>().unwrap());
+            }
+        }
+
+        assert_eq!(
+            taken,
+            vec![
+                parse("{\"a\": \"\\\"é\"}").unwrap(),
+                parse("[1, 2]").unwrap(),
+                parse("'x'").unwrap(),
+            ]
+        );
+        // Nothing follows the last number to tell it's whole
+        assert_eq!(parser.finish::<u8>().unwrap(), 12);
+    }
+
+    #[test]
+    fn stream_waits_for_the_end_of_a_value() {
+        let mut parser = StreamParser::new();
+
+        feed_bytes(&mut parser, "[1, [2], \"]\"");
+
+        assert!(!parser.poll_complete());
+        assert!(parser.take::<Value>().is_err());
+        assert!(parser.finish::<Value>().is_err());
+    }
+
+    #[test]
+    fn stream_errors_are_global() {
+        let mut parser = StreamParser::new();
+
+        feed_bytes(&mut parser, "[1]\n[2] [3 4] ");
+
+        parser.take::<Value>().unwrap();
+        parser.take::<Value>().unwrap();
+
+        let error = parser.take::<Value>().unwrap_err();
+
+        assert_eq!((error.line, error.column), (Some(2), Some(8)));
+        assert_eq!(error.span(), Some(11..12));
+        assert_eq!(error.context.as_deref(), Some("[2] [3 4]"));
+    }
+
+    #[test]
+    fn stream_utf8_errors_are_global() {
+        let mut parser = StreamParser::new();
+
+        feed_bytes(&mut parser, "1\n2 ");
+        parser.take::<Value>().unwrap();
+
+        let error = parser.feed(b"\xFF").unwrap_err();
+
+        assert_eq!((error.line, error.column), (Some(2), Some(3)));
+        assert_eq!(error.span(), Some(4..5));
+    }
+
+    celkit_core::impl_for_struct! {
+        #[derive(Debug, PartialEq, Default)]
+        struct Client {
+            retries: u8,
+        }
+    }
+
+    celkit_core::impl_for_struct! {
+        #[derive(Debug, PartialEq)]
+        struct ServerConfig {
+            client: Client,
+            fallback: Option<Client>,
+        }
+    }
+
+    #[test]
+    fn strict_struct_names_checks_nested_structs() {
+        let options = DecodeOptions::new().strict_struct_names(true);
+        let decode = |input| from_string_with_options::<ServerConfig>(input, &options);
+
+        assert!(decode("@ServerConfig(client=@Client(retries=1),fallback=@(retries=2))").is_ok());
+        assert!(decode("@Wrong(client=@Client(retries=1),fallback=null)").is_err());
+
+        let error = decode("@ServerConfig(client=@Wrong(retries=1),fallback=null)").unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Invalid `client` field in ServerConfig: Expected struct `Client`, found `@Wrong`"
+        );
+        assert!(decode("@(client=@(retries=1),fallback=@Wrong(retries=2))").is_err());
+    }
+
+    #[test]
+    fn struct_names_are_not_checked_by_default() {
+        let config: ServerConfig =
+            from_string("@Wrong(client=@Other(retries=1),fallback=null)").unwrap();
+
+        assert_eq!(config.client, Client { retries: 1 });
+    }
+
+    /// Version 2 of a settings file, `client` and `timeout` were added
+    #[derive(Debug, PartialEq)]
+    struct Settings {
+        name: String,
+        client: Client,
+        timeout: u32,
+    }
+
+    impl Deserialize for Settings {
+        fn deserialize(value: Value) -> Result<Self> {
+            Self::deserialize_with(value, &DeserializeOptions::default())
+        }
+
+        fn deserialize_with(value: Value, options: &DeserializeOptions) -> Result<Self> {
+            let (Value::Struct(_, mut fields) | Value::Object(mut fields)) = value else {
+                return Err(Error::new("Expected struct for Settings"));
+            };
+
+            Ok(Settings {
+                name: celkit_core::struct_field(&mut fields, "name", "Settings", options)?,
+                client: celkit_core::field_or_default(&mut fields, "client", "Settings", options)?,
+                timeout: celkit_core::struct_field(&mut fields, "timeout", "Settings", options)?,
+            })
+        }
+    }
+
+    #[test]
+    fn missing_fields_fall_back_to_defaults() {
+        let options = DecodeOptions::new().missing_fields(MissingFields::UseDefault);
+        let settings: Settings = from_string_with_options("@(name = 'v1')", &options).unwrap();
+
+        assert_eq!(
+            settings,
+            Settings {
+                name: "v1".to_string(),
+                client: Client::default(),
+                timeout: 0,
+            }
+        );
+
+        let settings: Settings = from_string_with_options(
+            "@(name = 'v2', client = @(retries = 3), timeout = 30)",
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(settings.client, Client { retries: 3 });
+        assert_eq!(settings.timeout, 30);
+
+        // Structs from `impl_for_struct` default field by field
+        let config: ServerConfig =
+            from_string_with_options("@(fallback = null)", &options).unwrap();
+
+        assert_eq!(config.client, Client::default());
+    }
+
+    #[test]
+    fn missing_fields_fail_by_default() {
+        let error = from_string::<Settings>("@(name = 'v1', timeout = 30)").unwrap_err();
+
+        assert_eq!(error.message, "Missing `client` field in Settings");
+
+        let error = from_string::<ServerConfig>("@(fallback = null)").unwrap_err();
+
+        assert_eq!(error.message, "Missing `client` field in ServerConfig");
+    }
+
+    #[test]
+    fn unknown_fields_suggest_the_closest_name() {
+        let options = DecodeOptions::new()
+            .missing_fields(MissingFields::UseDefault)
+            .unknown_fields(UnknownFields::Error);
+        let input = "@ServerConfig(\n    client = @Client(retires = 3),\n)";
+        let error = from_string_with_options::<ServerConfig>(input, &options).unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Invalid `client` field in ServerConfig: Unknown `retires` field in Client, \
+             expected one of `retries`, did you mean `retries`?"
+        );
+        assert_eq!(error.path(), Some("/client/retires"));
+        assert_eq!((error.line, error.column), (Some(2), Some(32)));
+
+        // Nothing is suggested for a name that's too far off
+        let error = from_string_with_options::<Client>("@Client(retries = 1, port = 2)", &options)
+            .unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Unknown `port` field in Client, expected one of `retries`"
+        );
+
+        // Unknown fields are ignored by default
+        assert_eq!(
+            from_string::<Client>("@Client(retries = 1, port = 2)").unwrap(),
+            Client { retries: 1 }
+        );
+    }
+
+    #[test]
+    fn objects_and_structs_coerce_both_ways() {
+        let options = DecodeOptions::new().coerce_struct_object(true);
+        let input = "{\"client\": {\"retries\": 2}, \"fallback\": null}";
+        let config: ServerConfig = from_string_with_options(input, &options).unwrap();
+
+        assert_eq!(config.client, Client { retries: 2 });
+
+        let ports: BTreeMap<String, u16> =
+            from_string_with_options("@Ports(http = 80, https = 443)", &options).unwrap();
+
+        assert_eq!(ports["https"], 443);
+
+        // Both are errors without the option
+        assert_eq!(
+            from_string::<ServerConfig>(input).unwrap_err().message,
+            "Expected struct for ServerConfig, found object"
+        );
+        assert_eq!(
+            from_string::<BTreeMap<String, u16>>("@Ports(http = 80)")
+                .unwrap_err()
+                .message,
+            "Expected object, found struct"
+        );
+
+        // Decoding from a value goes by the same option
+        let value = Value::Object(BTreeMap::from([(String::from("retries"), Value::from(4))]));
+
+        assert!(celkit_core::from_value::<Client>(value.clone()).is_err());
+        assert_eq!(
+            celkit_core::from_value_with_options::<Client>(value, &options.deserialize).unwrap(),
+            Client { retries: 4 }
+        );
+    }
+
+    #[test]
+    fn fields_match_ignoring_case() {
+        let options = DecodeOptions::new().case_insensitive_fields(true);
+        let input = "@SERVERCONFIG(CLIENT = @CLIENT(RETRIES = 3), FALLBACK = @(Retries = 1))";
+        let config: ServerConfig = from_string_with_options(input, &options).unwrap();
+
+        assert_eq!(
+            config,
+            ServerConfig {
+                client: Client { retries: 3 },
+                fallback: Some(Client { retries: 1 }),
+            }
+        );
+
+        let error = from_string::<ServerConfig>(input).unwrap_err();
+
+        assert_eq!(error.message, "Missing `client` field in ServerConfig");
+
+        // Map keys keep their spelling
+        let ports: BTreeMap<String, u16> =
+            from_string_with_options("{\"HTTP\": 80}", &options).unwrap();
+
+        assert_eq!(ports.keys().collect::<Vec<_>>(), ["HTTP"]);
+    }
+
+    #[test]
+    fn fields_differing_only_in_case_conflict() {
+        let options = DecodeOptions::new().case_insensitive_fields(true);
+        let error =
+            from_string_with_options::<Client>("@Client(retries = 1, Retries = 2)", &options)
+                .unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Fields `Retries` and `retries` in Client both match `retries` ignoring case"
+        );
+
+        // Whatever the duplicate key policy
+        let options = options.duplicate_keys(DuplicateKeys::LastWins);
+
+        assert!(
+            from_string_with_options::<Client>("@(RETRIES = 1, retries = 2)", &options).is_err()
+        );
+
+        // Keys spelled the same way are duplicates as usual
+        assert_eq!(
+            from_string_with_options::<Client>("@(retries = 1, retries = 2)", &options).unwrap(),
+            Client { retries: 2 }
+        );
+    }
+
+    #[test]
+    fn range_errors_point_at_the_value() {
+        let input = "{\n    \"a\": [1, 65536],\n}";
+        let error = from_string::<BTreeMap<String, Vec<u16>>>(input).unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Invalid item at index 1: Value 65536 out of range for u16"
+        );
+        assert_eq!(error.path(), Some("/a/1"));
+        assert_eq!((error.line, error.column), (Some(2), Some(14)));
+        assert_eq!(error.span(), input.find("65536").map(|i| i..i + 5));
+        assert_eq!(error.context.as_deref(), Some("    \"a\": [1, 65536],"));
+
+        // Struct fields, the root and values found by path are pointed at too
+        let error = from_string::<Client>("@Client(retries = 3.5)").unwrap_err();
+
+        assert_eq!(error.path(), Some("/retries"));
+        assert_eq!(error.span(), Some(18..21));
+
+        let error = from_string::<u8>("  // Comment\n  300").unwrap_err();
+
+        assert_eq!(
+            (error.line, error.column, error.path()),
+            (Some(2), Some(3), None)
+        );
+
+        let error = get_path::<u16>("[0, {\"x\": 70000}]", "/1/x").unwrap_err();
+
+        assert_eq!((error.line, error.column), (Some(1), Some(11)));
+    }
+
+    #[test]
+    fn integral_floats_fit_integers() {
+        assert_eq!(from_string::<u8>("3.0").unwrap(), 3);
+        assert_eq!(from_string::<Vec<u8>>("[1e2, 2.0]").unwrap(), [100, 2]);
+
+        let error = from_string::<u8>("3.5").unwrap_err();
+
+        assert_eq!(error.message, "Value 3.5 is not an integer, expected u8");
+        assert_eq!((error.line, error.column), (Some(1), Some(1)));
+    }
+
+    #[test]
+    fn get_path_goes_through_arrays_and_structs() {
+        let input = r#"{
+            "servers": [
+                @Server(host = "a", ports = (80, 443)),
+                @Server(host = "b", ports = (8080,), "odd key/~" = [true]),
+            ],
+            // Comments and text with delimiters are skipped as well
+            "notes": ["]", '}', """
+                ) ]
+                """],
+            "client": @Client(retries = 3),
+        }"#;
+
+        assert_eq!(
+            get_path::<String>(input, "/servers/1/host").unwrap(),
+            Some("b".to_string())
+        );
+        assert_eq!(
+            get_path::<u16>(input, "/servers/0/ports/1").unwrap(),
+            Some(443)
+        );
+        assert_eq!(
+            get_path::<Vec<bool>>(input, "/servers/1/odd key~1~0").unwrap(),
+            Some(vec![true])
+        );
+        assert_eq!(
+            get_path::<Client>(input, "/client").unwrap(),
+            Some(Client { retries: 3 })
+        );
+        assert_eq!(
+            get_path::<Value>(input, "").unwrap(),
+            Some(parse(input).unwrap())
+        );
+    }
+
+    #[test]
+    fn get_path_misses_are_none() {
+        let input = r#"{"list": [1, 2], "point": @(x = 1), "empty": {}, "text": "a"}"#;
+
+        for path in [
+            "/missing",
+            "/list/2",
+            "/list/x",
+            "/list/-1",
+            "/point/y",
+            "/empty/a",
+            "/text/0",
+            "/list/0/deeper",
+        ] {
+            assert_eq!(get_path::<Value>(input, path).unwrap(), None, "{}", path);
+        }
+
+        assert!(get_path::<Value>(input, "no slash").is_err());
+        assert!(get_path::<String>(input, "/list/0").is_err());
+    }
+
+    #[test]
+    fn get_path_skips_malformed_regions_unchecked() {
+        // Balanced garbage off the path isn't validated
+        let input = r#"{"skip": [1, @@ ::: {x y}, (]], "want": 2}"#;
+
+        assert_eq!(get_path::<u8>(input, "/want").unwrap(), Some(2));
+        assert!(parse(input).is_err());
+
+        // Garbage on the path up to the target still fails, with a position
+        let error = get_path::<u8>(r#"{"a": 1, ::: "want": 2}"#, "/want").unwrap_err();
+
+        assert_eq!(error.message, "Expected a key, found `:`");
+        assert_eq!((error.line, error.column), (Some(1), Some(10)));
+
+        // So does a malformed target
+        assert!(get_path::<Value>(r#"{"want": [1 2]}"#, "/want").is_err());
+    }
+
+    #[test]
+    fn get_path_follows_the_first_duplicate_key() {
+        let input = r#"{"a": {"b": 1}, "a": {"b": 2}}"#;
+        let last_wins = DecodeOptions::new().duplicate_keys(DuplicateKeys::LastWins);
+        let parsed = parse_with_options(input, &last_wins).unwrap();
+
+        assert_eq!(parsed.pointer("/a/b"), Some(&Value::from(2)));
+        assert_eq!(get_path::<u8>(input, "/a/b").unwrap(), Some(1));
+        assert_eq!(
+            get_path_with_options::<u8>(input, "/a/b", &last_wins).unwrap(),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn single_trailing_commas_are_accepted() {
+        let cases = [
+            ("[1, 2, 3,]", "[1, 2, 3]"),
+            ("(1, 'a',)", "(1, 'a')"),
+            ("{\"k\": 1,}", "{\"k\": 1}"),
+            ("@(a = 1,)", "@(a = 1)"),
+            ("[[1,], {\"k\": (2, 3,),},]", "[[1], {\"k\": (2, 3)}]"),
+            ("[1, /* c */ 2, // c\n]", "[1, 2]"),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse(input).unwrap(), parse(expected).unwrap(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn stray_commas_are_rejected() {
+        let cases = [
+            "[,]", "[1,,2]", "[1,,]", "(,)", "{,}", "@(,)", "@Name(,)", "[1] ,",
+        ];
+
+        for input in cases {
+            assert!(parse(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn value_macro_matches_the_parsed_text() {
+        use celkit_core::value;
+
+        let cases = [
+            (value!(null), "null"),
+            (value!(-12), "-12"),
+            (value!(1.5), "1.5"),
+            (value!("a \"quoted\" word"), r#""a \"quoted\" word""#),
+            (value!([1, [true, null], ()]), "[1, [true, null], ()]"),
+            (value!((1, "two", [3.0])), r#"(1, "two", [3.0])"#),
+            (
+                value!({"host": "localhost", "ports": [80, 443], "tls": null}),
+                r#"{"host": "localhost", "ports": [80, 443], "tls": null}"#,
+            ),
+            (
+                value!(@Config(name = "svc", retries = 3, client = @(tags = []))),
+                r#"@Config(name = "svc", retries = 3, client = @(tags = []))"#,
+            ),
+            (
+                value!({"a": {"b": {"c": [{}]}}}),
+                r#"{"a": {"b": {"c": [{}]}}}"#,
+            ),
+        ];
+
+        // The encoded text also compares struct names and number forms
+        let encode = |value: &Value| {
+            crate::encode::mini_encoder(value)
+                .unwrap()
+                .struct_names(true)
+                .encode()
+                .unwrap()
+        };
+
+        for (value, text) in cases {
+            let parsed = parse(text).unwrap();
+
+            assert_eq!(value, parsed, "{}", text);
+            assert_eq!(encode(&value), encode(&parsed), "{}", text);
+        }
+    }
+
+    #[test]
+    fn pretty_output_with_trailing_commas_parses_back() {
+        let value = celkit_core::value!({
+            "empty": [],
+            "items": [1, [2, [3]], {"k": null}],
+            "long": ["aaaaaaaaaaaaaaaaaaaa", "bbbbbbbbbbbbbbbbbbbb", "cccccccccccccccccccc"],
+            "nested": {"a": {"b": {}}},
+            "text": "x"
+        });
+        let value = Value::Array(alloc::vec![
+            value,
+            Value::Tuple(alloc::vec![Value::Null, Value::Boolean(true)]),
+            Value::Struct(
+                "Point".into(),
+                [("x".into(), Value::Tuple(alloc::vec![]))].into()
+            ),
+        ]);
+
+        for max_line_length in [0, 20, 100] {
+            let pretty = crate::encode::pretty_encoder(&value)
+                .unwrap()
+                .trailing_comma(true)
+                .max_line_length(max_line_length)
+                .encode()
+                .unwrap();
+
+            assert!(pretty.ends_with(",\n]"), "{}", pretty);
+            assert_eq!(parse(&pretty).unwrap(), value, "{}", pretty);
+        }
+    }
+
+    #[test]
+    fn megabyte_of_brackets_fails_at_the_depth_limit() {
+        extern crate std;
+
+        let started = std::time::Instant::now();
+
+        for open in ["[", "(", "{\"a\":", "@(a=", "@Name(a=[{\"b\":("] {
+            let input = open.repeat(1024 * 1024 / open.len());
+            let error = parse(&input).unwrap_err();
+
+            assert_eq!(
+                error.message,
+                "Cannot decode value nested deeper than 128 levels"
+            );
+            assert_eq!(error.line, Some(1));
+
+            let (_, errors) = parse_all_errors(&input);
+
+            assert!(errors.iter().any(|e| e.message == error.message));
+            assert!(parse_spanned(&input).is_err());
+        }
+
+        assert_eq!(
+            parse("[".repeat(1024 * 1024).as_str()).unwrap_err().column,
+            Some(129)
+        );
+
+        // Without a limit the parser's own stack keeps it from crashing
+        let options = DecodeOptions::new().max_depth(usize::MAX);
+        let error = parse_with_options(&"[".repeat(1024 * 1024), &options).unwrap_err();
+
+        assert_eq!(error.message, "Unexpected end of input");
+        assert!(started.elapsed() < std::time::Duration::from_secs(30));
+    }
+
+    /// Drops `value` a level at a time, as dropping it outright recurses
+    fn drop_nested(value: Value) {
+        let mut stack = vec![value];
+
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::Array(items) | Value::Tuple(items) => stack.extend(items),
+                Value::Object(map) | Value::Struct(_, map) => stack.extend(map.into_values()),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn very_deep_documents_parse_with_the_limit_raised() {
+        let depth = 200_000;
+        let input = format!("{}0{}", "[".repeat(depth), "]".repeat(depth));
+        let options = DecodeOptions::new().max_depth(depth);
+        let value = parse_with_options(&input, &options).unwrap();
+
+        // Walks down the chain without recursing
+        let mut levels = 0;
+        let mut current = &value;
+
+        while let Value::Array(items) = current {
+            assert_eq!(items.len(), 1);
+
+            levels += 1;
+            current = &items[0];
+        }
+
+        assert_eq!(levels, depth);
+        assert!(matches!(current, Value::Number(_)));
+        drop_nested(value);
+
+        let options = DecodeOptions::new().max_depth(depth - 1);
+        let error = parse_with_options(&input, &options).unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Cannot decode value nested deeper than 199999 levels"
+        );
+        assert_eq!(error.column, Some(depth));
+    }
+
+    #[test]
+    fn depth_limit_counts_every_container() {
+        let options = DecodeOptions::new().max_depth(4);
+        let parse = |input| parse_with_options(input, &options);
+
+        assert!(parse("[({\"a\": @(b = 1)})]").is_ok());
+        assert!(parse("[({\"a\": @(b = [])})]").is_err());
+        assert!(parse("@(a = @(b = @(c = @(d = 1))))").is_ok());
+        assert!(parse("@(a = @(b = @(c = @(d = ()))))").is_err());
+    }
+
+    #[test]
+    fn three_errors_are_reported() {
+        let input = "{\n  \"a\": [1, 2 3],\n  \"b\": @(x = ),\n  \"c\": tru,\n  \"d\": 4,\n}";
+        let (value, errors) = parse_all_errors(input);
+        let positions: Vec<_> = errors
+            .iter()
+            .map(|error| (error.line.unwrap(), error.column.unwrap()))
+            .collect();
+
+        assert_eq!(positions, vec![(2, 14), (3, 14), (4, 8)], "{:?}", errors);
+        assert!(errors.iter().all(|error| error.context.is_some()));
+        // What parsed before each error is kept, the field that failed is left out
+        assert_eq!(
+            value.unwrap(),
+            parse("{\"a\": [1, 2], \"b\": @(), \"d\": 4}").unwrap()
+        );
+    }
+
+    #[test]
+    fn recovery_ends_on_pathological_input() {
+        for input in [
+            ",,,,", "[,,,,", "{::::}", "[1 2 3 4", "@(=,=,=)", ")]}", "[\"", "{\"a\"",
+        ] {
+            let (_, errors) = parse_all_errors(input);
+
+            assert!(!errors.is_empty(), "{}", input);
+            assert!(errors.len() <= input.len() + 1, "{}", input);
+        }
+    }
+
+    /// Reads `data` a few bytes at a time, interrupted before every read and
+    /// failing with `error` once it runs out
+    #[cfg(feature = "std")]
+    struct Trickle {
+        data: &'static [u8],
+        step: usize,
+        interrupted: bool,
+        error: Option<std::io::ErrorKind>,
+    }
+
+    #[cfg(feature = "std")]
+    impl std::io::Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.interrupted = !self.interrupted;
+
+            if self.interrupted {
+                return Err(std::io::ErrorKind::Interrupted.into());
+            }
+
+            if self.data.is_empty() {
+                return match self.error {
+                    Some(kind) => Err(std::io::Error::new(kind, "connection reset")),
+                    None => Ok(0),
+                };
+            }
+
+            let length = self.step.min(self.data.len()).min(buf.len());
+
+            buf[..length].copy_from_slice(&self.data[..length]);
+            self.data = &self.data[length..];
+
+            Ok(length)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn trickle(data: &'static str, error: Option<std::io::ErrorKind>) -> Trickle {
+        Trickle {
+            data: data.as_bytes(),
+            step: 1,
+            interrupted: false,
+            error,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reader_retries_interrupted_reads() {
+        let input = "{\"caf\u{e9}\": [1, \"\\u00e9 \u{1F389}\"], // done\n}";
+        let value: Value = from_reader(trickle(input, None)).unwrap();
+
+        assert_eq!(value, parse(input).unwrap());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reader_errors_say_how_far_reading_got() {
+        let error =
+            from_reader::<_, Value>(trickle("[1, 2", Some(std::io::ErrorKind::ConnectionReset)))
+                .unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Failed to read input after 5 bytes: connection reset"
+        );
+
+        let error = from_reader::<_, Value>(&b"[\"ok\", \"\xC3\x28\"]"[..]).unwrap_err();
+
+        assert_eq!(error.offset, Some(8));
+
+        let error = from_reader::<_, Value>(trickle("[1, 2", None)).unwrap_err();
+
+        assert_eq!((error.line, error.column), (Some(1), Some(6)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn missing_files_are_named() {
+        let error = from_file::<Value>("does/not/exist.cel").unwrap_err();
+
+        assert!(
+            error
+                .message
+                .starts_with("Failed to open `does/not/exist.cel`: "),
+            "{}",
+            error
+        );
+    }
 }
@@ -1,528 +1,2599 @@
+use crate::decode::parse;
+use crate::raw::raw_text;
+use crate::DEFAULT_MAX_DEPTH;
+use alloc::borrow::Cow;
+use alloc::collections::btree_map;
 use celkit_core::internal::sys::*;
+use celkit_core::internal::{Error, Number, Result, Value, WalkControl};
+use celkit_core::{is_bare_identifier, KEYWORDS};
+use core::fmt;
+use core::slice;
+
+/// How the encoders handle NaN and infinite floats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonFiniteMode {
+    /// Fail with an error pointing at the offending number
+    #[default]
+    Error,
+    /// Emit the `nan`, `inf` and `-inf` tokens, which the parser reads back
+    Tokens,
+    /// Emit `null` in place of the number
+    Null,
+}
 
-fn escape_text(input: &str) -> String {
-    let mut output = String::new();
+/// When the encoders quote object keys and struct field names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyStyle {
+    /// Quote every key (`"key": 1`)
+    #[default]
+    AlwaysQuote,
+    /// Leave identifiers bare, but quote the ones that read as keywords
+    /// (`key: 1`, `"true": 1`)
+    QuoteAsNeeded,
+    /// Leave every identifier bare, keywords included (`true: 1`)
+    PreferBare,
+}
 
-    for c in input.chars() {
-        match c {
-            '\x08' => output.push_str("\\b"), // Backspace                \b
-            '\x0C' => output.push_str("\\f"), // Formfeed Page Break      \f
-            '\n' => output.push_str("\\n"),   // Newline (Line Feed)      \n
-            '\r' => output.push_str("\\r"),   // Carriage Return          \r
-            '\t' => output.push_str("\\t"),   // Horizontal Tab           \t
-            '\\' => output.push_str("\\\\"),  // Backslash                \\
-            '"' => output.push_str("\\\""),   // Double quotation mark    \"
-            c if c.is_control() => {
-                output.push_str(&format!("\\u{:04x}", c as u32));
-            }
-            c => output.push(c),
+impl KeyStyle {
+    fn is_bare(self, key: &str) -> bool {
+        match self {
+            KeyStyle::AlwaysQuote => false,
+            KeyStyle::QuoteAsNeeded => is_bare_identifier(key) && !KEYWORDS.contains(&key),
+            KeyStyle::PreferBare => is_bare_identifier(key),
         }
     }
+}
 
-    output
+/// When the pretty encoder spreads containers over multiple lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExpandMode {
+    /// Only when a container doesn't fit within `max_line_length`, packing
+    /// as many items per line as fit
+    #[default]
+    Auto,
+    /// Always, with every item on its own line. Arrays laid out by
+    /// `numbers_per_line` are the exception and keep that many to a line.
+    Always,
 }
 
-/// Minified encoding (single-line)
-mod mini {
-    use crate::encode::escape_text;
-    use celkit_core::internal::sys::*;
-    use celkit_core::internal::{Number, Result, Value};
+/// Line break written by the pretty encoder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
 
-    pub struct Encoder {
-        input: Value,
+impl LineEnding {
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
     }
+}
 
-    impl Encoder {
-        pub fn new(input: Value) -> Self {
-            Self { input }
-        }
+/// Which encoder `to_string_with` uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EncodeStyle {
+    Mini,
+    #[default]
+    Pretty,
+}
 
-        pub fn encode(self) -> Result<String> {
-            self.encode_value(&self.input)
-        }
+/// Comments the pretty encoder writes around a value, see
+/// `PrettyEncoder::comment`. The mini encoder has no room for them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Annotations {
+    /// Lines written above the value, each one as a `//` comment
+    pub leading: Vec<String>,
+    /// Written after the value and its comma, on the same line
+    pub trailing: Option<String>,
+}
 
-        fn encode_null(&self) -> Result<String> {
-            Ok("null".to_string())
-        }
+/// Every encoder option in one value, so formatting policy can be stored and
+/// passed around. Options that only affect layout are ignored by the mini
+/// encoder, see the builder methods of the same name for what each one does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodeOptions {
+    pub style: EncodeStyle,
+    pub indent: String,
+    pub tab_width: usize,
+    pub max_line_length: usize,
+    pub trailing_comma: bool,
+    pub non_finite: NonFiniteMode,
+    pub max_depth: usize,
+    pub exponent_floats: bool,
+    pub sort_keys: bool,
+    pub key_priority: Vec<String>,
+    pub struct_names: bool,
+    pub ascii_only: bool,
+    pub prefer_single_quotes: bool,
+    pub multiline_strings: bool,
+    pub digit_grouping: usize,
+    pub newline: LineEnding,
+    pub final_newline: bool,
+    pub blank_lines_between_top_level: bool,
+    pub key_style: KeyStyle,
+    pub field_style: KeyStyle,
+    pub omit_nulls: bool,
+    pub float_precision: Option<usize>,
+    pub align_struct_fields: bool,
+    pub numbers_per_line: usize,
+    pub align_numbers: bool,
+    pub expand: ExpandMode,
+    pub expand_threshold: usize,
+    #[cfg(feature = "unicode-width")]
+    pub unicode_width: bool,
+}
 
-        fn encode_boolean(&self, value: &bool) -> Result<String> {
-            Ok(value.to_string())
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            style: EncodeStyle::default(),
+            indent: "  ".to_string(),
+            tab_width: 4,
+            max_line_length: 100,
+            trailing_comma: true,
+            non_finite: NonFiniteMode::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            exponent_floats: false,
+            sort_keys: false,
+            key_priority: Vec::new(),
+            struct_names: false,
+            ascii_only: false,
+            prefer_single_quotes: false,
+            multiline_strings: false,
+            digit_grouping: 0,
+            newline: LineEnding::default(),
+            final_newline: false,
+            blank_lines_between_top_level: false,
+            key_style: KeyStyle::default(),
+            field_style: KeyStyle::PreferBare,
+            omit_nulls: false,
+            float_precision: None,
+            align_struct_fields: false,
+            numbers_per_line: 0,
+            align_numbers: false,
+            expand: ExpandMode::default(),
+            expand_threshold: usize::MAX,
+            #[cfg(feature = "unicode-width")]
+            unicode_width: false,
         }
+    }
+}
 
-        fn encode_number(&self, value: &Number) -> Result<String> {
-            Ok(value.to_string())
+impl EncodeOptions {
+    /// Keys emitted first, none when `sort_keys` asks for plain key order
+    fn priority(&self) -> &[String] {
+        if self.sort_keys {
+            return &[];
         }
 
-        fn encode_text(&self, value: &String) -> Result<String> {
-            Ok(format!("\"{}\"", escape_text(value)))
-        }
+        &self.key_priority
+    }
+}
 
-        fn encode_array(&self, value: &Vec<Value>) -> Result<String> {
-            let items: Result<Vec<String>> = value.iter().map(|v| self.encode_value(v)).collect();
-            let items = items?;
+/// Rejects what the encoders won't write before any output is produced:
+/// non-finite numbers when `non_finite` is `Error`, and containers nested
+/// deeper than `max_depth`. The walk is iterative, like the encoders, so it's
+/// safe on values of any depth.
+fn validate(value: &Value, non_finite: NonFiniteMode, max_depth: usize) -> Result<()> {
+    // The walk tracks paths for the error message, so it only runs once the
+    // allocation-free check has found something to report, or the value is
+    // too deep for it
+    if is_encodable(value, non_finite, max_depth.min(MAX_CHECK_DEPTH)) {
+        return Ok(());
+    }
 
-            Ok(format!("[{}]", items.join(",")))
-        }
+    let mut error = None;
 
-        fn encode_tuple(&self, value: &Vec<Value>) -> Result<String> {
-            let members: Result<Vec<String>> = value.iter().map(|v| self.encode_value(v)).collect();
-            let members = members?;
+    value.walk(|path, value| {
+        let message = match value {
+            Value::Number(number) if non_finite == NonFiniteMode::Error && !number.is_finite() => {
+                format!("Cannot encode non-finite number {}", number)
+            }
+            Value::Array(_) | Value::Tuple(_) | Value::Object(_) | Value::Struct(..)
+                if path.depth() >= max_depth =>
+            {
+                format!(
+                    "Cannot encode value nested deeper than {} levels",
+                    max_depth
+                )
+            }
+            _ => return WalkControl::Continue,
+        };
 
-            Ok(format!("({})", members.join(",")))
-        }
+        let location = if path.depth() == 0 {
+            "root".to_string()
+        } else {
+            format!("`{}`", path)
+        };
 
-        fn encode_object(&self, value: &BTreeMap<String, Value>) -> Result<String> {
-            let entries: Result<Vec<String>> = value
-                .iter()
-                .map(|entry| {
-                    Ok(format!(
-                        "\"{}\":{}",
-                        escape_text(entry.0),        // Entry key
-                        self.encode_value(entry.1)?  // Entry value
-                    ))
-                })
-                .collect();
-            let entries = entries?;
+        error = Some(Error::new(format!("{} at {}", message, location)));
 
-            Ok(format!("{{{}}}", entries.join(",")))
-        }
+        WalkControl::Stop
+    });
 
-        fn encode_struct(&self, value: &BTreeMap<String, Value>) -> Result<String> {
-            let fields: Result<Vec<String>> = value
-                .iter()
-                .map(|field| {
-                    Ok(format!(
-                        "{}={}",
-                        field.0,                     // Field name
-                        self.encode_value(field.1)?  // Field value
-                    ))
-                })
-                .collect();
-            let fields = fields?;
+    error.map_or(Ok(()), Err)
+}
 
-            Ok(format!("@({})", fields.join(",")))
+/// Deepest nesting `is_encodable` recurses into. Deeper values fail the check
+/// and are left to the iterative walk.
+const MAX_CHECK_DEPTH: usize = 1024;
+
+/// Recursion stops at containers past `max_depth`, which `validate` caps at
+/// `MAX_CHECK_DEPTH`
+fn is_encodable(value: &Value, non_finite: NonFiniteMode, max_depth: usize) -> bool {
+    match value {
+        Value::Number(number) => non_finite != NonFiniteMode::Error || number.is_finite(),
+        Value::Array(items) | Value::Tuple(items) => {
+            max_depth > 0
+                && items
+                    .iter()
+                    .all(|item| is_encodable(item, non_finite, max_depth - 1))
         }
-
-        fn encode_value(&self, value: &Value) -> Result<String> {
-            match value {
-                Value::Null => self.encode_null(),
-                Value::Boolean(b) => self.encode_boolean(b),
-                Value::Number(n) => self.encode_number(n),
-                Value::Text(t) => self.encode_text(t),
-                Value::Array(a) => self.encode_array(a),
-                Value::Tuple(t) => self.encode_tuple(t),
-                Value::Object(o) => self.encode_object(o),
-                Value::Struct(_, s) => self.encode_struct(s),
-            }
+        Value::Object(map) | Value::Struct(_, map) => {
+            max_depth > 0
+                && map
+                    .values()
+                    .all(|item| is_encodable(item, non_finite, max_depth - 1))
         }
+        _ => true,
     }
 }
 
-/// Prettified encoding (multi-line)
-mod pretty {
-    use crate::encode::escape_text;
-    use celkit_core::internal::sys::*;
-    use celkit_core::internal::{Number, Result, Value};
+fn format_number(
+    number: &Number,
+    mode: NonFiniteMode,
+    exponent_floats: bool,
+    float_precision: Option<usize>,
+) -> String {
+    // `Error` mode never gets here with a non-finite number, see `validate`
+    if !number.is_finite() && mode == NonFiniteMode::Null {
+        return "null".to_string();
+    }
 
-    pub struct Encoder {
-        input: Value,
-        indent_size: usize,
-        max_line_length: usize,
-        trailing_comma: bool,
+    if !number.is_finite() {
+        return number.to_string();
     }
 
-    impl Encoder {
-        pub fn new(input: Value) -> Self {
-            Self {
-                input,
-                indent_size: 2,
-                max_line_length: 100,
-                trailing_comma: true,
-            }
+    let float = match *number {
+        Number::F32(n) => Some(f64::from(n)),
+        Number::F64(n) => Some(n),
+        _ => None,
+    };
+
+    let positional = number.to_string();
+    let fraction_digits = positional
+        .split_once('.')
+        .map_or(0, |(_, digits)| digits.len());
+
+    // Integral floats and ones that are already short enough keep their
+    // shortest form, rounding could only add digits to them
+    let positional = match (float, float_precision) {
+        (Some(n), Some(precision)) if n % 1.0 != 0.0 && fraction_digits > precision => {
+            round_float(n, precision)
         }
+        _ => positional,
+    };
 
-        pub fn indent_size(mut self, size: usize) -> Self {
-            self.indent_size = size;
+    if !exponent_floats {
+        return positional;
+    }
 
-            self
-        }
+    // The exponent form is derived from the rounded value when there's a
+    // precision, so both forms carry the same digits
+    let scientific = match (number, float_precision) {
+        (Number::F32(_) | Number::F64(_), Some(_)) => match positional.parse::<f64>() {
+            Ok(n) => format!("{:e}", n),
+            Err(_) => return positional,
+        },
+        (Number::F32(n), None) => format!("{:e}", n),
+        (Number::F64(n), None) => format!("{:e}", n),
+        _ => return positional,
+    };
+
+    // Only switch notation when it actually saves space, e.g. `1e300`
+    if scientific.len() < positional.len() {
+        scientific
+    } else {
+        positional
+    }
+}
 
-        pub fn max_line_length(mut self, length: usize) -> Self {
-            self.max_line_length = length;
+/// Formats a float that has a fractional part with at most `precision`
+/// fractional digits, keeping the decimal point so it reads back as a float
+fn round_float(number: f64, precision: usize) -> String {
+    // Rounds the exact binary value, so ties are resolved half to even
+    let mut output = format!("{:.*}", precision, number);
 
-            self
-        }
+    if precision == 0 {
+        output.push_str(".0");
 
-        pub fn trailing_comma(mut self, enabled: bool) -> Self {
-            self.trailing_comma = enabled;
+        return output;
+    }
 
-            self
-        }
+    output.truncate(output.trim_end_matches('0').len());
 
-        pub fn encode(self) -> Result<String> {
-            let depth = 0;
+    if output.ends_with('.') {
+        output.push('0');
+    }
 
-            self.encode_value(&self.input, depth)
-        }
+    output
+}
 
-        fn indent(&self, level: usize) -> String {
-            " ".repeat(level * self.indent_size)
-        }
+/// Inserts `_` separators every `size` digits, counting from the right
+fn group_digits(number: &str, size: usize) -> String {
+    let (sign, digits) = match number.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", number),
+    };
+
+    let mut output = String::from(sign);
 
-        fn encode_null(&self) -> Result<String> {
-            Ok("null".to_string())
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % size == 0 {
+            output.push('_');
         }
 
-        fn encode_boolean(&self, value: &bool) -> Result<String> {
-            Ok(value.to_string())
+        output.push(c);
+    }
+
+    output
+}
+
+/// Object entries or struct fields in emission order: the keys listed in
+/// `key_priority` in their listed order, then the rest in key order, leaving
+/// out nulls when `omit_nulls` is enabled. Maps already iterate in key order,
+/// so `sort_keys` only has to drop the priority keys.
+fn ordered_entries<'a>(
+    map: &'a BTreeMap<String, Value>,
+    key_priority: &'a [String],
+    omit_nulls: bool,
+) -> Entries<'a> {
+    Entries {
+        map,
+        key_priority,
+        listed: 0,
+        iter: map.iter(),
+        omit_nulls,
+    }
+}
+
+/// Iterator returned by `ordered_entries`, named so that the encoders can
+/// keep it on their work stacks
+#[derive(Clone)]
+struct Entries<'a> {
+    map: &'a BTreeMap<String, Value>,
+    key_priority: &'a [String],
+    /// How many of the `key_priority` keys were looked up so far
+    listed: usize,
+    iter: btree_map::Iter<'a, String, Value>,
+    omit_nulls: bool,
+}
+
+impl<'a> Entries<'a> {
+    fn is_emitted(&self, value: &Value) -> bool {
+        !self.omit_nulls || !matches!(value, Value::Null)
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = (&'a String, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(key) = self.key_priority.get(self.listed) {
+            let listed_before = self.key_priority[..self.listed].contains(key);
+
+            self.listed += 1;
+
+            if let Some(entry) = self.map.get_key_value(key) {
+                if !listed_before && self.is_emitted(entry.1) {
+                    return Some(entry);
+                }
+            }
         }
 
-        fn encode_number(&self, value: &Number) -> Result<String> {
-            Ok(value.to_string())
+        let key_priority = self.key_priority;
+
+        while let Some(entry) = self.iter.next() {
+            if !key_priority.contains(entry.0) && self.is_emitted(entry.1) {
+                return Some(entry);
+            }
         }
 
-        fn encode_text(&self, value: &String) -> Result<String> {
-            Ok(format!("\"{}\"", escape_text(value)))
+        None
+    }
+}
+
+/// Children of a container still to be written by the encoders, along with
+/// how to write the keys of entries
+#[derive(Clone)]
+enum Children<'a> {
+    Items(slice::Iter<'a, Value>),
+    Entries {
+        entries: Entries<'a>,
+        style: KeyStyle,
+        separator: &'static str,
+    },
+}
+
+/// Key of an entry along with how to write it
+type Key<'a> = (&'a str, KeyStyle, &'static str);
+
+impl<'a> Children<'a> {
+    fn next(&mut self) -> Option<(Option<Key<'a>>, &'a Value)> {
+        match self {
+            Children::Items(items) => items.next().map(|item| (None, item)),
+            Children::Entries {
+                entries,
+                style,
+                separator,
+            } => entries
+                .next()
+                .map(|(key, value)| (Some((key.as_str(), *style, *separator)), value)),
         }
+    }
 
-        fn encode_array(&self, value: &Vec<Value>, depth: usize) -> Result<String> {
-            if value.is_empty() {
-                return Ok("[]".to_string());
-            }
+    /// Number of children left to write
+    fn remaining(&self) -> usize {
+        match self {
+            Children::Items(items) => items.len(),
+            Children::Entries { entries, .. } => entries.clone().count(),
+        }
+    }
+}
 
-            let current_indent = self.indent(depth);
-            let next_indent = self.indent(depth + 1);
+/// Name to emit between `@` and `(`, empty unless `struct_names` is enabled
+fn struct_name(name: &str, struct_names: bool) -> Result<&str> {
+    if !struct_names || name.is_empty() {
+        return Ok("");
+    }
 
-            let mut items = Vec::new();
-            let mut single_line_length = 0;
-            let mut can_fit_single_line = true;
+    if !is_bare_identifier(name) {
+        return Err(Error::new(format!(
+            "Cannot encode struct name `{}`, it's not a valid identifier",
+            name
+        )));
+    }
 
-            single_line_length += 1; // Opening array character "["
+    Ok(name)
+}
 
-            for (i, item) in value.iter().enumerate() {
-                let encoded_item = self.encode_value(item, depth + 1)?;
+/// Writes `text` between quotes, in single quotes when `single_quotes` is
+/// enabled and they need no more escapes than double quotes would
+fn write_quoted<O: Output>(
+    output: &mut O,
+    text: &str,
+    single_quotes: bool,
+    ascii_only: bool,
+) -> Result<()> {
+    let quote = if single_quotes && count_byte(text, b'\'') <= count_byte(text, b'"') {
+        "'"
+    } else {
+        "\""
+    };
+
+    output.emit(quote)?;
+    write_escaped(output, text, quote.as_bytes()[0], ascii_only)?;
+    output.emit(quote)
+}
 
-                items.push(encoded_item);
+fn count_byte(text: &str, byte: u8) -> usize {
+    text.bytes().filter(|&b| b == byte).count()
+}
 
-                if can_fit_single_line {
-                    single_line_length += items[i].len();
+/// Escapes `text` the way the encoders write it between double quotes,
+/// borrowing it when there's nothing to escape
+pub fn escape_text(text: &str) -> Cow<'_, str> {
+    if find_escape(text.as_bytes(), 0, b'"', false).is_none() {
+        return Cow::Borrowed(text);
+    }
 
-                    if i < value.len() - 1 {
-                        single_line_length += 2; // Separator comma and space ", "
-                    }
-                }
-            }
+    let mut output = String::with_capacity(text.len() + 8);
 
-            if can_fit_single_line {
-                single_line_length += 1; // Closing array character "]"
+    // Writing into a `String` can't fail
+    let _ = write_escaped(&mut output, text, b'"', false);
 
-                // It's safe to assume this value is a child (nested) element if
-                // the `depth` is non-zero. So, we add `1` to the length of the line
-                // to account for a possible comma from the parent.
-                let comma_allowance = if depth > 0 { 1 } else { 0 };
+    Cow::Owned(output)
+}
 
-                // Check if it exceeded the line limit
-                if current_indent.len() + single_line_length + comma_allowance
-                    > self.max_line_length
-                {
-                    can_fit_single_line = false;
+/// Writes `input` escaped into `output`, emitting unescaped runs as slices.
+/// Only the `quote` delimiting the text is escaped, the other one is kept.
+fn write_escaped<O: Output>(
+    output: &mut O,
+    input: &str,
+    quote: u8,
+    ascii_only: bool,
+) -> Result<()> {
+    let mut start = 0;
+    let mut next = 0;
+
+    while let Some(i) = find_escape(input.as_bytes(), next, quote, ascii_only) {
+        // Candidates are always ASCII or the first byte of a character
+        let Some(c) = input[i..].chars().next() else {
+            break;
+        };
+
+        next = i + c.len_utf8();
+
+        let escape = match c {
+            '\x08' => Some("\\b"), // Backspace                \b
+            '\x0C' => Some("\\f"), // Formfeed Page Break      \f
+            '\n' => Some("\\n"),   // Newline (Line Feed)      \n
+            '\r' => Some("\\r"),   // Carriage Return          \r
+            '\t' => Some("\\t"),   // Horizontal Tab           \t
+            '\\' => Some("\\\\"),  // Backslash                \\
+            '"' => Some("\\\""),   // Double quotation mark    \"
+            '\'' => Some("\\'"),   // Single quotation mark    \'
+            c if c.is_control() || (ascii_only && !c.is_ascii()) => None,
+            _ => continue,
+        };
+
+        output.emit(&input[start..i])?;
+        start = next;
+
+        match escape {
+            Some(escape) => output.emit(escape)?,
+            None => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    output.emit_fmt(format_args!("\\u{:04x}", unit))?;
                 }
             }
+        }
+    }
 
-            if can_fit_single_line {
-                return Ok(format!("[{}]", items.join(", ")));
-            }
+    output.emit(&input[start..])
+}
 
-            let mut output = String::new();
-            let mut current_line = next_indent.clone();
-            let empty_line_len = next_indent.len();
+/// Position of the first byte from `from` on that may need escaping, see
+/// `is_clean_byte`. Clean stretches are skipped 8 bytes at a time, so long
+/// texts aren't decoded character by character.
+fn find_escape(bytes: &[u8], from: usize, quote: u8, ascii_only: bool) -> Option<usize> {
+    let mut i = from;
 
-            output.push_str("[\n");
+    while let Some(chunk) = bytes[i..].first_chunk::<8>() {
+        if !is_clean_word(u64::from_ne_bytes(*chunk), quote, ascii_only) {
+            break;
+        }
 
-            for (i, encoded_item) in items.into_iter().enumerate() {
-                let mut formatted_item = encoded_item;
+        i += 8;
+    }
 
-                if i < value.len() - 1 || self.trailing_comma {
-                    formatted_item.push_str(", ");
-                }
+    bytes[i..]
+        .iter()
+        .position(|&byte| !is_clean_byte(byte, quote, ascii_only))
+        .map(|position| i + position)
+}
 
-                // Check if this item would fit in the current line
-                if current_line.len() + formatted_item.len() <= self.max_line_length
-                    || current_line.len() <= empty_line_len
-                {
-                    current_line.push_str(&formatted_item);
+/// Whether `byte` can be written as is. `0xC2` lead bytes count as needing
+/// escapes since U+0080 to U+009F are control characters.
+fn is_clean_byte(byte: u8, quote: u8, ascii_only: bool) -> bool {
+    match byte {
+        b'\\' | 0x00..=0x1F | 0x7F | 0xC2 => false,
+        _ if byte == quote => false,
+        0x80.. => !ascii_only,
+        _ => true,
+    }
+}
 
-                    continue;
-                }
+/// `is_clean_byte` for 8 bytes at once
+fn is_clean_word(word: u64, quote: u8, ascii_only: bool) -> bool {
+    const ONES: u64 = u64::from_ne_bytes([0x01; 8]);
+    const HIGHS: u64 = u64::from_ne_bytes([0x80; 8]);
+
+    // Sets the high bit of a lane when it's below `limit`, for limits up to
+    // `0x80`. Lanes past the first match may be set wrongly, which doesn't
+    // matter for telling whether there's any.
+    let below = |word: u64, limit: u8| word.wrapping_sub(ONES * u64::from(limit)) & !word & HIGHS;
+    let equal = |byte: u8| below(word ^ (ONES * u64::from(byte)), 1);
+
+    let dirty = below(word, 0x20)
+        | equal(quote)
+        | equal(b'\\')
+        | equal(0x7F)
+        | equal(0xC2)
+        | if ascii_only { word & HIGHS } else { 0 };
+
+    dirty == 0
+}
 
-                // Current line has content and would exceed the limit, wrap to next line
-                output.push_str(&current_line.trim_end());
-                output.push('\n');
+/// Writes an object key or a struct field name, bare when `style` allows
+/// it, quoted and escaped otherwise
+fn write_key<O: Output>(
+    output: &mut O,
+    key: &str,
+    style: KeyStyle,
+    single_quotes: bool,
+    ascii_only: bool,
+) -> Result<()> {
+    if style.is_bare(key) {
+        return output.emit(key);
+    }
 
-                current_line = format!("{}{}", next_indent, formatted_item);
-            }
+    write_quoted(output, key, single_quotes, ascii_only)
+}
 
-            // Add the last line if it has content
-            if current_line.len() > empty_line_len {
-                output.push_str(&current_line.trim_end());
-                output.push('\n');
-            }
+/// Destination the encoders write into
+trait Output {
+    fn emit(&mut self, text: &str) -> Result<()>;
 
-            output.push_str(&current_indent);
-            output.push(']');
+    fn emit_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<()>;
+}
 
-            Ok(output)
-        }
+impl Output for String {
+    fn emit(&mut self, text: &str) -> Result<()> {
+        self.push_str(text);
 
-        fn encode_tuple(&self, value: &Vec<Value>, depth: usize) -> Result<String> {
-            if value.is_empty() {
-                return Ok("()".to_string());
-            }
+        Ok(())
+    }
 
-            let current_indent = self.indent(depth);
-            let next_indent = self.indent(depth + 1);
+    fn emit_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<()> {
+        fmt::Write::write_fmt(self, args).map_err(|_| Error::new("Failed to format encoded output"))
+    }
+}
 
-            let mut members = Vec::new();
-            let mut single_line_length = 0;
-            let mut can_fit_single_line = true;
+/// Largest buffer put in front of a writer, output past it is written in
+/// chunks of this size
+#[cfg(feature = "std")]
+const MAX_WRITE_BUFFER: usize = 64 * 1024;
 
-            single_line_length += 1; // Opening tuple character "("
+/// Buffered `io::Write` output, IO errors are converted into crate errors
+#[cfg(feature = "std")]
+struct IoOutput<W: std::io::Write> {
+    writer: std::io::BufWriter<W>,
+}
 
-            for (i, member) in value.iter().enumerate() {
-                let encoded_member = self.encode_value(member, depth + 1)?;
+#[cfg(feature = "std")]
+impl<W: std::io::Write> IoOutput<W> {
+    fn new(writer: W) -> Self {
+        Self {
+            writer: std::io::BufWriter::new(writer),
+        }
+    }
 
-                members.push(encoded_member);
+    /// Sized for output that's written in one go, up to `MAX_WRITE_BUFFER`
+    fn with_capacity(capacity: usize, writer: W) -> Self {
+        Self {
+            writer: std::io::BufWriter::with_capacity(capacity.min(MAX_WRITE_BUFFER), writer),
+        }
+    }
 
-                if can_fit_single_line {
-                    single_line_length += members[i].len();
+    fn flush(&mut self) -> Result<()> {
+        use std::io::Write;
 
-                    if i < value.len() - 1 {
-                        single_line_length += 2; // Separator comma and space ", "
-                    }
-                }
-            }
+        self.writer.flush().map_err(io_error)
+    }
+}
 
-            if can_fit_single_line {
-                single_line_length += 1; // Closing tuple character ")"
+#[cfg(feature = "std")]
+impl<W: std::io::Write> Output for IoOutput<W> {
+    fn emit(&mut self, text: &str) -> Result<()> {
+        use std::io::Write;
 
-                // It's safe to assume this value is a child (nested) element if
-                // the `depth` is non-zero. So, we add `1` to the length of the line
-                // to account for a possible comma from the parent.
-                let comma_allowance = if depth > 0 { 1 } else { 0 };
+        self.writer.write_all(text.as_bytes()).map_err(io_error)
+    }
 
-                // Check if it exceeded the line limit
-                if current_indent.len() + single_line_length + comma_allowance
-                    > self.max_line_length
-                {
-                    can_fit_single_line = false;
-                }
-            }
+    fn emit_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<()> {
+        use std::io::Write;
 
-            if can_fit_single_line {
-                return Ok(format!("({})", members.join(", ")));
-            }
+        self.writer.write_fmt(args).map_err(io_error)
+    }
+}
 
-            let mut output = String::new();
-            let mut current_line = next_indent.clone();
-            let empty_line_len = next_indent.len();
+#[cfg(feature = "std")]
+pub(crate) fn io_error(error: std::io::Error) -> Error {
+    Error::new(format!("Failed to write encoded output: {}", error))
+}
 
-            output.push_str("(\n");
+/// Minified encoding (single-line)
+mod mini {
+    use crate::encode::{
+        estimate_size, format_number, ordered_entries, struct_name, validate, write_key,
+        write_quoted, Children, EncodeOptions, EncodeStyle, KeyStyle, NonFiniteMode, Output,
+        MAX_PREALLOCATION,
+    };
+    #[cfg(feature = "std")]
+    use crate::encode::{IoOutput, MAX_WRITE_BUFFER};
+    use crate::raw::raw_text;
+    use celkit_core::internal::sys::*;
+    use celkit_core::internal::{Number, Result, Value};
 
-            for (i, encoded_member) in members.into_iter().enumerate() {
-                let mut formatted_member = encoded_member;
+    #[derive(Debug, Clone)]
+    pub struct Encoder {
+        input: Value,
+        options: EncodeOptions,
+    }
 
-                if i < value.len() - 1 || self.trailing_comma {
-                    formatted_member.push_str(", ");
-                }
+    impl Encoder {
+        pub fn new(input: Value) -> Self {
+            Self {
+                input,
+                options: EncodeOptions::default(),
+            }
+        }
 
-                // Check if this member would fit in the current line
-                if current_line.len() + formatted_member.len() <= self.max_line_length
-                    || current_line.len() <= empty_line_len
-                {
-                    current_line.push_str(&formatted_member);
+        /// Encoder configured from `options` (its `style` is ignored) without
+        /// a value of its own, for encoding many values with `encode_value`
+        pub fn with_options(options: EncodeOptions) -> Self {
+            Self::new(Value::Null).options(options)
+        }
 
-                    continue;
-                }
+        pub fn options(mut self, options: EncodeOptions) -> Self {
+            self.options = options;
 
-                // Current line has content and would exceed the limit, wrap to next line
-                output.push_str(&current_line.trim_end());
-                output.push('\n');
+            self
+        }
 
-                current_line = format!("{}{}", next_indent, formatted_member);
-            }
+        pub fn non_finite(mut self, mode: NonFiniteMode) -> Self {
+            self.options.non_finite = mode;
 
-            // Add the last line if it has content
-            if current_line.len() > empty_line_len {
-                output.push_str(&current_line.trim_end());
-                output.push('\n');
-            }
+            self
+        }
 
-            output.push_str(&current_indent);
-            output.push(')');
+        /// Maximum number of nested containers, deeper values fail to encode
+        pub fn max_depth(mut self, depth: usize) -> Self {
+            self.options.max_depth = depth;
 
-            Ok(output)
+            self
         }
 
-        fn encode_object(&self, value: &BTreeMap<String, Value>, depth: usize) -> Result<String> {
-            if value.is_empty() {
-                return Ok("{}".to_string());
-            }
+        /// Writes floats in exponent form (`1e300`) when that's shorter
+        pub fn exponent_floats(mut self, enabled: bool) -> Self {
+            self.options.exponent_floats = enabled;
 
-            let current_indent = self.indent(depth);
-            let next_indent = self.indent(depth + 1);
+            self
+        }
 
-            let mut entries = Vec::new();
-            let mut single_line_length = 0;
-            let mut can_fit_single_line = true;
+        /// Sorts object entries and struct fields by key before emitting them,
+        /// overriding `key_priority`
+        pub fn sort_keys(mut self, enabled: bool) -> Self {
+            self.options.sort_keys = enabled;
 
-            single_line_length += 1; // Opening object character "{"
+            self
+        }
 
-            for (i, entry) in value.iter().enumerate() {
-                let encoded_entry = format!(
-                    "\"{}\": {}",
-                    escape_text(entry.0),                   // Entry key
-                    self.encode_value(entry.1, depth + 1)?  // Entry value
-                );
+        /// Emits the entries and fields with these keys first, in this order,
+        /// followed by the others in key order. Ignored under `sort_keys`.
+        pub fn key_priority(mut self, keys: &[&str]) -> Self {
+            self.options.key_priority = keys.iter().map(|key| key.to_string()).collect();
 
-                entries.push(encoded_entry);
+            self
+        }
 
-                if can_fit_single_line {
-                    single_line_length += entries[i].len();
+        /// Emits the struct name, as in `@Config(...)`, instead of `@(...)`
+        pub fn struct_names(mut self, enabled: bool) -> Self {
+            self.options.struct_names = enabled;
 
-                    if i < value.len() - 1 {
-                        single_line_length += 2; // Separator comma and space ", "
-                    }
-                }
-            }
+            self
+        }
 
-            if can_fit_single_line {
-                single_line_length += 1; // Closing object character "}"
+        /// Escapes every non-ASCII character in text as `\uXXXX`
+        pub fn ascii_only(mut self, enabled: bool) -> Self {
+            self.options.ascii_only = enabled;
 
-                // It's safe to assume this value is a child (nested) element if
-                // the `depth` is non-zero. So, we add `1` to the length of the line
-                // to account for a possible comma from the parent.
-                let comma_allowance = if depth > 0 { 1 } else { 0 };
+            self
+        }
 
-                // Check if it exceeded the line limit
-                if current_indent.len() + single_line_length + comma_allowance
-                    > self.max_line_length
-                {
-                    can_fit_single_line = false;
-                }
-            }
+        /// Quotes text and keys with `'` instead of `"` unless that takes
+        /// more escapes, so text full of double quotes stays readable
+        pub fn prefer_single_quotes(mut self, enabled: bool) -> Self {
+            self.options.prefer_single_quotes = enabled;
 
-            if can_fit_single_line {
-                return Ok(format!("{{{}}}", entries.join(", ")));
-            }
+            self
+        }
 
-            let mut output = String::new();
-            let mut current_line = next_indent.clone();
-            let empty_line_len = next_indent.len();
+        /// How object keys are quoted
+        pub fn key_style(mut self, style: KeyStyle) -> Self {
+            self.options.key_style = style;
 
-            output.push_str("{\n");
+            self
+        }
 
-            for (i, encoded_entry) in entries.into_iter().enumerate() {
-                let mut formatted_entry = encoded_entry;
+        /// How struct field names are quoted
+        pub fn field_style(mut self, style: KeyStyle) -> Self {
+            self.options.field_style = style;
 
-                if i < value.len() - 1 || self.trailing_comma {
-                    formatted_entry.push_str(", ");
-                }
+            self
+        }
 
-                // Check if this entry would fit in the current line
-                if current_line.len() + formatted_entry.len() <= self.max_line_length
-                    || current_line.len() <= empty_line_len
-                {
-                    current_line.push_str(&formatted_entry);
+        /// Leaves out object entries and struct fields that are null, nulls
+        /// in arrays and tuples are kept since their position matters
+        pub fn omit_nulls(mut self, enabled: bool) -> Self {
+            self.options.omit_nulls = enabled;
 
-                    continue;
-                }
+            self
+        }
 
-                // Current line has content and would exceed the limit, wrap to next line
-                output.push_str(&current_line.trim_end());
-                output.push('\n');
+        /// Writes floats with at most `precision` fractional digits, `None`
+        /// keeps the shortest form that reads back to the same value
+        pub fn float_precision(mut self, precision: Option<usize>) -> Self {
+            self.options.float_precision = precision;
 
-                current_line = format!("{}{}", next_indent, formatted_entry);
-            }
+            self
+        }
 
-            // Add the last line if it has content
-            if current_line.len() > empty_line_len {
-                output.push_str(&current_line.trim_end());
-                output.push('\n');
-            }
+        pub fn encode(self) -> Result<String> {
+            self.encode_value(&self.input)
+        }
+
+        /// Encodes `value` rather than the encoder's own input, so a configured
+        /// encoder can be reused and borrowed values encoded
+        pub fn encode_value(&self, value: &Value) -> Result<String> {
+            let mut output =
+                String::with_capacity(estimate_size(value, EncodeStyle::Mini, MAX_PREALLOCATION));
 
-            output.push_str(&current_indent);
-            output.push('}');
+            self.encode_value_into(value, &mut output)?;
 
             Ok(output)
         }
 
-        fn encode_struct(&self, value: &BTreeMap<String, Value>, depth: usize) -> Result<String> {
-            if value.is_empty() {
-                return Ok("@()".to_string());
-            }
+        /// Appends the output to `output` without clearing it, so one buffer
+        /// can be reused across calls. Nothing is appended on error.
+        pub fn encode_into(&self, output: &mut String) -> Result<()> {
+            self.encode_value_into(&self.input, output)
+        }
 
-            let current_indent = self.indent(depth);
-            let next_indent = self.indent(depth + 1);
+        /// Like `encode_into`, for `value` rather than the encoder's own input
+        pub fn encode_value_into(&self, value: &Value, output: &mut String) -> Result<()> {
+            let length = output.len();
 
-            let fields: Result<Vec<String>> = value
-                .iter()
-                .map(|field| {
-                    Ok(format!(
-                        "{} = {}",
-                        field.0,                                // Field name
-                        self.encode_value(field.1, depth + 1)?  // Field value
-                    ))
-                })
-                .collect();
-            let fields = fields?;
+            self.write_into(value, output)
+                .inspect_err(|_| output.truncate(length))
+        }
+
+        /// Streams the output into `writer` as it's produced, through a buffer
+        #[cfg(feature = "std")]
+        pub fn encode_to_writer<W: std::io::Write>(self, writer: W) -> Result<()> {
+            let capacity = estimate_size(&self.input, EncodeStyle::Mini, MAX_WRITE_BUFFER);
+            let mut output = IoOutput::with_capacity(capacity, writer);
 
-            let mut output = String::new();
-            let mut current_line = next_indent.clone();
-            let empty_line_len = next_indent.len();
+            self.write_into(&self.input, &mut output)?;
 
-            output.push_str("@(");
+            output.flush()
+        }
 
-            for (i, encoded_field) in fields.into_iter().enumerate() {
-                let mut formatted_field = encoded_field;
+        pub(super) fn write_into<O: Output>(&self, value: &Value, output: &mut O) -> Result<()> {
+            validate(value, self.options.non_finite, self.options.max_depth)?;
 
-                if i < value.len() - 1 || self.trailing_comma {
-                    formatted_field.push_str(", ");
-                }
+            self.write_value(value, output)
+        }
 
-                // Each field has its own line
-                output.push_str(&current_line.trim_end());
-                output.push('\n');
+        fn encode_null<O: Output>(&self, output: &mut O) -> Result<()> {
+            output.emit("null")
+        }
 
-                current_line = format!("{}{}", next_indent, formatted_field);
-            }
+        fn encode_boolean<O: Output>(&self, value: &bool, output: &mut O) -> Result<()> {
+            output.emit(if *value { "true" } else { "false" })
+        }
 
-            // Add the last line if it has content
-            if current_line.len() > empty_line_len {
-                output.push_str(&current_line.trim_end());
-                output.push('\n');
+        fn encode_number<O: Output>(&self, value: &Number, output: &mut O) -> Result<()> {
+            if self.options.exponent_floats
+                || self.options.float_precision.is_some()
+                || !value.is_finite()
+            {
+                return output.emit(&format_number(
+                    value,
+                    self.options.non_finite,
+                    self.options.exponent_floats,
+                    self.options.float_precision,
+                ));
             }
 
-            output.push_str(&current_indent);
-            output.push(')');
+            output.emit_fmt(format_args!("{}", value))
+        }
 
-            Ok(output)
+        fn encode_text<O: Output>(&self, value: &str, output: &mut O) -> Result<()> {
+            write_quoted(
+                output,
+                value,
+                self.options.prefer_single_quotes,
+                self.options.ascii_only,
+            )
         }
 
-        fn encode_value(&self, value: &Value, depth: usize) -> Result<String> {
-            match value {
-                Value::Null => self.encode_null(),
-                Value::Boolean(b) => self.encode_boolean(b),
-                Value::Number(n) => self.encode_number(n),
-                Value::Text(t) => self.encode_text(t),
-                Value::Array(a) => self.encode_array(a, depth),
-                Value::Tuple(t) => self.encode_tuple(t, depth),
-                Value::Object(o) => self.encode_object(o, depth),
-                Value::Struct(_, s) => self.encode_struct(s, depth),
-            }
+        fn encode_items<'a, O: Output>(
+            &self,
+            value: &'a [Value],
+            open: &str,
+            close: &'static str,
+            output: &mut O,
+        ) -> Result<Frame<'a>> {
+            output.emit(open)?;
+
+            Ok(Frame {
+                children: Children::Items(value.iter()),
+                close,
+                first: true,
+            })
+        }
+
+        fn encode_object<'a, O: Output>(
+            &'a self,
+            value: &'a BTreeMap<String, Value>,
+            output: &mut O,
+        ) -> Result<Frame<'a>> {
+            output.emit("{")?;
+
+            Ok(Frame {
+                children: Children::Entries {
+                    entries: ordered_entries(
+                        value,
+                        self.options.priority(),
+                        self.options.omit_nulls,
+                    ),
+                    style: self.options.key_style,
+                    separator: ":",
+                },
+                close: "}",
+                first: true,
+            })
+        }
+
+        fn encode_struct<'a, O: Output>(
+            &'a self,
+            name: &str,
+            value: &'a BTreeMap<String, Value>,
+            output: &mut O,
+        ) -> Result<Frame<'a>> {
+            output.emit("@")?;
+            output.emit(struct_name(name, self.options.struct_names)?)?;
+            output.emit("(")?;
+
+            Ok(Frame {
+                children: Children::Entries {
+                    entries: ordered_entries(
+                        value,
+                        self.options.priority(),
+                        self.options.omit_nulls,
+                    ),
+                    style: self.options.field_style,
+                    separator: "=",
+                },
+                close: ")",
+                first: true,
+            })
+        }
+
+        /// Writes a scalar completely, or opens a container and returns the
+        /// frame its children are written from
+        fn open_value<'a, O: Output>(
+            &'a self,
+            value: &'a Value,
+            output: &mut O,
+        ) -> Result<Option<Frame<'a>>> {
+            if let Some(text) = raw_text(value) {
+                return output.emit(text).map(|_| None);
+            }
+
+            let frame = match value {
+                Value::Null => return self.encode_null(output).map(|_| None),
+                Value::Boolean(b) => return self.encode_boolean(b, output).map(|_| None),
+                Value::Number(n) => return self.encode_number(n, output).map(|_| None),
+                Value::Text(t) => return self.encode_text(t, output).map(|_| None),
+                Value::Array(a) => self.encode_items(a, "[", "]", output)?,
+                Value::Tuple(t) => self.encode_items(t, "(", ")", output)?,
+                Value::Object(o) => self.encode_object(o, output)?,
+                Value::Struct(n, s) => self.encode_struct(n, s, output)?,
+            };
+
+            Ok(Some(frame))
+        }
+
+        /// Open containers are kept on a stack instead of the call stack, so
+        /// nesting is only bounded by `max_depth` and memory
+        fn write_value<O: Output>(&self, value: &Value, output: &mut O) -> Result<()> {
+            let mut stack = Vec::new();
+
+            stack.extend(self.open_value(value, output)?);
+
+            while let Some(frame) = stack.last_mut() {
+                let Some((key, child)) = frame.children.next() else {
+                    output.emit(frame.close)?;
+                    stack.pop();
+
+                    continue;
+                };
+
+                if !frame.first {
+                    output.emit(",")?;
+                }
+
+                frame.first = false;
+
+                if let Some((key, style, separator)) = key {
+                    write_key(
+                        output,
+                        key,
+                        style,
+                        self.options.prefer_single_quotes,
+                        self.options.ascii_only,
+                    )?;
+                    output.emit(separator)?;
+                }
+
+                stack.extend(self.open_value(child, output)?);
+            }
+
+            Ok(())
+        }
+    }
+
+    /// A container whose children are still being written
+    struct Frame<'a> {
+        children: Children<'a>,
+        close: &'static str,
+        first: bool,
+    }
+}
+
+/// Prettified encoding (multi-line)
+mod pretty {
+    #[cfg(feature = "std")]
+    use crate::encode::IoOutput;
+    use crate::encode::{
+        estimate_size, format_number, group_digits, ordered_entries, struct_name, validate,
+        write_key, write_quoted, Annotations, Children, EncodeOptions, EncodeStyle, ExpandMode,
+        KeyStyle, LineEnding, NonFiniteMode, Output, MAX_PREALLOCATION,
+    };
+    use crate::raw::raw_text;
+    use celkit_core::internal::sys::*;
+    use celkit_core::internal::{Number, Result, Value};
+    #[cfg(feature = "unicode-width")]
+    use unicode_width::UnicodeWidthStr;
+
+    #[derive(Debug, Clone)]
+    pub struct Encoder {
+        input: Value,
+        options: EncodeOptions,
+        comments: BTreeMap<String, Annotations>,
+    }
+
+    impl Encoder {
+        pub fn new(input: Value) -> Self {
+            Self {
+                input,
+                options: EncodeOptions::default(),
+                comments: BTreeMap::new(),
+            }
+        }
+
+        /// Encoder configured from `options` (its `style` is ignored) without
+        /// a value of its own, for encoding many values with `encode_value`
+        pub fn with_options(options: EncodeOptions) -> Self {
+            Self::new(Value::Null).options(options)
+        }
+
+        pub fn options(mut self, options: EncodeOptions) -> Self {
+            self.options = options;
+
+            self
+        }
+
+        /// Writes comments around the value at `path`, in `Value::pointer`
+        /// syntax with `""` for the root. Values with comments always get
+        /// lines of their own.
+        pub fn comment(mut self, path: &str, annotations: Annotations) -> Self {
+            self.comments.insert(path.to_string(), annotations);
+
+            self
+        }
+
+        /// Replaces all comments, keyed by path like with `comment`
+        pub fn comments(mut self, comments: BTreeMap<String, Annotations>) -> Self {
+            self.comments = comments;
+
+            self
+        }
+
+        pub fn indent_size(mut self, size: usize) -> Self {
+            self.options.indent = " ".repeat(size);
+
+            self
+        }
+
+        /// Indents with an arbitrary unit, e.g. `"\t"`
+        pub fn indent(mut self, unit: &str) -> Self {
+            self.options.indent = unit.to_string();
+
+            self
+        }
+
+        /// Number of columns a tab counts for when measuring lines
+        pub fn tab_width(mut self, width: usize) -> Self {
+            self.options.tab_width = width;
+
+            self
+        }
+
+        /// Measures lines in terminal columns, so wide characters such as CJK
+        /// count as two, instead of in characters
+        #[cfg(feature = "unicode-width")]
+        pub fn unicode_width(mut self, enabled: bool) -> Self {
+            self.options.unicode_width = enabled;
+
+            self
+        }
+
+        pub fn max_line_length(mut self, length: usize) -> Self {
+            self.options.max_line_length = length;
+
+            self
+        }
+
+        pub fn trailing_comma(mut self, enabled: bool) -> Self {
+            self.options.trailing_comma = enabled;
+
+            self
+        }
+
+        pub fn non_finite(mut self, mode: NonFiniteMode) -> Self {
+            self.options.non_finite = mode;
+
+            self
+        }
+
+        /// Maximum number of nested containers, deeper values fail to encode
+        pub fn max_depth(mut self, depth: usize) -> Self {
+            self.options.max_depth = depth;
+
+            self
+        }
+
+        /// Writes floats in exponent form (`1e300`) when that's shorter
+        pub fn exponent_floats(mut self, enabled: bool) -> Self {
+            self.options.exponent_floats = enabled;
+
+            self
+        }
+
+        /// Sorts object entries and struct fields by key before emitting them,
+        /// overriding `key_priority`
+        pub fn sort_keys(mut self, enabled: bool) -> Self {
+            self.options.sort_keys = enabled;
+
+            self
+        }
+
+        /// Emits the entries and fields with these keys first, in this order,
+        /// followed by the others in key order. Ignored under `sort_keys`.
+        pub fn key_priority(mut self, keys: &[&str]) -> Self {
+            self.options.key_priority = keys.iter().map(|key| key.to_string()).collect();
+
+            self
+        }
+
+        /// Emits the struct name, as in `@Config(...)`, instead of `@(...)`
+        pub fn struct_names(mut self, enabled: bool) -> Self {
+            self.options.struct_names = enabled;
+
+            self
+        }
+
+        /// Escapes every non-ASCII character in text as `\uXXXX`
+        pub fn ascii_only(mut self, enabled: bool) -> Self {
+            self.options.ascii_only = enabled;
+
+            self
+        }
+
+        /// Quotes text and keys with `'` instead of `"` unless that takes
+        /// more escapes, so text full of double quotes stays readable
+        pub fn prefer_single_quotes(mut self, enabled: bool) -> Self {
+            self.options.prefer_single_quotes = enabled;
+
+            self
+        }
+
+        /// How object keys are quoted
+        pub fn key_style(mut self, style: KeyStyle) -> Self {
+            self.options.key_style = style;
+
+            self
+        }
+
+        /// How struct field names are quoted
+        pub fn field_style(mut self, style: KeyStyle) -> Self {
+            self.options.field_style = style;
+
+            self
+        }
+
+        /// Leaves out object entries and struct fields that are null, nulls
+        /// in arrays and tuples are kept since their position matters
+        pub fn omit_nulls(mut self, enabled: bool) -> Self {
+            self.options.omit_nulls = enabled;
+
+            self
+        }
+
+        /// Writes floats with at most `precision` fractional digits, `None`
+        /// keeps the shortest form that reads back to the same value
+        pub fn float_precision(mut self, precision: Option<usize>) -> Self {
+            self.options.float_precision = precision;
+
+            self
+        }
+
+        /// Writes text spanning multiple lines verbatim in a `"""` block,
+        /// other texts keep the escaped single-line form
+        pub fn multiline_strings(mut self, enabled: bool) -> Self {
+            self.options.multiline_strings = enabled;
+
+            self
+        }
+
+        pub fn newline(mut self, newline: LineEnding) -> Self {
+            self.options.newline = newline;
+
+            self
+        }
+
+        /// Ends the document with a `newline`, as text files usually do. Off
+        /// by default, so the output ends right after the value.
+        pub fn final_newline(mut self, enabled: bool) -> Self {
+            self.options.final_newline = enabled;
+
+            self
+        }
+
+        /// Separates the children of the root container with an empty line
+        /// when it spans multiple lines, giving each child a line of its own.
+        /// Nested containers are laid out as usual.
+        pub fn blank_lines_between_top_level(mut self, enabled: bool) -> Self {
+            self.options.blank_lines_between_top_level = enabled;
+
+            self
+        }
+
+        /// Separates integer digits with `_` every `size` digits, `0` disables it
+        pub fn digit_grouping(mut self, size: usize) -> Self {
+            self.options.digit_grouping = size;
+
+            self
+        }
+
+        /// Pads struct field names so the `=` signs of a struct line up
+        pub fn align_struct_fields(mut self, enabled: bool) -> Self {
+            self.options.align_struct_fields = enabled;
+
+            self
+        }
+
+        /// Lays out arrays of only numbers `count` items to a line once they
+        /// have more than that, `0` leaves them to the usual layout. Takes
+        /// precedence over `ExpandMode::Always`, which still expands every
+        /// such array but keeps `count` numbers to a line.
+        pub fn numbers_per_line(mut self, count: usize) -> Self {
+            self.options.numbers_per_line = count;
+
+            self
+        }
+
+        /// Pads the numbers of arrays laid out by `numbers_per_line` to the
+        /// widest one, so they line up in right-aligned columns
+        pub fn align_numbers(mut self, enabled: bool) -> Self {
+            self.options.align_numbers = enabled;
+
+            self
+        }
+
+        pub fn expand(mut self, mode: ExpandMode) -> Self {
+            self.options.expand = mode;
+
+            self
+        }
+
+        /// Expands containers with more than `count` items even when they'd
+        /// fit on one line, there's no threshold by default (`usize::MAX`)
+        pub fn expand_threshold(mut self, count: usize) -> Self {
+            self.options.expand_threshold = count;
+
+            self
+        }
+
+        /// Streams the output into `writer` through a buffer. Each child of a
+        /// root container that doesn't fit a single line is written once it's
+        /// finished, the rest of the output is only written once complete as
+        /// its layout depends on all of it. So is a root with
+        /// `align_struct_fields` or `align_numbers`.
+        #[cfg(feature = "std")]
+        pub fn encode_to_writer<W: std::io::Write>(self, writer: W) -> Result<()> {
+            let mut output = IoOutput::new(writer);
+
+            self.write_into(&self.input, &mut output)?;
+
+            output.flush()
+        }
+
+        pub fn encode(self) -> Result<String> {
+            self.encode_value(&self.input)
+        }
+
+        /// Encodes `value` rather than the encoder's own input, so a configured
+        /// encoder can be reused and borrowed values encoded
+        pub fn encode_value(&self, value: &Value) -> Result<String> {
+            let mut output =
+                String::with_capacity(estimate_size(value, EncodeStyle::Pretty, MAX_PREALLOCATION));
+
+            self.encode_value_into(value, &mut output)?;
+
+            Ok(output)
+        }
+
+        /// Appends the output to `output` without clearing it, so one buffer
+        /// can be reused across calls. Nothing is appended on error.
+        pub fn encode_into(&self, output: &mut String) -> Result<()> {
+            self.encode_value_into(&self.input, output)
+        }
+
+        /// Like `encode_into`, for `value` rather than the encoder's own input
+        pub fn encode_value_into(&self, value: &Value, output: &mut String) -> Result<()> {
+            let length = output.len();
+
+            self.write_into(value, output)
+                .inspect_err(|_| output.truncate(length))
+        }
+
+        fn write_into<O: Output>(&self, value: &Value, output: &mut O) -> Result<()> {
+            validate(value, self.options.non_finite, self.options.max_depth)?;
+
+            let mut buffer = String::new();
+            let depth = 0;
+            let root = self.comments.get("");
+
+            for line in root.iter().flat_map(|root| &root.leading) {
+                self.write_comment(line, depth, &mut buffer);
+                buffer.push_str(self.options.newline.as_str());
+            }
+
+            self.write_node(value, &mut buffer, output)?;
+
+            if let Some(trailing) = root.and_then(|root| root.trailing.as_deref()) {
+                buffer.push(' ');
+                self.write_comment(trailing, depth, &mut buffer);
+            }
+
+            if self.options.final_newline {
+                buffer.push_str(self.options.newline.as_str());
+            }
+
+            output.emit(&buffer)
+        }
+
+        fn write_indentation(&self, level: usize, output: &mut String) {
+            for _ in 0..level {
+                output.push_str(&self.options.indent);
+            }
+        }
+
+        fn indentation_width(&self, level: usize) -> usize {
+            self.width(&self.options.indent) * level
+        }
+
+        /// Display width of `text` in characters, or in terminal columns with
+        /// `unicode_width`, counting tabs as `tab_width` columns
+        fn width(&self, text: &str) -> usize {
+            let tabs = text.matches('\t').count();
+
+            #[cfg(feature = "unicode-width")]
+            if self.options.unicode_width {
+                let columns: usize = text.split('\t').map(UnicodeWidthStr::width).sum();
+
+                return columns + tabs * self.options.tab_width;
+            }
+
+            text.chars().count() - tabs + tabs * self.options.tab_width
+        }
+
+        /// Width of `text` if it fits on a single line, `None` otherwise
+        fn line_width(&self, text: &str) -> Option<usize> {
+            if text.contains('\n') {
+                return None;
+            }
+
+            Some(self.width(text))
+        }
+
+        /// Writes `text` as a `//` comment, continuing on lines of its own at
+        /// `level` when it has several
+        fn write_comment(&self, text: &str, level: usize, output: &mut String) {
+            for (i, line) in text.split('\n').enumerate() {
+                let line = line.strip_suffix('\r').unwrap_or(line);
+
+                if i > 0 {
+                    output.push_str(self.options.newline.as_str());
+                    self.write_indentation(level, output);
+                }
+
+                output.push_str("//");
+
+                if !line.is_empty() {
+                    output.push(' ');
+                    output.push_str(line);
+                }
+            }
+        }
+
+        /// Leading comments of a child, each on its own line at `level`
+        fn write_leading(&self, span: &Span<'_>, level: usize, output: &mut String) {
+            for line in span.comments.iter().flat_map(|comments| &comments.leading) {
+                self.write_indentation(level, output);
+                self.write_comment(line, level, output);
+                output.push_str(self.options.newline.as_str());
+            }
+        }
+
+        /// Trailing comment of a child, after its comma
+        fn write_trailing(&self, span: &Span<'_>, level: usize, output: &mut String) {
+            if let Some(trailing) = span
+                .comments
+                .and_then(|comments| comments.trailing.as_deref())
+            {
+                output.push(' ');
+                self.write_comment(trailing, level, output);
+            }
+        }
+
+        fn encode_null(&self, output: &mut String) -> Result<()> {
+            output.emit("null")
+        }
+
+        fn encode_boolean(&self, value: &bool, output: &mut String) -> Result<()> {
+            output.emit(if *value { "true" } else { "false" })
+        }
+
+        fn encode_number(&self, value: &Number, output: &mut String) -> Result<()> {
+            let grouped = self.options.digit_grouping > 0 && value.is_integer();
+
+            if !self.options.exponent_floats
+                && self.options.float_precision.is_none()
+                && value.is_finite()
+                && !grouped
+            {
+                return output.emit_fmt(format_args!("{}", value));
+            }
+
+            let number = format_number(
+                value,
+                self.options.non_finite,
+                self.options.exponent_floats,
+                self.options.float_precision,
+            );
+
+            if !grouped {
+                return output.emit(&number);
+            }
+
+            output.emit(&group_digits(&number, self.options.digit_grouping))
+        }
+
+        fn encode_text(&self, value: &str, output: &mut String) -> Result<()> {
+            if self.options.multiline_strings && self.is_block_text(value) {
+                output.emit("\"\"\"")?;
+                output.emit(self.options.newline.as_str())?;
+                output.emit(value)?;
+
+                return output.emit("\"\"\"");
+            }
+
+            write_quoted(
+                output,
+                value,
+                self.options.prefer_single_quotes,
+                self.options.ascii_only,
+            )
+        }
+
+        /// Whether `value` can be written verbatim in a `"""` block: it must
+        /// span multiple lines, have no control characters besides newlines
+        /// and tabs, and neither contain `"""` nor end with a quote that
+        /// would merge into the closing delimiter
+        fn is_block_text(&self, value: &str) -> bool {
+            let has_control = value
+                .chars()
+                .any(|c| c.is_control() && c != '\n' && c != '\t');
+
+            value.contains('\n')
+                && !value.contains("\"\"\"")
+                && !value.ends_with('"')
+                && (value.is_ascii() || !self.options.ascii_only)
+                && !has_control
+        }
+
+        /// Writes a scalar completely, or the opening of a container along
+        /// with the frame its children are written from
+        fn open_node<'a>(
+            &'a self,
+            value: &'a Value,
+            depth: usize,
+            first_span: usize,
+            path_len: usize,
+            output: &mut String,
+        ) -> Result<Option<Frame<'a>>> {
+            let start = output.len();
+
+            if let Some(text) = raw_text(value) {
+                output.push_str(text);
+
+                return Ok(None);
+            }
+
+            let (container, children) = match value {
+                Value::Null => return self.encode_null(output).map(|_| None),
+                Value::Boolean(b) => return self.encode_boolean(b, output).map(|_| None),
+                Value::Number(n) => return self.encode_number(n, output).map(|_| None),
+                Value::Text(t) => return self.encode_text(t, output).map(|_| None),
+                Value::Array(a) => {
+                    output.push('[');
+
+                    let container = if self.is_number_array(a) {
+                        Container::Numbers
+                    } else {
+                        Container::Array
+                    };
+
+                    (container, Children::Items(a.iter()))
+                }
+                Value::Tuple(t) => {
+                    output.push('(');
+
+                    (Container::Tuple, Children::Items(t.iter()))
+                }
+                Value::Object(o) => {
+                    output.push('{');
+
+                    let entries =
+                        ordered_entries(o, self.options.priority(), self.options.omit_nulls);
+
+                    (
+                        Container::Object,
+                        Children::Entries {
+                            entries,
+                            style: self.options.key_style,
+                            separator: ": ",
+                        },
+                    )
+                }
+                Value::Struct(n, s) => {
+                    let name = struct_name(n, self.options.struct_names)?;
+
+                    output.push('@');
+                    output.push_str(name);
+                    output.push('(');
+
+                    let entries =
+                        ordered_entries(s, self.options.priority(), self.options.omit_nulls);
+
+                    (
+                        Container::Struct(name),
+                        Children::Entries {
+                            entries,
+                            style: self.options.field_style,
+                            separator: " = ",
+                        },
+                    )
+                }
+            };
+
+            Ok(Some(Frame {
+                container,
+                children,
+                index: 0,
+                start,
+                depth,
+                first_span,
+                path_len,
+            }))
+        }
+
+        /// Finishes a container on a single line if it fits, otherwise lays
+        /// its children out again over multiple lines. Returns the width of the
+        /// single line, or `None` once it spans multiple lines.
+        fn close_node(
+            &self,
+            frame: &Frame<'_>,
+            spans: &[Span<'_>],
+            output: &mut String,
+            scratch: &mut String,
+        ) -> Option<usize> {
+            if let Some(width) = self.single_line_width(frame, spans) {
+                output.push(frame.container.close());
+
+                return Some(width);
+            }
+
+            scratch.clear();
+            scratch.push_str(&output[frame.start..]);
+            output.truncate(frame.start);
+
+            let mut lines = self.open_lines(frame, spans, spans.len(), output);
+
+            for span in spans {
+                self.push_line(frame, &mut lines, span, scratch, frame.start, output);
+            }
+
+            self.close_lines(frame, &lines, output);
+
+            None
+        }
+
+        /// Width of the container on a single line with `spans` as its
+        /// children, `None` if it doesn't fit one. Only ever grows as children
+        /// are added, so a container that doesn't fit with some of its
+        /// children won't fit with all of them either.
+        fn single_line_width(&self, frame: &Frame<'_>, spans: &[Span<'_>]) -> Option<usize> {
+            let mut single_line_length = match frame.container {
+                Container::Struct(name) => self.width(name) + 2, // Opening characters "@" and "("
+                _ => 1,                                          // Opening character
+            };
+
+            if spans.is_empty() {
+                return Some(single_line_length + 1);
+            }
+
+            let count = spans.len();
+            let mut can_fit_single_line =
+                self.options.expand == ExpandMode::Auto && count <= self.options.expand_threshold;
+
+            if let Container::Numbers = frame.container {
+                can_fit_single_line &= count <= self.options.numbers_per_line;
+            }
+
+            for (i, span) in spans.iter().enumerate() {
+                // A multi-line or commented child can't be part of a single line
+                let Some(width) = span.width.filter(|_| span.comments.is_none()) else {
+                    can_fit_single_line = false;
+
+                    break;
+                };
+
+                single_line_length += width;
+
+                if i < count - 1 {
+                    single_line_length += 2; // Separator comma and space ", "
+                }
+            }
+
+            if can_fit_single_line {
+                single_line_length += 1; // Closing character
+
+                // It's safe to assume this value is a child (nested) element if
+                // the `depth` is non-zero. So, we add `1` to the length of the line
+                // to account for a possible comma from the parent.
+                let comma_allowance = if frame.depth > 0 { 1 } else { 0 };
+
+                // Check if it exceeded the line limit
+                if self.indentation_width(frame.depth) + single_line_length + comma_allowance
+                    > self.options.max_line_length
+                {
+                    can_fit_single_line = false;
+                }
+            }
+
+            // The children are already written one after another, separated
+            // by ", ", which is exactly the single-line form
+            can_fit_single_line.then_some(single_line_length)
+        }
+
+        /// Whether the multi-line layout of `frame` can be picked before all
+        /// of its children are written, which isn't the case when they're
+        /// aligned or when a commented number may still follow
+        fn is_streamable(&self, frame: &Frame<'_>) -> bool {
+            match frame.container {
+                Container::Struct(_) => !self.options.align_struct_fields,
+                Container::Numbers => {
+                    !self.options.align_numbers
+                        && (self.is_separated(frame) || self.comments.is_empty())
+                }
+                _ => true,
+            }
+        }
+
+        /// Picks the multi-line layout of `frame` and writes its opening.
+        /// `spans` are the children written so far, all of them when the
+        /// layout aligns them.
+        fn open_lines(
+            &self,
+            frame: &Frame<'_>,
+            spans: &[Span<'_>],
+            count: usize,
+            output: &mut String,
+        ) -> Lines {
+            let layout = match frame.container {
+                Container::Struct(_) => Layout::Struct,
+                // Commented and separated numbers need lines of their own like
+                // any other child
+                Container::Numbers
+                    if !self.is_separated(frame)
+                        && spans.iter().all(|span| span.comments.is_none()) =>
+                {
+                    Layout::Numbers
+                }
+                _ => Layout::Items,
+            };
+
+            // Fields with multi-line values are left unaligned and don't count
+            // towards the width the other names are padded to
+            let align = match layout {
+                Layout::Struct if self.options.align_struct_fields => spans
+                    .iter()
+                    .filter(|span| span.width.is_some())
+                    .map(|span| span.key_width)
+                    .max()
+                    .unwrap_or(0),
+                Layout::Numbers if self.options.align_numbers => spans
+                    .iter()
+                    .filter_map(|span| span.width)
+                    .max()
+                    .unwrap_or(0),
+                _ => 0,
+            };
+
+            match &frame.container {
+                Container::Struct(name) => {
+                    output.push('@');
+                    output.push_str(name);
+                    output.push('(');
+
+                    // The opening line ends with whatever part of the indentation
+                    // isn't whitespace
+                    self.write_indentation(frame.depth + 1, output);
+                    trim_line_end(output);
+                }
+                container => {
+                    output.push(container.open());
+
+                    if let Layout::Items = layout {
+                        output.push_str(self.options.newline.as_str());
+                    }
+                }
+            }
+
+            Lines {
+                layout,
+                count,
+                index: 0,
+                line_len: self.indentation_width(frame.depth + 1),
+                align,
+            }
+        }
+
+        /// Writes the next child in the layout picked by `open_lines`. The
+        /// child is at `span` in `scratch`, which starts at offset `base` of
+        /// the output it was first written to.
+        fn push_line(
+            &self,
+            frame: &Frame<'_>,
+            lines: &mut Lines,
+            span: &Span<'_>,
+            scratch: &str,
+            base: usize,
+            output: &mut String,
+        ) {
+            let i = lines.index;
+            let child = &scratch[span.start - base..span.end - base];
+            let comma = i < lines.count - 1 || self.options.trailing_comma;
+            let newline = self.options.newline.as_str();
+            let separated = self.is_separated(frame);
+
+            lines.index += 1;
+
+            match lines.layout {
+                // Packs as many children on each line as fit within
+                // `max_line_length`
+                Layout::Items => {
+                    let empty_line_len = self.indentation_width(frame.depth + 1);
+
+                    // Multi-line, commented and separated children always get
+                    // lines of their own
+                    let Some(width) = span.width.filter(|_| span.comments.is_none() && !separated)
+                    else {
+                        if lines.line_len > empty_line_len {
+                            output.push_str(newline);
+                        }
+
+                        if separated && i > 0 {
+                            output.push_str(newline);
+                        }
+
+                        self.write_leading(span, frame.depth + 1, output);
+                        self.write_indentation(frame.depth + 1, output);
+                        output.push_str(child);
+
+                        if comma {
+                            output.push(',');
+                        }
+
+                        self.write_trailing(span, frame.depth + 1, output);
+                        output.push_str(newline);
+                        lines.line_len = empty_line_len;
+
+                        return;
+                    };
+
+                    let width = if comma { width + 2 } else { width };
+
+                    // The space after the previous comma is only written once
+                    // this child turns out to share its line. Children never
+                    // share a line when expanding always.
+                    if lines.line_len <= empty_line_len {
+                        self.write_indentation(frame.depth + 1, output);
+                    } else if self.options.expand == ExpandMode::Auto
+                        && lines.line_len + width <= self.options.max_line_length
+                    {
+                        output.push(' ');
+                    } else {
+                        // Current line has content and would exceed the limit,
+                        // wrap to next line
+                        output.push_str(newline);
+                        self.write_indentation(frame.depth + 1, output);
+                        lines.line_len = empty_line_len;
+                    }
+
+                    output.push_str(child);
+
+                    if comma {
+                        output.push(',');
+                    }
+
+                    lines.line_len += width;
+                }
+                // Puts `numbers_per_line` numbers on each line, padded to the
+                // widest one with `align_numbers`
+                Layout::Numbers => {
+                    if i.is_multiple_of(self.options.numbers_per_line) {
+                        output.push_str(newline);
+                        self.write_indentation(frame.depth + 1, output);
+                    } else {
+                        output.push(' ');
+                    }
+
+                    for _ in span.width.unwrap_or(0)..lines.align {
+                        output.push(' ');
+                    }
+
+                    output.push_str(child);
+
+                    if comma {
+                        output.push(',');
+                    }
+                }
+                // Puts every field on a line of its own
+                Layout::Struct => {
+                    let field_name = &child[..span.key_end - span.start];
+                    let field_value = &child[span.value_start - span.start..];
+                    let padding = match span.width {
+                        Some(_) => lines.align.saturating_sub(span.key_width),
+                        None => 0,
+                    };
+
+                    output.push_str(newline);
+
+                    if separated && i > 0 {
+                        output.push_str(newline);
+                    }
+
+                    self.write_leading(span, frame.depth + 1, output);
+                    self.write_indentation(frame.depth + 1, output);
+                    output.push_str(field_name);
+
+                    for _ in 0..padding {
+                        output.push(' ');
+                    }
+
+                    output.push_str(" = ");
+                    output.push_str(field_value);
+
+                    if comma {
+                        output.push(',');
+                    }
+
+                    self.write_trailing(span, frame.depth + 1, output);
+                }
+            }
+        }
+
+        /// Writes the closing of a container laid out by `open_lines`
+        fn close_lines(&self, frame: &Frame<'_>, lines: &Lines, output: &mut String) {
+            // Packed items end their last line only if it has content
+            if !matches!(lines.layout, Layout::Items)
+                || lines.line_len > self.indentation_width(frame.depth + 1)
+            {
+                output.push_str(self.options.newline.as_str());
+            }
+
+            self.write_indentation(frame.depth, output);
+            output.push(frame.container.close());
+        }
+
+        /// Whether `items` is laid out by `numbers_per_line`
+        fn is_number_array(&self, items: &[Value]) -> bool {
+            self.options.numbers_per_line > 0
+                && !items.is_empty()
+                && items.iter().all(|item| matches!(item, Value::Number(_)))
+        }
+
+        /// Whether the children of `frame` are separated by empty lines, only
+        /// ever the case for the root container
+        fn is_separated(&self, frame: &Frame<'_>) -> bool {
+            self.options.blank_lines_between_top_level && frame.depth == 0
+        }
+
+        /// Writes containers in their single-line form first and lays them out
+        /// again when they turn out not to fit. Open containers are kept on a
+        /// stack instead of the call stack, so nesting is only bounded by
+        /// `max_depth` and memory.
+        ///
+        /// Everything is written to `buffer` until the root container turns
+        /// out not to fit a single line. From then on each of its children is
+        /// laid out and written to `output` as soon as it's finished, so only
+        /// the child being written is kept. Whatever is left in `buffer` at
+        /// the end is for the caller to write.
+        fn write_node<O: Output>(
+            &self,
+            value: &Value,
+            buffer: &mut String,
+            output: &mut O,
+        ) -> Result<()> {
+            let depth = 0;
+            let mut stack = Vec::new();
+            let mut spans: Vec<Span> = Vec::new();
+            let mut scratch = String::new();
+            // Path of the current child, only tracked when there are comments
+            let mut path = String::new();
+            // Layout of the root once it's written out child by child
+            let mut root_lines = None;
+
+            stack.extend(self.open_node(value, depth, spans.len(), path.len(), buffer)?);
+
+            loop {
+                let is_root = stack.len() == 1;
+                let Some(frame) = stack.last_mut() else {
+                    break;
+                };
+
+                if is_root && spans.len() > frame.first_span {
+                    self.flush_root(
+                        frame,
+                        &mut spans,
+                        &mut root_lines,
+                        buffer,
+                        &mut scratch,
+                        output,
+                    )?;
+                }
+
+                let Some((key, child)) = frame.children.next() else {
+                    let width = match root_lines.as_ref().filter(|_| is_root) {
+                        Some(lines) => {
+                            self.close_lines(frame, lines, buffer);
+
+                            None
+                        }
+                        None => {
+                            self.close_node(frame, &spans[frame.first_span..], buffer, &mut scratch)
+                        }
+                    };
+
+                    spans.truncate(frame.first_span);
+                    stack.pop();
+
+                    if let Some(span) = spans.last_mut() {
+                        span.finish(self, buffer, width);
+                    }
+
+                    continue;
+                };
+
+                let index = frame.index;
+
+                frame.index += 1;
+
+                if spans.len() > frame.first_span {
+                    buffer.push_str(", ");
+                }
+
+                let start = buffer.len();
+                let mut key_end = start;
+                let mut key_width = 0;
+
+                if let Some((key, style, separator)) = key {
+                    write_key(
+                        buffer,
+                        key,
+                        style,
+                        self.options.prefer_single_quotes,
+                        self.options.ascii_only,
+                    )?;
+                    key_end = buffer.len();
+                    key_width = self.width(&buffer[start..]);
+                    buffer.push_str(separator);
+                }
+
+                let comments = if self.comments.is_empty() {
+                    None
+                } else {
+                    path.truncate(frame.path_len);
+                    path.push('/');
+
+                    match key {
+                        Some((key, ..)) => push_segment(&mut path, key),
+                        None => {
+                            let _ = path.emit_fmt(format_args!("{}", index));
+                        }
+                    }
+
+                    self.comments.get(path.as_str())
+                };
+
+                let depth = frame.depth + 1;
+                let value_start = buffer.len();
+
+                spans.push(Span {
+                    start,
+                    key_end,
+                    value_start,
+                    end: value_start,
+                    key_width,
+                    width: None,
+                    comments,
+                });
+
+                match self.open_node(child, depth, spans.len(), path.len(), buffer)? {
+                    Some(frame) => stack.push(frame),
+                    None => {
+                        if let Some(span) = spans.last_mut() {
+                            let width = self.line_width(&buffer[value_start..]);
+
+                            span.finish(self, buffer, width);
+                        }
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Lays out the finished children of the root and writes them to
+        /// `output` once the root is known not to fit a single line
+        fn flush_root<'a, O: Output>(
+            &self,
+            frame: &Frame<'a>,
+            spans: &mut Vec<Span<'a>>,
+            root_lines: &mut Option<Lines>,
+            buffer: &mut String,
+            scratch: &mut String,
+            output: &mut O,
+        ) -> Result<()> {
+            let children = &spans[frame.first_span..];
+
+            // Once streaming, the buffer only holds the children since the
+            // last flush
+            let base = match root_lines {
+                Some(_) => 0,
+                None if self.is_streamable(frame)
+                    && self.single_line_width(frame, children).is_none() =>
+                {
+                    frame.start
+                }
+                None => return Ok(()),
+            };
+
+            scratch.clear();
+            scratch.push_str(&buffer[base..]);
+            buffer.truncate(base);
+
+            let lines = root_lines.get_or_insert_with(|| {
+                let count = frame.index + frame.children.remaining();
+
+                self.open_lines(frame, children, count, buffer)
+            });
+
+            for span in children {
+                self.push_line(frame, lines, span, scratch, base, buffer);
+            }
+
+            spans.truncate(frame.first_span);
+            output.emit(buffer)?;
+            buffer.clear();
+
+            Ok(())
+        }
+    }
+
+    /// A container whose children are still being written
+    struct Frame<'a> {
+        container: Container<'a>,
+        children: Children<'a>,
+        /// Number of children taken from `children` so far
+        index: usize,
+        /// Offset of the container in the output
+        start: usize,
+        depth: usize,
+        /// Index of the container's first child in the spans
+        first_span: usize,
+        /// Length of the container's path
+        path_len: usize,
+    }
+
+    enum Container<'a> {
+        Array,
+        /// Array of only numbers, see `numbers_per_line`
+        Numbers,
+        Tuple,
+        Object,
+        Struct(&'a str),
+    }
+
+    impl Container<'_> {
+        /// Opening character, after the name for structs
+        fn open(&self) -> char {
+            match self {
+                Container::Array | Container::Numbers => '[',
+                Container::Tuple | Container::Struct(_) => '(',
+                Container::Object => '{',
+            }
+        }
+
+        fn close(&self) -> char {
+            match self {
+                Container::Array | Container::Numbers => ']',
+                Container::Tuple | Container::Struct(_) => ')',
+                Container::Object => '}',
+            }
+        }
+    }
+
+    /// Multi-line layout of a container, written one child at a time
+    struct Lines {
+        layout: Layout,
+        /// Number of children, the last one only gets a trailing comma
+        count: usize,
+        /// Index of the next child
+        index: usize,
+        /// Width of the line being written
+        line_len: usize,
+        /// Width field names or numbers are padded to when aligned
+        align: usize,
+    }
+
+    enum Layout {
+        /// As many children on each line as fit
+        Items,
+        /// `numbers_per_line` numbers on each line
+        Numbers,
+        /// Every field on a line of its own
+        Struct,
+    }
+
+    /// Where a child was written in the output, including its key
+    struct Span<'c> {
+        start: usize,
+        key_end: usize,
+        value_start: usize,
+        end: usize,
+        key_width: usize,
+        /// Width of the whole child, `None` if it spans multiple lines
+        width: Option<usize>,
+        comments: Option<&'c Annotations>,
+    }
+
+    impl Span<'_> {
+        /// Records the end of the child once its value is written, `width`
+        /// being the width of the value alone
+        fn finish(&mut self, encoder: &Encoder, output: &str, width: Option<usize>) {
+            let prefix = &output[self.start..self.value_start];
+
+            self.end = output.len();
+            self.width = width.map(|width| encoder.width(prefix) + width);
+        }
+    }
+
+    /// Appends `segment` escaped like in `Value::pointer` paths
+    fn push_segment(path: &mut String, segment: &str) {
+        for c in segment.chars() {
+            match c {
+                '~' => path.push_str("~0"),
+                '/' => path.push_str("~1"),
+                c => path.push(c),
+            }
+        }
+    }
+
+    /// Removes the trailing whitespace of the line being written, e.g. the
+    /// space after its last comma
+    fn trim_line_end(output: &mut String) {
+        output.truncate(output.trim_end().len());
+    }
+}
+
+/// Streaming encoding (minified), built one element at a time
+#[cfg(feature = "std")]
+mod stream {
+    use crate::encode::{
+        mini, struct_name, write_key, EncodeOptions, IoOutput, KeyStyle, NonFiniteMode, Output,
+    };
+    use crate::DEFAULT_MAX_DEPTH;
+    use celkit_core::internal::sys::*;
+    use celkit_core::internal::{Error, Result};
+    use celkit_core::Serialize;
+    use std::io::Write;
+
+    /// Writes a single top-level value into `writer` without building it in
+    /// memory first. Containers are opened as guards that must be `end`ed, and
+    /// `finish` flushes the output once the value is complete.
+    pub struct StreamEncoder<W: Write> {
+        output: IoOutput<W>,
+        open: usize,
+        started: bool,
+        non_finite: NonFiniteMode,
+        exponent_floats: bool,
+        struct_names: bool,
+        ascii_only: bool,
+        prefer_single_quotes: bool,
+        key_style: KeyStyle,
+        field_style: KeyStyle,
+    }
+
+    impl<W: Write> StreamEncoder<W> {
+        pub fn mini(writer: W) -> Self {
+            Self {
+                output: IoOutput::new(writer),
+                open: 0,
+                started: false,
+                non_finite: NonFiniteMode::default(),
+                exponent_floats: false,
+                struct_names: false,
+                ascii_only: false,
+                prefer_single_quotes: false,
+                key_style: KeyStyle::default(),
+                field_style: KeyStyle::PreferBare,
+            }
+        }
+
+        pub fn non_finite(mut self, mode: NonFiniteMode) -> Self {
+            self.non_finite = mode;
+
+            self
+        }
+
+        /// Writes floats in exponent form (`1e300`) when that's shorter
+        pub fn exponent_floats(mut self, enabled: bool) -> Self {
+            self.exponent_floats = enabled;
+
+            self
+        }
+
+        /// Emits the struct name, as in `@Config(...)`, instead of `@(...)`
+        pub fn struct_names(mut self, enabled: bool) -> Self {
+            self.struct_names = enabled;
+
+            self
+        }
+
+        /// Escapes every non-ASCII character in text as `\uXXXX`
+        pub fn ascii_only(mut self, enabled: bool) -> Self {
+            self.ascii_only = enabled;
+
+            self
+        }
+
+        /// Quotes text and keys with `'` instead of `"` unless that takes
+        /// more escapes, so text full of double quotes stays readable
+        pub fn prefer_single_quotes(mut self, enabled: bool) -> Self {
+            self.prefer_single_quotes = enabled;
+
+            self
+        }
+
+        /// How object keys are quoted
+        pub fn key_style(mut self, style: KeyStyle) -> Self {
+            self.key_style = style;
+
+            self
+        }
+
+        /// How struct field names are quoted
+        pub fn field_style(mut self, style: KeyStyle) -> Self {
+            self.field_style = style;
+
+            self
+        }
+
+        /// Writes `value` as the whole document
+        pub fn value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+            self.start()?;
+            self.write_value(value)
+        }
+
+        pub fn array(&mut self) -> Result<ArrayEncoder<'_, W>> {
+            self.start()?;
+
+            ArrayEncoder::open(self)
+        }
+
+        pub fn object(&mut self) -> Result<ObjectEncoder<'_, W>> {
+            self.start()?;
+
+            ObjectEncoder::open(self)
+        }
+
+        /// Opens a struct, `name` is only written when `struct_names` is enabled
+        pub fn structure(&mut self, name: &str) -> Result<StructEncoder<'_, W>> {
+            self.start()?;
+
+            StructEncoder::open(self, name)
+        }
+
+        /// Flushes the output, failing if the document is empty or a
+        /// container was dropped without being ended
+        pub fn finish(mut self) -> Result<()> {
+            if !self.started {
+                return Err(Error::new("Cannot finish a stream that has no value"));
+            }
+
+            if self.open > 0 {
+                return Err(Error::new(format!(
+                    "Cannot finish a stream with {} unclosed container(s)",
+                    self.open
+                )));
+            }
+
+            self.output.flush()
+        }
+
+        fn start(&mut self) -> Result<()> {
+            if self.started {
+                return Err(Error::new(
+                    "Cannot write a second top-level value, a stream holds a single value",
+                ));
+            }
+
+            self.started = true;
+
+            Ok(())
+        }
+
+        fn write_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+            let value = value.serialize()?;
+            let encoder = mini::Encoder::with_options(EncodeOptions::default())
+                .non_finite(self.non_finite)
+                .max_depth(DEFAULT_MAX_DEPTH - self.open)
+                .exponent_floats(self.exponent_floats)
+                .struct_names(self.struct_names)
+                .ascii_only(self.ascii_only)
+                .prefer_single_quotes(self.prefer_single_quotes)
+                .key_style(self.key_style)
+                .field_style(self.field_style);
+
+            encoder.write_into(&value, &mut self.output)
+        }
+    }
+
+    /// State shared by the container guards: the nesting level they were
+    /// opened at and whether anything has been written to them yet
+    struct Container<'a, W: Write> {
+        stream: &'a mut StreamEncoder<W>,
+        level: usize,
+        empty: bool,
+    }
+
+    impl<'a, W: Write> Container<'a, W> {
+        fn open(stream: &'a mut StreamEncoder<W>, open: &str) -> Result<Self> {
+            if stream.open >= DEFAULT_MAX_DEPTH {
+                return Err(Error::new(format!(
+                    "Cannot open a container nested deeper than {} levels",
+                    DEFAULT_MAX_DEPTH
+                )));
+            }
+
+            stream.output.emit(open)?;
+            stream.open += 1;
+
+            let level = stream.open;
+
+            Ok(Self {
+                stream,
+                level,
+                empty: true,
+            })
+        }
+
+        /// Writes the separator before the next item
+        fn next(&mut self) -> Result<()> {
+            self.check()?;
+
+            if !self.empty {
+                self.stream.output.emit(",")?;
+            }
+
+            self.empty = false;
+
+            Ok(())
+        }
+
+        fn close(self, close: &str) -> Result<()> {
+            self.check()?;
+            self.stream.output.emit(close)?;
+            self.stream.open -= 1;
+
+            Ok(())
+        }
+
+        // The borrow keeps a parent from being used while a nested guard is
+        // alive, so a mismatch means a nested guard was dropped unfinished
+        fn check(&self) -> Result<()> {
+            if self.stream.open != self.level {
+                return Err(Error::new(
+                    "Cannot continue a container while a nested one is unfinished, call `end` on it",
+                ));
+            }
+
+            Ok(())
+        }
+    }
+
+    #[must_use = "the array must be closed with `end`"]
+    pub struct ArrayEncoder<'a, W: Write> {
+        container: Container<'a, W>,
+    }
+
+    impl<'a, W: Write> ArrayEncoder<'a, W> {
+        fn open(stream: &'a mut StreamEncoder<W>) -> Result<Self> {
+            Ok(Self {
+                container: Container::open(stream, "[")?,
+            })
+        }
+
+        pub fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+            self.container.next()?;
+            self.container.stream.write_value(value)
+        }
+
+        pub fn array(&mut self) -> Result<ArrayEncoder<'_, W>> {
+            self.container.next()?;
+
+            ArrayEncoder::open(self.container.stream)
+        }
+
+        pub fn object(&mut self) -> Result<ObjectEncoder<'_, W>> {
+            self.container.next()?;
+
+            ObjectEncoder::open(self.container.stream)
+        }
+
+        pub fn structure(&mut self, name: &str) -> Result<StructEncoder<'_, W>> {
+            self.container.next()?;
+
+            StructEncoder::open(self.container.stream, name)
+        }
+
+        pub fn end(self) -> Result<()> {
+            self.container.close("]")
+        }
+    }
+
+    #[must_use = "the object must be closed with `end`"]
+    pub struct ObjectEncoder<'a, W: Write> {
+        container: Container<'a, W>,
+    }
+
+    impl<'a, W: Write> ObjectEncoder<'a, W> {
+        fn open(stream: &'a mut StreamEncoder<W>) -> Result<Self> {
+            Ok(Self {
+                container: Container::open(stream, "{")?,
+            })
+        }
+
+        pub fn entry<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<()> {
+            self.key(key)?;
+            self.container.stream.write_value(value)
+        }
+
+        pub fn array(&mut self, key: &str) -> Result<ArrayEncoder<'_, W>> {
+            self.key(key)?;
+
+            ArrayEncoder::open(self.container.stream)
+        }
+
+        pub fn object(&mut self, key: &str) -> Result<ObjectEncoder<'_, W>> {
+            self.key(key)?;
+
+            ObjectEncoder::open(self.container.stream)
+        }
+
+        pub fn structure(&mut self, key: &str, name: &str) -> Result<StructEncoder<'_, W>> {
+            self.key(key)?;
+
+            StructEncoder::open(self.container.stream, name)
+        }
+
+        pub fn end(self) -> Result<()> {
+            self.container.close("}")
+        }
+
+        fn key(&mut self, key: &str) -> Result<()> {
+            self.container.next()?;
+
+            let stream = &mut *self.container.stream;
+
+            write_key(
+                &mut stream.output,
+                key,
+                stream.key_style,
+                stream.prefer_single_quotes,
+                stream.ascii_only,
+            )?;
+            stream.output.emit(":")
+        }
+    }
+
+    #[must_use = "the struct must be closed with `end`"]
+    pub struct StructEncoder<'a, W: Write> {
+        container: Container<'a, W>,
+    }
+
+    impl<'a, W: Write> StructEncoder<'a, W> {
+        fn open(stream: &'a mut StreamEncoder<W>, name: &str) -> Result<Self> {
+            let open = format!("@{}(", struct_name(name, stream.struct_names)?);
+
+            Ok(Self {
+                container: Container::open(stream, &open)?,
+            })
+        }
+
+        pub fn field<T: ?Sized + Serialize>(&mut self, name: &str, value: &T) -> Result<()> {
+            self.name(name)?;
+            self.container.stream.write_value(value)
+        }
+
+        pub fn array(&mut self, name: &str) -> Result<ArrayEncoder<'_, W>> {
+            self.name(name)?;
+
+            ArrayEncoder::open(self.container.stream)
+        }
+
+        pub fn object(&mut self, name: &str) -> Result<ObjectEncoder<'_, W>> {
+            self.name(name)?;
+
+            ObjectEncoder::open(self.container.stream)
+        }
+
+        pub fn structure(&mut self, name: &str, struct_name: &str) -> Result<StructEncoder<'_, W>> {
+            self.name(name)?;
+
+            StructEncoder::open(self.container.stream, struct_name)
+        }
+
+        pub fn end(self) -> Result<()> {
+            self.container.close(")")
+        }
+
+        fn name(&mut self, name: &str) -> Result<()> {
+            self.container.next()?;
+
+            let stream = &mut *self.container.stream;
+
+            write_key(
+                &mut stream.output,
+                name,
+                stream.field_style,
+                stream.prefer_single_quotes,
+                stream.ascii_only,
+            )?;
+            stream.output.emit("=")
         }
     }
 }
 
+pub use mini::Encoder as MiniEncoder;
+pub use pretty::Encoder as PrettyEncoder;
+
+#[cfg(feature = "std")]
+pub use stream::{ArrayEncoder, ObjectEncoder, StreamEncoder, StructEncoder};
+
 pub fn to_string<T: ?Sized + celkit_core::Serialize>(
     value: &T,
 ) -> celkit_core::internal::Result<String> {
@@ -531,7 +2602,17 @@ pub fn to_string<T: ?Sized + celkit_core::Serialize>(
     pretty::Encoder::new(serialized).encode()
 }
 
-pub fn to_mini<T: ?Sized + celkit_core::Serialize>(
+/// Minified counterpart of `to_string`
+pub fn to_mini_string<T: ?Sized + celkit_core::Serialize>(
+    value: &T,
+) -> celkit_core::internal::Result<String> {
+    let serialized = value.serialize()?;
+
+    mini::Encoder::new(serialized).encode()
+}
+
+/// Minified encoder for `value`, to configure before calling `encode`
+pub fn mini_encoder<T: ?Sized + celkit_core::Serialize>(
     value: &T,
 ) -> celkit_core::internal::Result<mini::Encoder> {
     let serialized = value.serialize()?;
@@ -539,10 +2620,1279 @@ pub fn to_mini<T: ?Sized + celkit_core::Serialize>(
     Ok(mini::Encoder::new(serialized))
 }
 
-pub fn to_pretty<T: ?Sized + celkit_core::Serialize>(
+/// Pretty encoder for `value`, to configure before calling `encode`
+pub fn pretty_encoder<T: ?Sized + celkit_core::Serialize>(
     value: &T,
 ) -> celkit_core::internal::Result<pretty::Encoder> {
     let serialized = value.serialize()?;
 
     Ok(pretty::Encoder::new(serialized))
 }
+
+#[deprecated(note = "renamed to `mini_encoder`, use `to_mini_string` for the encoded text")]
+pub fn to_mini<T: ?Sized + celkit_core::Serialize>(
+    value: &T,
+) -> celkit_core::internal::Result<mini::Encoder> {
+    mini_encoder(value)
+}
+
+#[deprecated(note = "renamed to `pretty_encoder`, use `to_string` for the encoded text")]
+pub fn to_pretty<T: ?Sized + celkit_core::Serialize>(
+    value: &T,
+) -> celkit_core::internal::Result<pretty::Encoder> {
+    pretty_encoder(value)
+}
+
+/// Deterministic encoding meant for hashing and signing. Values that compare
+/// equal produce identical bytes, following these rules:
+///
+/// - Minified form, no whitespace and no trailing commas
+/// - Object entries and struct fields ordered by the bytes of their keys.
+///   Object keys are always quoted, struct field names are left bare when
+///   they're identifiers (keywords included) and quoted otherwise. Struct
+///   names are omitted since equality ignores them.
+/// - Integers and integral floats written as plain integers (`1.0` and `-0.0`
+///   become `1` and `0`), other floats as the shortest decimal that reads back
+///   to the same `f64`, `f32` values are widened to `f64` first
+/// - `Big` numbers equal to an integer or a float are written as that number,
+///   others without trailing fractional zeros, positionally up to 309 integer
+///   digits and 20 zeros after the decimal point, and as `d.ddde±n` beyond
+/// - Text escapes only `"`, `\` and control characters, using `\b \f \n \r \t`
+///   where they apply and lowercase `\u00xx` otherwise, with no Unicode
+///   normalization
+/// - Non-finite floats and values nested deeper than `DEFAULT_MAX_DEPTH` are
+///   rejected
+///
+/// These rules are fixed, changing any of them is a breaking change.
+pub fn to_canonical<T: ?Sized + celkit_core::Serialize>(
+    value: &T,
+) -> celkit_core::internal::Result<String> {
+    let mut value = value.serialize()?;
+
+    expand_raw(&mut value)?;
+
+    // Checked up front so the normalization below can recurse safely
+    validate(&value, NonFiniteMode::Error, DEFAULT_MAX_DEPTH)?;
+    canonicalize(&mut value);
+
+    mini::Encoder::new(value).encode()
+}
+
+/// Replaces the `RawValue`s in `value` with the values their text holds, so
+/// their layout doesn't make it into canonical output
+fn expand_raw(value: &mut Value) -> Result<()> {
+    let mut stack = Vec::from([value]);
+
+    while let Some(value) = stack.pop() {
+        if let Some(text) = raw_text(value) {
+            *value = parse(text)?;
+
+            continue;
+        }
+
+        match value {
+            Value::Array(items) | Value::Tuple(items) => stack.extend(items.iter_mut()),
+            Value::Object(map) | Value::Struct(_, map) => stack.extend(map.values_mut()),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Rewrites numbers into the single form `to_canonical` writes for all the
+/// numbers equal to them
+fn canonicalize(value: &mut Value) {
+    match value {
+        Value::Number(number) => *number = number.canonical(),
+        Value::Array(items) | Value::Tuple(items) => items.iter_mut().for_each(canonicalize),
+        Value::Object(map) | Value::Struct(_, map) => map.values_mut().for_each(canonicalize),
+        _ => {}
+    }
+}
+
+pub fn to_string_with<T: ?Sized + celkit_core::Serialize>(
+    value: &T,
+    options: &EncodeOptions,
+) -> celkit_core::internal::Result<String> {
+    let serialized = value.serialize()?;
+
+    match options.style {
+        EncodeStyle::Mini => mini::Encoder::new(serialized)
+            .options(options.clone())
+            .encode(),
+        EncodeStyle::Pretty => pretty::Encoder::new(serialized)
+            .options(options.clone())
+            .encode(),
+    }
+}
+
+/// Rough length of `value` encoded in `style` with the default options, the
+/// encoders size their output with it. Escapes in text are counted, pretty
+/// layout is assumed to put entries and nested containers on lines of their
+/// own.
+pub fn estimated_size(value: &Value, style: EncodeStyle) -> usize {
+    estimate_size(value, style, usize::MAX)
+}
+
+/// Largest output the encoders allocate up front from the estimate, which
+/// assumes the default options. Pretty output of deep values is quadratic
+/// with the default indentation, but not with others.
+const MAX_PREALLOCATION: usize = 16 * 1024 * 1024;
+
+/// Same as `estimated_size`, but stops counting once `limit` is reached
+fn estimate_size(value: &Value, style: EncodeStyle, limit: usize) -> usize {
+    let options = EncodeOptions::default();
+    let pretty = style == EncodeStyle::Pretty;
+    let separator = if pretty { ", " } else { "," };
+
+    // Every byte is counted once in `size`, the frames keep the single-line
+    // length of their containers to tell whether they fit on one line
+    let mut size = 0;
+    let mut stack: Vec<SizeFrame> = Vec::new();
+    let mut next = Some(value);
+
+    loop {
+        if let Some(value) = next.take() {
+            match scalar_len(value) {
+                Some(length) => {
+                    size += length;
+
+                    if let Some(frame) = stack.last_mut() {
+                        frame.length += length;
+                    }
+                }
+                None => {
+                    let frame = SizeFrame::open(value, pretty);
+
+                    size += frame.length;
+                    stack.push(frame);
+                }
+            }
+        }
+
+        if size >= limit {
+            return limit;
+        }
+
+        let Some(frame) = stack.last_mut() else {
+            return size;
+        };
+
+        if let Some((key, child)) = frame.children.next() {
+            let mut length = 0;
+
+            if frame.count > 0 {
+                length += separator.len();
+            }
+
+            if let Some((key, style, separator)) = key {
+                length += separator.len();
+                length += if style.is_bare(key) {
+                    key.len()
+                } else {
+                    quoted_len(key)
+                };
+            }
+
+            frame.count += 1;
+            frame.length += length;
+            size += length;
+            next = Some(child);
+
+            continue;
+        }
+
+        let Some(frame) = stack.pop() else {
+            return size;
+        };
+
+        let depth = stack.len();
+        let indent = options.indent.len();
+        let comma_allowance = if depth > 0 { 1 } else { 0 };
+        let length = frame.length + 1; // Closing character
+
+        size += 1;
+
+        // Same rule as the pretty encoder, containers that don't fit get a
+        // line per entry or nested container, or are packed otherwise
+        let multiline = pretty
+            && frame.count > 0
+            && (frame.multiline
+                || depth * indent + length + comma_allowance > options.max_line_length);
+
+        if multiline {
+            let width = options
+                .max_line_length
+                .saturating_sub((depth + 1) * indent)
+                .max(1);
+            let lines = if frame.entries || frame.multiline {
+                frame.count
+            } else {
+                length.div_ceil(width)
+            };
+
+            size += lines * (1 + (depth + 1) * indent) + 1 + depth * indent;
+        }
+
+        if let Some(parent) = stack.last_mut() {
+            parent.length += length;
+            parent.multiline |= multiline;
+        }
+    }
+}
+
+/// A container whose children are being counted by `estimate_size`
+struct SizeFrame<'a> {
+    children: Children<'a>,
+    /// Length of the container on a single line so far
+    length: usize,
+    count: usize,
+    /// Whether a child spans multiple lines
+    multiline: bool,
+    /// Whether the children are object entries or struct fields
+    entries: bool,
+}
+
+impl<'a> SizeFrame<'a> {
+    fn open(value: &'a Value, pretty: bool) -> Self {
+        let (length, children) = match value {
+            Value::Object(map) => (
+                1, // "{"
+                Children::Entries {
+                    entries: ordered_entries(map, &[], false),
+                    style: KeyStyle::AlwaysQuote,
+                    separator: if pretty { ": " } else { ":" },
+                },
+            ),
+            Value::Struct(_, map) => (
+                2, // "@(", struct names are left out by default
+                Children::Entries {
+                    entries: ordered_entries(map, &[], false),
+                    style: KeyStyle::PreferBare,
+                    separator: if pretty { " = " } else { "=" },
+                },
+            ),
+            Value::Array(items) | Value::Tuple(items) => (1, Children::Items(items.iter())),
+            _ => (0, Children::Items([].iter())),
+        };
+
+        Self {
+            entries: matches!(children, Children::Entries { .. }),
+            children,
+            length,
+            count: 0,
+            multiline: false,
+        }
+    }
+}
+
+/// Encoded length of a scalar, `None` for containers
+fn scalar_len(value: &Value) -> Option<usize> {
+    let length = match value {
+        Value::Null => 4,
+        Value::Boolean(true) => 4,
+        Value::Boolean(false) => 5,
+        Value::Number(number) => {
+            let mut counter = Counter(0);
+
+            let _ = fmt::Write::write_fmt(&mut counter, format_args!("{}", number));
+
+            counter.0
+        }
+        Value::Text(text) => quoted_len(text),
+        _ => return None,
+    };
+
+    Some(length)
+}
+
+/// Length of `text` once quoted and escaped, without `ascii_only`
+fn quoted_len(text: &str) -> usize {
+    let mut length = text.len() + 2; // Quotation marks
+    let mut next = 0;
+
+    while let Some(i) = find_escape(text.as_bytes(), next, b'"', false) {
+        let Some(c) = text[i..].chars().next() else {
+            break;
+        };
+
+        next = i + c.len_utf8();
+        length += match c {
+            '\x08' | '\x0C' | '\n' | '\r' | '\t' | '\\' | '"' => 1,
+            c if c.is_control() => 6 - c.len_utf8(), // \uXXXX
+            _ => 0,
+        };
+    }
+
+    length
+}
+
+/// Counts the bytes written instead of storing them
+struct Counter(usize);
+
+impl fmt::Write for Counter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+
+        Ok(())
+    }
+}
+
+/// Appends the minified encoding of `value` to `output`
+pub fn to_mini_into<T: ?Sized + celkit_core::Serialize>(
+    value: &T,
+    output: &mut String,
+) -> celkit_core::internal::Result<()> {
+    mini_encoder(value)?.encode_into(output)
+}
+
+#[cfg(feature = "std")]
+pub fn to_writer<W: std::io::Write, T: ?Sized + celkit_core::Serialize>(
+    writer: W,
+    value: &T,
+) -> celkit_core::internal::Result<()> {
+    mini_encoder(value)?.encode_to_writer(writer)
+}
+
+#[cfg(feature = "std")]
+pub fn to_writer_pretty<W: std::io::Write, T: ?Sized + celkit_core::Serialize>(
+    writer: W,
+    value: &T,
+) -> celkit_core::internal::Result<()> {
+    pretty_encoder(value)?.encode_to_writer(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use celkit_core::value;
+
+    fn big(digits: &str) -> Value {
+        Value::Number(Number::Big(digits.to_string()))
+    }
+
+    celkit_core::impl_for_struct! {
+        struct Server {
+            port: u16,
+            host: String,
+            tags: Vec<String>,
+            limits: (u8, f32),
+        }
+    }
+
+    // Golden outputs, these must never change
+    #[test]
+    fn canonical_golden_corpus() {
+        let server = Server {
+            port: 8080,
+            host: "h\t\"x\"".to_string(),
+            tags: Vec::from(["b".to_string(), "a".to_string()]),
+            limits: (3, 1.5),
+        };
+
+        let corpus = [
+            (
+                to_canonical(&server).unwrap(),
+                r#"@(host="h\t\"x\"",limits=(3,1.5),port=8080,tags=["b","a"])"#,
+            ),
+            (
+                to_canonical(&value!({"b": 1, "a": 2, "é": 3, "Z": @Inner(z = null, a = true)}))
+                    .unwrap(),
+                r#"{"Z":@(a=true,z=null),"a":2,"b":1,"é":3}"#,
+            ),
+            (
+                to_canonical(&value!([
+                    1u8,
+                    -1i64,
+                    1.0,
+                    -0.0,
+                    0.5,
+                    1.5f32,
+                    1e-7,
+                    18446744073709551615u64,
+                    340282366920938463463374607431768211455u128
+                ]))
+                .unwrap(),
+                "[1,-1,1,0,0.5,1.5,0.0000001,18446744073709551615,\
+                 340282366920938463463374607431768211455]",
+            ),
+            (
+                to_canonical(&Value::Array(Vec::from([
+                    big("1.0"),
+                    big("0.10"),
+                    big("1.230"),
+                    big("1e2"),
+                    big("12345678901234567890123.000"),
+                    big("1.2345678901234567890123456789"),
+                    big("340282366920938463463374607431768211457"),
+                    big("-0.0000000000000000000000000000010000000000000000001"),
+                    big("1e400"),
+                ])))
+                .unwrap(),
+                "[1,0.1,1.23,100,12345678901234567890123,1.2345678901234567890123456789,\
+                 340282366920938463463374607431768211457,\
+                 -1.0000000000000000001e-30,1e400]",
+            ),
+            (
+                to_canonical(&Value::Struct(
+                    "Odd".to_string(),
+                    BTreeMap::from([
+                        ("x".to_string(), Value::from(1)),
+                        ("odd key".to_string(), Value::from(2)),
+                        ("true".to_string(), Value::from(3)),
+                    ]),
+                ))
+                .unwrap(),
+                r#"@("odd key"=2,true=3,x=1)"#,
+            ),
+            (
+                to_canonical(&value!(("line\nbreak \u{1} ünï", (), {"k": []}))).unwrap(),
+                r#"("line\nbreak \u0001 ünï",(),{"k":[]})"#,
+            ),
+        ];
+
+        for (output, golden) in corpus {
+            assert_eq!(output, golden);
+        }
+    }
+
+    #[test]
+    fn blank_lines_separate_top_level_children() {
+        let value = value!(@Config(
+            server = @(host = "localhost", port = 8080, names = ["first", "second", "third"]),
+            tags = ["a", "b"],
+            debug = true,
+        ));
+        let encode = |trailing_comma, final_newline| {
+            pretty_encoder(&value)
+                .unwrap()
+                .max_line_length(40)
+                .blank_lines_between_top_level(true)
+                .trailing_comma(trailing_comma)
+                .final_newline(final_newline)
+                .encode()
+                .unwrap()
+        };
+
+        // Golden outputs, nested containers get no blank lines
+        assert_eq!(
+            encode(false, false),
+            "@(\n  debug = true,\n\n  server = @(\n    host = \"localhost\",\n    \
+             names = [\"first\", \"second\", \"third\"],\n    port = 8080\n  ),\n\n  \
+             tags = [\"a\", \"b\"]\n)"
+        );
+        assert_eq!(
+            encode(true, true),
+            "@(\n  debug = true,\n\n  server = @(\n    host = \"localhost\",\n    \
+             names = [\"first\", \"second\", \"third\"],\n    port = 8080,\n  ),\n\n  \
+             tags = [\"a\", \"b\"],\n)\n"
+        );
+
+        // Blank lines use the configured line ending as well
+        let output = pretty_encoder(&value!([[1, 2], {"k": "v"}, 3]))
+            .unwrap()
+            .expand(ExpandMode::Always)
+            .blank_lines_between_top_level(true)
+            .trailing_comma(true)
+            .final_newline(true)
+            .newline(LineEnding::CrLf)
+            .encode()
+            .unwrap();
+
+        assert_eq!(
+            output,
+            "[\r\n  [\r\n    1,\r\n    2,\r\n  ],\r\n\r\n  {\r\n    \"k\": \"v\",\r\n  },\
+             \r\n\r\n  3,\r\n]\r\n"
+        );
+
+        // A root that fits on one line or has no children is left alone
+        let encode = |value: &Value| {
+            pretty_encoder(value)
+                .unwrap()
+                .blank_lines_between_top_level(true)
+                .final_newline(true)
+                .encode()
+                .unwrap()
+        };
+
+        assert_eq!(encode(&value!([1, 2, 3])), "[1, 2, 3]\n");
+        assert_eq!(encode(&value!({})), "{}\n");
+    }
+
+    #[test]
+    fn expanding_always_puts_each_item_on_its_own_line() {
+        let value = value!({"a": [], "b": @(), "c": (), "d": [1, [2]], "e": @(x = 1, y = "z")});
+        let output = pretty_encoder(&value)
+            .unwrap()
+            .expand(ExpandMode::Always)
+            .trailing_comma(true)
+            .encode()
+            .unwrap();
+
+        assert_eq!(
+            output,
+            "{\n  \"a\": [],\n  \"b\": @(),\n  \"c\": (),\n  \"d\": [\n    1,\n    [\n      2,\n    ],\n  \
+             ],\n  \"e\": @(\n    x = 1,\n    y = \"z\",\n  ),\n}"
+        );
+
+        // Even short items that would otherwise share one line
+        let output = pretty_encoder(&value!(["a", "b", "c", "d"]))
+            .unwrap()
+            .expand(ExpandMode::Always)
+            .trailing_comma(false)
+            .encode()
+            .unwrap();
+
+        assert_eq!(output, "[\n  \"a\",\n  \"b\",\n  \"c\",\n  \"d\"\n]");
+    }
+
+    #[test]
+    fn numbers_are_packed_per_line() {
+        let numbers: Vec<f64> = (0..25).map(|i| i as f64 * 1.25).collect();
+        let encode = |trailing_comma, align| {
+            pretty_encoder(&numbers)
+                .unwrap()
+                .numbers_per_line(8)
+                .align_numbers(align)
+                .trailing_comma(trailing_comma)
+                .encode()
+                .unwrap()
+        };
+
+        // Golden outputs, 25 numbers make 3 full lines and 1 more
+        assert_eq!(
+            encode(true, false),
+            "[\n  0.0, 1.25, 2.5, 3.75, 5.0, 6.25, 7.5, 8.75,\n  \
+             10.0, 11.25, 12.5, 13.75, 15.0, 16.25, 17.5, 18.75,\n  \
+             20.0, 21.25, 22.5, 23.75, 25.0, 26.25, 27.5, 28.75,\n  30.0,\n]"
+        );
+        assert_eq!(
+            encode(false, true),
+            "[\n    0.0,  1.25,   2.5,  3.75,   5.0,  6.25,   7.5,  8.75,\n   \
+             10.0, 11.25,  12.5, 13.75,  15.0, 16.25,  17.5, 18.75,\n   \
+             20.0, 21.25,  22.5, 23.75,  25.0, 26.25,  27.5, 28.75,\n   30.0\n]"
+        );
+    }
+
+    #[test]
+    fn numbers_per_line_wins_over_expanding_always() {
+        let value = value!({"big": [1, 2, 3, 4, 5], "mixed": [1, "a"], "small": [1, 2]});
+        let output = pretty_encoder(&value)
+            .unwrap()
+            .expand(ExpandMode::Always)
+            .numbers_per_line(2)
+            .trailing_comma(true)
+            .encode()
+            .unwrap();
+
+        // Short arrays are expanded too, and mixed ones get a line per item
+        assert_eq!(
+            output,
+            "{\n  \"big\": [\n    1, 2,\n    3, 4,\n    5,\n  ],\n  \
+             \"mixed\": [\n    1,\n    \"a\",\n  ],\n  \"small\": [\n    1, 2,\n  ],\n}"
+        );
+    }
+
+    #[test]
+    fn canonical_ignores_insertion_order() {
+        let mut forward = BTreeMap::new();
+        let mut backward = BTreeMap::new();
+
+        for key in ["x", "a", "m"] {
+            forward.insert(key.to_string(), Value::from(key));
+        }
+
+        for key in ["m", "a", "x"] {
+            backward.insert(key.to_string(), Value::from(key));
+        }
+
+        assert_eq!(
+            to_canonical(&Value::Object(forward)).unwrap(),
+            to_canonical(&Value::Object(backward)).unwrap()
+        );
+    }
+
+    #[test]
+    fn equal_values_encode_identically() {
+        let equal = [
+            Value::Number(Number::I64(1)),
+            Value::Number(Number::F64(1.0)),
+            Value::Number(Number::U8(1)),
+            big("1.0"),
+            big("1.00"),
+            big("10e-1"),
+        ];
+
+        for value in &equal {
+            assert_eq!(value, &equal[0]);
+            assert_eq!(to_canonical(value).unwrap(), "1");
+        }
+
+        assert_eq!(
+            to_canonical(&big("0.10")).unwrap(),
+            to_canonical(&0.1).unwrap()
+        );
+    }
+
+    #[test]
+    fn canonical_output_reads_back_to_itself() {
+        for digits in [
+            "1000000000000000000000000000000000000000000000000000000000000000",
+            "1.50000000000000000000000001e-40",
+            "-2.5e-400",
+            "99999999999999999999999999999999999999999.5",
+        ] {
+            let canonical = to_canonical(&big(digits)).unwrap();
+            let decoded = crate::parse(&canonical).unwrap();
+
+            assert_eq!(decoded, big(digits));
+            assert_eq!(to_canonical(&decoded).unwrap(), canonical);
+        }
+    }
+
+    #[test]
+    fn canonical_rejects_non_finite_floats() {
+        assert!(to_canonical(&f64::NAN).is_err());
+        assert!(to_canonical(&value!([1, f64::INFINITY])).is_err());
+    }
+
+    #[test]
+    fn key_priority_orders_keys_first() {
+        let value = value!({"a": null, "m": 1, "z": null});
+        let text = mini_encoder(&value)
+            .unwrap()
+            .key_priority(&["z", "m"])
+            .encode()
+            .unwrap();
+
+        assert_eq!(text, "{\"z\":null,\"m\":1,\"a\":null}");
+    }
+
+    #[test]
+    fn sort_keys_overrides_key_priority() {
+        let value = value!({"a": null, "m": 1, "z": null});
+        let encode = |sort_keys| {
+            mini_encoder(&value)
+                .unwrap()
+                .key_priority(&["z"])
+                .sort_keys(sort_keys)
+                .encode()
+                .unwrap()
+        };
+
+        assert_eq!(encode(true), "{\"a\":null,\"m\":1,\"z\":null}");
+        assert_eq!(encode(false), "{\"z\":null,\"a\":null,\"m\":1}");
+
+        let pretty = pretty_encoder(&value)
+            .unwrap()
+            .key_priority(&["z"])
+            .sort_keys(true)
+            .encode()
+            .unwrap();
+
+        assert_eq!(pretty, "{\"a\": null, \"m\": 1, \"z\": null}");
+    }
+
+    #[test]
+    fn clean_text_is_borrowed() {
+        for text in [
+            "",
+            "plain ascii text",
+            "key_1",
+            "caf\u{e9} \u{65e5}\u{672c} \u{1F389}",
+        ] {
+            assert!(matches!(escape_text(text), Cow::Borrowed(t) if t == text));
+        }
+    }
+
+    #[test]
+    fn dirty_text_is_escaped() {
+        let cases = [
+            ("say \"hi\"", "say \\\"hi\\\""),
+            ("it's", "it's"),
+            ("a\\b", "a\\\\b"),
+            ("tab\tline\nfeed\r", "tab\\tline\\nfeed\\r"),
+            ("\u{8}\u{c}\u{0}\u{1f}\u{7f}", "\\b\\f\\u0000\\u001f\\u007f"),
+            // 0xC2 lead bytes are checked, only U+0080 to U+009F are escaped
+            ("\u{85}\u{9f}", "\\u0085\\u009f"),
+            ("\u{a0}\u{a9}\u{bf}", "\u{a0}\u{a9}\u{bf}"),
+            ("clean \u{a0} then \n", "clean \u{a0} then \\n"),
+        ];
+
+        for (text, escaped) in cases {
+            assert_eq!(escape_text(text), escaped);
+
+            // The encoders write exactly the same between their quotes
+            let encoded = mini_encoder(&Value::Text(text.to_string()))
+                .unwrap()
+                .encode()
+                .unwrap();
+
+            assert_eq!(encoded, format!("\"{}\"", escaped));
+        }
+    }
+
+    fn block_encode(value: &Value, newline: LineEnding) -> String {
+        pretty_encoder(value)
+            .unwrap()
+            .multiline_strings(true)
+            .newline(newline)
+            .encode()
+            .unwrap()
+    }
+
+    #[test]
+    fn block_text_round_trips() {
+        let texts = [
+            "-----BEGIN CERTIFICATE-----\nMIIB\tIjAN\n-----END CERTIFICATE-----\n",
+            "SELECT *\n  FROM t\n  WHERE a = 'x' AND b = \"y\"\n",
+            "#!/bin/sh\n\n  echo \"\\n\" // not a comment\n",
+            "\n",
+            "\n\n  leading and trailing blank lines\n\n",
+            "no newline at the end\n  ~",
+            "unicode \u{e9}\u{1F600}\n\u{2028}",
+        ];
+
+        for text in texts {
+            for newline in [LineEnding::Lf, LineEnding::CrLf] {
+                let value = value!({"nested": [text], "text": text});
+                let encoded = block_encode(&value, newline);
+
+                assert!(encoded.contains("\"\"\""), "{}", encoded);
+                assert_eq!(
+                    crate::decode::parse(&encoded).unwrap(),
+                    value,
+                    "{}",
+                    encoded
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn block_text_falls_back_to_escapes() {
+        let texts = [
+            "single line",
+            "holds \"\"\" inside\n",
+            "ends with a quote\n\"",
+            "control\u{7}\ncharacter",
+            "carriage\r\nreturn",
+        ];
+
+        for text in texts {
+            let value = Value::Text(text.to_string());
+            let encoded = block_encode(&value, LineEnding::Lf);
+
+            assert!(!encoded.starts_with("\"\"\""), "{}", encoded);
+            assert_eq!(
+                crate::decode::parse(&encoded).unwrap(),
+                value,
+                "{}",
+                encoded
+            );
+        }
+    }
+
+    #[test]
+    fn non_finite_floats_round_trip_as_tokens() {
+        for number in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -0.0] {
+            for encoded in [
+                mini_encoder(&number)
+                    .unwrap()
+                    .non_finite(NonFiniteMode::Tokens)
+                    .encode()
+                    .unwrap(),
+                pretty_encoder(&number)
+                    .unwrap()
+                    .non_finite(NonFiniteMode::Tokens)
+                    .encode()
+                    .unwrap(),
+            ] {
+                let back: f32 = crate::decode::from_string(&encoded).unwrap();
+
+                assert_eq!(back.to_bits(), number.to_bits(), "{}", encoded);
+            }
+        }
+
+        for number in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, -0.0] {
+            let encoded = mini_encoder(&number)
+                .unwrap()
+                .non_finite(NonFiniteMode::Tokens)
+                .encode()
+                .unwrap();
+            let back: f64 = crate::decode::from_string(&encoded).unwrap();
+
+            assert_eq!(back.to_bits(), number.to_bits(), "{}", encoded);
+        }
+    }
+
+    #[test]
+    fn random_float_bits_round_trip() {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let encoder = MiniEncoder::new(Value::Null).non_finite(NonFiniteMode::Tokens);
+
+        let edges = [
+            0.0,
+            -0.0,
+            0.1,
+            1e22,
+            1e23,
+            5e-324,
+            f64::MIN_POSITIVE,
+            f64::MAX,
+            f64::MIN,
+            f64::EPSILON,
+        ];
+        let random = (0..20_000).map(|_| {
+            // xorshift64, every bit pattern is as likely
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+
+            f64::from_bits(state)
+        });
+
+        for number in edges.into_iter().chain(random) {
+            if number.is_nan() {
+                continue;
+            }
+
+            let encoded = encoder
+                .encode_value(&Value::Number(Number::F64(number)))
+                .unwrap();
+            let back: f64 = crate::decode::from_string(&encoded).unwrap();
+
+            assert_eq!(back.to_bits(), number.to_bits(), "{}", encoded);
+        }
+    }
+
+    /// `depth` arrays nested in one another around a `0`
+    fn nested_arrays(depth: usize) -> Value {
+        let mut value = value!(0);
+
+        for _ in 0..depth {
+            value = Value::Array(vec![value]);
+        }
+
+        value
+    }
+
+    /// Drops `value` a level at a time, as dropping it outright recurses
+    fn drop_nested(value: Value) {
+        let mut stack = vec![value];
+
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::Array(items) | Value::Tuple(items) => stack.extend(items),
+                Value::Object(map) | Value::Struct(_, map) => stack.extend(map.into_values()),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn very_deep_values_encode() {
+        let depth = 100_000;
+        let value = nested_arrays(depth);
+        let expected = format!("{}0{}", "[".repeat(depth), "]".repeat(depth));
+
+        let mini = MiniEncoder::new(Value::Null)
+            .max_depth(depth + 1)
+            .encode_value(&value)
+            .unwrap();
+
+        assert_eq!(mini, expected);
+
+        // Everything fits on one line, which is the same as the mini output
+        let pretty = PrettyEncoder::new(Value::Null)
+            .max_depth(depth + 1)
+            .max_line_length(usize::MAX)
+            .encode_value(&value)
+            .unwrap();
+
+        assert_eq!(pretty, expected);
+
+        // The default limit still applies
+        assert!(MiniEncoder::new(Value::Null).encode_value(&value).is_err());
+        assert!(PrettyEncoder::new(Value::Null)
+            .encode_value(&value)
+            .is_err());
+
+        drop_nested(value);
+    }
+
+    /// Values of every kind, with texts and keys that need escaping, quoted
+    /// field names and keywords among them
+    fn corpus(seed: u64, count: usize) -> Vec<Value> {
+        const TEXTS: [&str; 10] = [
+            "",
+            "plain",
+            "key_1",
+            "true",
+            "two words",
+            "quote \" and \\",
+            "tab\tnewline\n",
+            "\u{7}\u{85}\u{a0}",
+            "caf\u{e9} \u{1F389}",
+            "@(",
+        ];
+
+        let mut state = seed;
+        let mut next = move |limit: usize| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+
+            (state >> 33) as usize % limit
+        };
+
+        let mut values = Vec::new();
+
+        for _ in 0..count {
+            // Builds each value bottom up from a pool of smaller ones
+            let mut pool: Vec<Value> = Vec::new();
+
+            for _ in 0..1 + next(30) {
+                let value = match next(10) {
+                    0 => Value::Null,
+                    1 => Value::Boolean(next(2) == 0),
+                    2 => Value::Number(Number::I64(next(1 << 20) as i64 - (1 << 19))),
+                    3 => Value::Number(Number::F64(next(1 << 20) as f64 / 7.0)),
+                    4 => Value::Number(Number::U8(next(256) as u8)),
+                    5 => Value::Text(TEXTS[next(TEXTS.len())].to_string()),
+                    kind => {
+                        let children: Vec<Value> =
+                            (0..next(4)).filter_map(|_| pool.pop()).collect();
+                        let entries = children
+                            .iter()
+                            .cloned()
+                            .map(|child| (TEXTS[next(TEXTS.len())].to_string(), child))
+                            .collect();
+
+                        match kind {
+                            6 => Value::Array(children),
+                            7 => Value::Tuple(children),
+                            8 => Value::Object(entries),
+                            _ => Value::Struct(["", "Point", "S"][next(3)].to_string(), entries),
+                        }
+                    }
+                };
+
+                pool.insert(next(pool.len() + 1), value);
+            }
+
+            values.push(Value::Array(pool));
+        }
+
+        values
+    }
+
+    #[test]
+    fn size_estimates_match_the_mini_output() {
+        for value in corpus(3, 300) {
+            let mini = to_mini_string(&value).unwrap();
+            let stats = value.stats();
+
+            assert_eq!(stats.encoded_size, mini.len(), "{}", mini);
+            assert_eq!(
+                estimated_size(&value, EncodeStyle::Mini),
+                mini.len(),
+                "{}",
+                mini
+            );
+
+            let named = mini_encoder(&value)
+                .unwrap()
+                .struct_names(true)
+                .encode()
+                .unwrap();
+
+            assert_eq!(stats.encoded_size + stats.struct_name_bytes, named.len());
+        }
+    }
+
+    /// Keeps everything written to it, failing once `limit` bytes are taken
+    #[cfg(feature = "std")]
+    struct Sink {
+        written: Vec<u8>,
+        writes: usize,
+        limit: usize,
+    }
+
+    #[cfg(feature = "std")]
+    impl std::io::Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.written.len() + buf.len() > self.limit {
+                return Err(std::io::Error::other("disk full"));
+            }
+
+            self.written.extend_from_slice(buf);
+            self.writes += 1;
+
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn sink(limit: usize) -> Sink {
+        Sink {
+            written: Vec::new(),
+            writes: 0,
+            limit,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn writer_errors_are_wrapped() {
+        let value = value!({"a": [1, 2, 3], "b": "text"});
+        let mut writer = sink(4);
+        let error = mini_encoder(&value)
+            .unwrap()
+            .encode_to_writer(&mut writer)
+            .unwrap_err();
+
+        assert_eq!(error.message, "Failed to write encoded output: disk full");
+
+        let mut writer = sink(4);
+        let error = pretty_encoder(&value)
+            .unwrap()
+            .encode_to_writer(&mut writer)
+            .unwrap_err();
+
+        assert_eq!(error.message, "Failed to write encoded output: disk full");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn pretty_writer_streams_top_level_children() {
+        let children: Vec<Value> = (0..2000)
+            .map(|i| value!({"id": i, "name": "a name long enough to fill the line"}))
+            .collect();
+        let value = Value::Array(children);
+        let expected = pretty_encoder(&value).unwrap().encode().unwrap();
+        let mut writer = sink(usize::MAX);
+
+        pretty_encoder(&value)
+            .unwrap()
+            .encode_to_writer(&mut writer)
+            .unwrap();
+
+        assert_eq!(String::from_utf8(writer.written).unwrap(), expected);
+        assert!(writer.writes > 1, "{} writes", writer.writes);
+
+        // Everything up to the failing write is already out
+        let mut writer = sink(expected.len() / 2);
+        let error = pretty_encoder(&value)
+            .unwrap()
+            .encode_to_writer(&mut writer)
+            .unwrap_err();
+
+        assert!(error.message.ends_with("disk full"), "{}", error);
+        assert!(expected.starts_with(core::str::from_utf8(&writer.written).unwrap()));
+        assert!(!writer.written.is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn pretty_writer_matches_encode() {
+        let mut comments = BTreeMap::new();
+
+        comments.insert(
+            "/b".to_string(),
+            Annotations {
+                leading: vec!["about b".to_string()],
+                trailing: Some("after b".to_string()),
+            },
+        );
+
+        let values = [
+            value!(1),
+            value!([]),
+            value!([1, 2]),
+            value!({"a": [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12], "b": {"c": "d"}}),
+            value!([10, 200, 3000, 40000, 500000, 6000000, 70000000, 800000000]),
+            Value::Struct(
+                "Config".to_string(),
+                [
+                    ("host".to_string(), value!("localhost")),
+                    ("b".to_string(), value!([1, 2, 3])),
+                    ("long_name".to_string(), value!({"x": [true, false, null]})),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+        ];
+
+        for value in values {
+            for (align, separated, per_line) in
+                [(false, false, 0), (true, true, 3), (false, true, 2)]
+            {
+                let encoder = PrettyEncoder::new(value.clone())
+                    .max_line_length(20)
+                    .trailing_comma(true)
+                    .align_struct_fields(align)
+                    .align_numbers(align)
+                    .blank_lines_between_top_level(separated)
+                    .numbers_per_line(per_line)
+                    .comments(comments.clone())
+                    .comment(
+                        "",
+                        Annotations {
+                            leading: vec!["root".to_string()],
+                            trailing: Some("end".to_string()),
+                        },
+                    );
+                let mut writer = sink(usize::MAX);
+
+                encoder.clone().encode_to_writer(&mut writer).unwrap();
+
+                assert_eq!(
+                    String::from_utf8(writer.written).unwrap(),
+                    encoder.encode().unwrap()
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn streamed_output_matches_to_mini_string() {
+        let mut written = Vec::new();
+        let mut stream = StreamEncoder::mini(&mut written);
+        let mut root = stream.object().unwrap();
+
+        root.entry("count", &3u8).unwrap();
+
+        let mut items = root.array("items").unwrap();
+
+        items.element(&"a\"b".to_string()).unwrap();
+
+        let mut point = items.structure("Point").unwrap();
+
+        point.field("x", &1.5f64).unwrap();
+        point.field("y", &Option::<u8>::None).unwrap();
+        point.end().unwrap();
+
+        items.object().unwrap().end().unwrap();
+        items.element(&(true, "c".to_string())).unwrap();
+        items.end().unwrap();
+        root.entry("name", &"streamed".to_string()).unwrap();
+        root.end().unwrap();
+        stream.finish().unwrap();
+
+        let expected = value!({
+            "count": 3,
+            "items": ["a\"b", @Point(x = 1.5, y = null), {}, (true, "c")],
+            "name": "streamed",
+        });
+        let written = String::from_utf8(written).unwrap();
+
+        assert_eq!(written, to_mini_string(&expected).unwrap());
+        assert_eq!(crate::decode::parse(&written).unwrap(), expected);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn stream_misuse_is_reported() {
+        let mut written = Vec::new();
+        let mut stream = StreamEncoder::mini(&mut written);
+        let array = stream.array().unwrap();
+
+        drop(array);
+
+        let error = stream.finish().unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Cannot finish a stream with 1 unclosed container(s)"
+        );
+
+        let mut stream = StreamEncoder::mini(Vec::new());
+
+        stream.value(&1u8).unwrap();
+
+        let error = stream.value(&2u8).unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Cannot write a second top-level value, a stream holds a single value"
+        );
+        assert!(stream.array().is_err());
+
+        let error = StreamEncoder::mini(Vec::new()).finish().unwrap_err();
+
+        assert_eq!(error.message, "Cannot finish a stream that has no value");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn dropped_nested_guards_are_caught() {
+        let mut stream = StreamEncoder::mini(Vec::new());
+        let mut object = stream.object().unwrap();
+        let nested = object.array("a").unwrap();
+
+        drop(nested);
+
+        let message =
+            "Cannot continue a container while a nested one is unfinished, call `end` on it";
+
+        assert_eq!(object.entry("b", &1u8).unwrap_err().message, message);
+        assert_eq!(object.end().unwrap_err().message, message);
+    }
+
+    #[cfg(feature = "std")]
+    fn nest<W: std::io::Write>(array: &mut ArrayEncoder<'_, W>, levels: usize) -> Result<()> {
+        if levels == 0 {
+            return array.element(&0u8);
+        }
+
+        let mut inner = array.array()?;
+
+        nest(&mut inner, levels - 1)?;
+        inner.end()
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn stream_depth_is_limited() {
+        let mut written = Vec::new();
+        let mut stream = StreamEncoder::mini(&mut written);
+        let mut root = stream.array().unwrap();
+
+        nest(&mut root, DEFAULT_MAX_DEPTH - 1).unwrap();
+        root.end().unwrap();
+        stream.finish().unwrap();
+
+        let expected = format!(
+            "{}0{}",
+            "[".repeat(DEFAULT_MAX_DEPTH),
+            "]".repeat(DEFAULT_MAX_DEPTH)
+        );
+
+        assert_eq!(String::from_utf8(written).unwrap(), expected);
+        assert!(crate::decode::parse(&expected).is_ok());
+
+        let mut stream = StreamEncoder::mini(Vec::new());
+        let mut root = stream.array().unwrap();
+        let error = nest(&mut root, DEFAULT_MAX_DEPTH).unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Cannot open a container nested deeper than 128 levels"
+        );
+
+        // Values written inside count towards the same limit
+        let mut stream = StreamEncoder::mini(Vec::new());
+        let mut root = stream.array().unwrap();
+
+        assert!(root.element(&nested_arrays(DEFAULT_MAX_DEPTH)).is_err());
+        assert!(root.element(&nested_arrays(DEFAULT_MAX_DEPTH - 2)).is_ok());
+    }
+}
@@ -1,7 +1,42 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
 mod decode;
 mod encode;
+#[cfg(feature = "std")]
+mod lines;
+mod raw;
+mod spanned;
+
+/// Default limit on nested containers, shared by the encoders and the parser
+/// so that anything the crate writes can be read back
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+pub use decode::{
+    from_string, from_string_prefix, from_string_prefix_with_options, from_string_with_options,
+    get_path, get_path_with_options, parse, parse_all_errors, parse_all_errors_with_options,
+    parse_spanned, parse_spanned_with_options, parse_with_options, DecodeOptions, DuplicateKeys,
+    StreamParser,
+};
+pub use encode::{
+    escape_text, estimated_size, mini_encoder, pretty_encoder, to_canonical, to_mini_into,
+    to_mini_string, to_string, to_string_with, Annotations, EncodeOptions, EncodeStyle, ExpandMode,
+    KeyStyle, LineEnding, MiniEncoder, NonFiniteMode, PrettyEncoder,
+};
+pub use raw::RawValue;
+pub use spanned::{Position, Span, Spanned, SpannedEntry, SpannedValue};
+
+#[allow(deprecated)]
+pub use encode::{to_mini, to_pretty};
+
+#[cfg(feature = "std")]
+pub use decode::{from_file, from_reader, from_reader_with_options};
+
+#[cfg(feature = "std")]
+pub use encode::{
+    to_writer, to_writer_pretty, ArrayEncoder, ObjectEncoder, StreamEncoder, StructEncoder,
+};
 
-pub use decode::from_string;
-pub use encode::{to_mini, to_pretty, to_string};
+#[cfg(feature = "std")]
+pub use lines::{from_lines, to_lines};
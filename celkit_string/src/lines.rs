@@ -0,0 +1,215 @@
+use crate::encode::io_error;
+use crate::{from_string, to_mini_into};
+use celkit_core::internal::sys::*;
+use celkit_core::internal::{Error, Result};
+use celkit_core::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// Writes every item in mini form on a line of its own. Mini output never
+/// contains a raw line break, so each line holds exactly one document.
+pub fn to_lines<W, I>(items: I, writer: W) -> Result<()>
+where
+    W: Write,
+    I: IntoIterator,
+    I::Item: Serialize,
+{
+    let mut writer = std::io::BufWriter::new(writer);
+    let mut line = String::new();
+
+    for item in items {
+        line.clear();
+        to_mini_into(&item, &mut line)?;
+        line.push('\n');
+
+        writer.write_all(line.as_bytes()).map_err(io_error)?;
+    }
+
+    writer.flush().map_err(io_error)
+}
+
+/// Lazily decodes one document per line, skipping blank lines. Errors carry
+/// the line number within the whole input, and decoding goes on with the
+/// next line after one fails to parse.
+pub fn from_lines<T, R>(reader: R) -> impl Iterator<Item = Result<T>>
+where
+    T: Deserialize,
+    R: Read,
+{
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    let mut number = 0;
+    let mut offset = 0; // Byte offset of `line` in the input
+    let mut failed = false;
+
+    core::iter::from_fn(move || {
+        // A read error leaves the reader at an unknown point, so it ends the
+        // sequence
+        if failed {
+            return None;
+        }
+
+        loop {
+            offset += line.len();
+            line.clear();
+            number += 1;
+
+            match reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) if line.trim().is_empty() => continue,
+                Ok(_) => {
+                    let document = line.trim_end_matches(['\n', '\r']);
+                    let result = from_string(document);
+
+                    return Some(result.map_err(|error| at_line(error, number, offset)));
+                }
+                Err(error) => {
+                    failed = true;
+
+                    return Some(Err(Error::new(format!(
+                        "Failed to read line {}: {}",
+                        number, error
+                    ))));
+                }
+            }
+        }
+    })
+}
+
+/// Moves a parse error from the single line it was decoded from to `line`,
+/// which starts `offset` bytes into the input
+fn at_line(mut error: Error, line: usize, offset: usize) -> Error {
+    match error.line {
+        Some(_) => error.line = Some(line),
+        None => error.message = format!("{} on line {}", error.message, line),
+    }
+
+    if let Some(start) = &mut error.offset {
+        *start += offset;
+    }
+
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use celkit_core::internal::Value;
+    use celkit_core::value;
+    use std::io;
+
+    /// Hands out `chunks` one read at a time, interrupted before each one
+    struct Interrupting {
+        chunks: Vec<&'static [u8]>,
+        interrupted: bool,
+    }
+
+    impl Read for Interrupting {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.interrupted = !self.interrupted;
+
+            if self.interrupted {
+                return Err(io::Error::from(io::ErrorKind::Interrupted));
+            }
+
+            let Some(chunk) = self.chunks.first_mut() else {
+                return Ok(0);
+            };
+
+            let length = chunk.len().min(buf.len());
+
+            buf[..length].copy_from_slice(&chunk[..length]);
+            *chunk = &chunk[length..];
+
+            if chunk.is_empty() {
+                self.chunks.remove(0);
+            }
+
+            Ok(length)
+        }
+    }
+
+    #[test]
+    fn lines_round_trip() {
+        let items = [
+            value!({"text": "two\nlines", "n": 1}),
+            value!([]),
+            value!("\r\n"),
+            value!(@Point(x = 1, y = 2)),
+        ];
+        let mut written = Vec::new();
+
+        to_lines(items.clone(), &mut written).unwrap();
+
+        let text = String::from_utf8(written.clone()).unwrap();
+
+        assert_eq!(text.lines().count(), items.len());
+
+        let read: Vec<Value> = from_lines(written.as_slice())
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(read, items);
+    }
+
+    #[test]
+    fn blank_lines_are_skipped() {
+        let input = "\n1\n   \n\t\r\n2\r\n\n3";
+        let read: Vec<u8> = from_lines(input.as_bytes()).collect::<Result<_>>().unwrap();
+
+        assert_eq!(read, [1, 2, 3]);
+    }
+
+    #[test]
+    fn errors_point_into_the_whole_input() {
+        let input = "1\n\n[2,\n\"three\"\n4 5\n6\n";
+        let results: Vec<Result<u8>> = from_lines(input.as_bytes()).collect();
+
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0].as_ref().unwrap(), &1);
+        assert_eq!(results[4].as_ref().unwrap(), &6);
+
+        // A syntax error keeps its column, on the line it's on
+        let error = results[1].as_ref().unwrap_err();
+
+        assert_eq!((error.line, error.column), (Some(3), Some(4)));
+        assert_eq!(error.offset, Some(6));
+
+        let error = results[3].as_ref().unwrap_err();
+
+        assert_eq!((error.line, error.column), (Some(5), Some(3)));
+        assert_eq!(error.offset, Some(input.find(" 5").unwrap() + 1));
+
+        // So does an error from deserializing, at the value it's about
+        let error = results[2].as_ref().unwrap_err();
+
+        assert_eq!((error.line, error.column), (Some(4), Some(1)));
+        assert_eq!(error.span(), input.find("\"three\"").map(|i| i..i + 7));
+    }
+
+    #[test]
+    fn interrupted_reads_are_retried() {
+        let reader = Interrupting {
+            chunks: vec![b"1\n[2", b", 3]\n", b"\n4"],
+            interrupted: false,
+        };
+        let read: Vec<Value> = from_lines(reader).collect::<Result<_>>().unwrap();
+
+        assert_eq!(read, [value!(1), value!([2, 3]), value!(4)]);
+    }
+
+    #[test]
+    fn read_errors_end_the_lines() {
+        let mut results = from_lines::<u8, _>(&b"1\n\xFF\n2\n"[..]);
+
+        assert_eq!(results.next().unwrap().unwrap(), 1);
+
+        let error = results.next().unwrap().unwrap_err();
+
+        assert!(
+            error.message.starts_with("Failed to read line 2"),
+            "{}",
+            error
+        );
+        assert!(results.next().is_none());
+    }
+}
@@ -0,0 +1,190 @@
+use crate::decode::{from_string, parse};
+use crate::encode::{MiniEncoder, NonFiniteMode};
+use celkit_core::internal::sys::*;
+use celkit_core::internal::{Result, Value};
+use celkit_core::{Deserialize, DeserializeOptions, Serialize, Source};
+
+/// Name of the struct a `RawValue` serializes to, which the encoders write
+/// out as the text it holds. It can't be written in a document.
+const RAW_VALUE: &str = "$celkit::RawValue";
+
+/// Text of the value `value` stands for, if it's a serialized `RawValue`
+pub(crate) fn raw_text(value: &Value) -> Option<&str> {
+    match value {
+        Value::Struct(name, fields) if name == RAW_VALUE => match fields.get("text") {
+            Some(Value::Text(text)) => Some(text),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Encoded text of a single value, for passing part of a document through
+/// without mapping it to a type.
+///
+/// Decoding keeps the exact slice of the source the value was parsed from,
+/// comments inside it and layout included. Encoding splices that text in
+/// verbatim, the pretty encoder doesn't re-indent it. Built from a `Value`
+/// instead, as by `from_value`, there's no source and the text is the mini
+/// form of the value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RawValue {
+    text: String,
+}
+
+impl RawValue {
+    /// Wraps `text`, which must hold exactly one document
+    pub fn from_string(text: String) -> Result<Self> {
+        parse(&text)?;
+
+        Ok(Self { text })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+
+    /// Decodes the text into `T`
+    pub fn get<T: Deserialize>(&self) -> Result<T> {
+        from_string(&self.text)
+    }
+}
+
+impl Serialize for RawValue {
+    fn serialize(&self) -> Result<Value> {
+        let fields = BTreeMap::from([("text".to_string(), Value::Text(self.text.clone()))]);
+
+        Ok(Value::Struct(RAW_VALUE.to_string(), fields))
+    }
+}
+
+impl Deserialize for RawValue {
+    fn deserialize(value: Value) -> Result<Self> {
+        if let Some(text) = raw_text(&value) {
+            return Ok(Self {
+                text: text.to_string(),
+            });
+        }
+
+        let text = MiniEncoder::new(value)
+            .struct_names(true)
+            .non_finite(NonFiniteMode::Tokens)
+            .encode()?;
+
+        Ok(Self { text })
+    }
+
+    fn deserialize_with(value: Value, options: &DeserializeOptions) -> Result<Self> {
+        match options.source.as_ref().and_then(Source::text) {
+            Some(text) => Ok(Self {
+                text: text.to_string(),
+            }),
+            None => Self::deserialize(value),
+        }
+    }
+
+    fn captures_source() -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{from_string_with_options, DecodeOptions};
+    use crate::encode::{to_canonical, to_mini_string, to_string};
+    use celkit_core::{from_value, to_value};
+
+    celkit_core::impl_for_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Envelope {
+            kind: String,
+            payload: RawValue,
+        }
+    }
+
+    const PAYLOAD: &str = "{ \"z\": [1.50, 0x1F, nan, @Point(y = -inf, x = 'a\\'b')], /* kept */ \"a\": {\"b\": {\"c\": {\"d\": [[[{\"e\": \"\\u00e9\"}]]]}}}, }";
+
+    #[test]
+    fn deeply_nested_payload_comes_out_byte_identical() {
+        let input = format!("@Envelope(kind=\"point\",payload={})", PAYLOAD);
+        let envelope: Envelope = from_string(&input).unwrap();
+
+        assert_eq!(envelope.payload.as_str(), PAYLOAD);
+        assert_eq!(
+            to_mini_string(&envelope).unwrap(),
+            input.replace("@Envelope", "@")
+        );
+        assert_eq!(
+            envelope.payload.get::<Value>().unwrap(),
+            parse(PAYLOAD).unwrap()
+        );
+    }
+
+    #[test]
+    fn pretty_output_splices_the_text_verbatim() {
+        let input = format!("@Envelope(kind=\"point\",payload={})", PAYLOAD);
+        let envelope: Envelope = from_string(&input).unwrap();
+        let pretty = to_string(&envelope).unwrap();
+
+        assert!(pretty.contains(PAYLOAD), "{}", pretty);
+        assert_eq!(from_string::<Envelope>(&pretty).unwrap(), envelope);
+    }
+
+    #[test]
+    fn text_is_kept_inside_containers() {
+        let input = "{\"Key\": [ 1 , [2,3] ], \"other\": ( 'x' ,)}";
+        let map: BTreeMap<String, Vec<RawValue>> =
+            from_string("{\"a\": [ 1 , [2, 3] ], \"b/~\": [ {} ]}").unwrap();
+
+        assert_eq!(map["a"][1].as_str(), "[2, 3]");
+        assert_eq!(map["b/~"][0].as_str(), "{}");
+
+        let tuple: (RawValue, Option<RawValue>) = from_string("( 'x' , null )").unwrap();
+
+        assert_eq!(tuple.0.as_str(), "'x'");
+        assert_eq!(tuple.1, None);
+
+        let options = DecodeOptions::new().case_insensitive_fields(true);
+        let envelope: Envelope =
+            from_string_with_options("@(KIND = 'k', PayLoad = [ 1 ])", &options).unwrap();
+
+        assert_eq!(envelope.payload.as_str(), "[ 1 ]");
+        assert!(from_string::<BTreeMap<String, RawValue>>(input).is_ok());
+    }
+
+    #[test]
+    fn values_round_trip_without_a_source() {
+        let input = format!("@Envelope(kind=\"point\",payload={})", PAYLOAD);
+        let envelope: Envelope = from_string(&input).unwrap();
+        let value = to_value(&envelope).unwrap();
+
+        assert_eq!(from_value::<Envelope>(value).unwrap(), envelope);
+
+        let raw: RawValue = from_value(celkit_core::value!({"b": [1, true]})).unwrap();
+
+        assert_eq!(raw.as_str(), "{\"b\":[1,true]}");
+    }
+
+    #[test]
+    fn canonical_output_ignores_the_layout() {
+        let spaced: RawValue = from_string("{ \"b\" : 1.0, \"a\": [ 2 ] }").unwrap();
+        let tight: RawValue = from_string("{\"a\":[2],\"b\":1}").unwrap();
+
+        assert_eq!(to_canonical(&spaced).unwrap(), "{\"a\":[2],\"b\":1}");
+        assert_eq!(
+            to_canonical(&spaced).unwrap(),
+            to_canonical(&tight).unwrap()
+        );
+    }
+
+    #[test]
+    fn text_must_hold_one_value() {
+        assert!(RawValue::from_string("[1".into()).is_err());
+        assert!(RawValue::from_string("1 2".into()).is_err());
+    }
+}
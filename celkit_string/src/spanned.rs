@@ -0,0 +1,194 @@
+use celkit_core::internal::sys::*;
+use celkit_core::internal::{Number, PathSegment, Value};
+use celkit_core::Source;
+use core::ops::Range;
+
+/// Point in the source, `line` and `column` count from 1 and columns are in
+/// characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Part of the source a node was parsed from, `end` is just past its last
+/// character
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// Byte range of the span in the source
+    pub fn range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
+
+/// Object entry or struct field along with the span of its key
+#[derive(Debug, Clone)]
+pub struct SpannedEntry {
+    pub key: Span,
+    pub value: Spanned<SpannedValue>,
+}
+
+/// `Value` tree where every node carries its source span, see `parse_spanned`
+#[derive(Debug, Clone)]
+pub enum SpannedValue {
+    Null,
+    Boolean(bool),
+    Number(Number),
+    Text(String),
+    Array(Vec<Spanned<SpannedValue>>),
+    Tuple(Vec<Spanned<SpannedValue>>),
+    Object(BTreeMap<String, SpannedEntry>),
+    Struct(String, BTreeMap<String, SpannedEntry>),
+}
+
+impl SpannedValue {
+    /// Drops the spans, leaving the plain `Value`
+    pub fn into_value(self) -> Value {
+        match self {
+            SpannedValue::Null => Value::Null,
+            SpannedValue::Boolean(b) => Value::Boolean(b),
+            SpannedValue::Number(n) => Value::Number(n),
+            SpannedValue::Text(s) => Value::Text(s),
+            SpannedValue::Array(items) => Value::Array(into_values(items)),
+            SpannedValue::Tuple(items) => Value::Tuple(into_values(items)),
+            SpannedValue::Object(entries) => Value::Object(into_entries(entries)),
+            SpannedValue::Struct(name, fields) => Value::Struct(name, into_entries(fields)),
+        }
+    }
+}
+
+impl Spanned<SpannedValue> {
+    pub fn into_value(self) -> Value {
+        self.value.into_value()
+    }
+
+    /// `text`, which this was parsed from, along with the span of every value
+    /// in it, for types that keep their source text
+    pub(crate) fn source(&self, text: &str) -> Source {
+        let mut source = Source::new(text);
+        let mut stack = Vec::from([(Vec::new(), self)]);
+
+        while let Some((path, node)) = stack.pop() {
+            source.insert(&path, node.span.range());
+
+            let child = |segment| {
+                let mut path = path.clone();
+
+                path.push(segment);
+
+                path
+            };
+
+            match &node.value {
+                SpannedValue::Array(items) | SpannedValue::Tuple(items) => {
+                    for (index, item) in items.iter().enumerate() {
+                        stack.push((child(PathSegment::Index(index)), item));
+                    }
+                }
+                SpannedValue::Object(entries) | SpannedValue::Struct(_, entries) => {
+                    for (key, entry) in entries {
+                        stack.push((child(PathSegment::Key(key)), &entry.value));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        source
+    }
+}
+
+fn into_values(items: Vec<Spanned<SpannedValue>>) -> Vec<Value> {
+    items.into_iter().map(Spanned::into_value).collect()
+}
+
+fn into_entries(entries: BTreeMap<String, SpannedEntry>) -> BTreeMap<String, Value> {
+    entries
+        .into_iter()
+        .map(|(key, entry)| (key, entry.value.into_value()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{parse, parse_spanned};
+
+    const INPUT: &str =
+        "// header\n{\n  \"k\\u00e9y\\n\": /* c */ [1, @P(x = 'é\\'')],\n  // trailing\n}";
+
+    fn field<'a>(entries: &'a BTreeMap<String, SpannedEntry>, key: &str) -> &'a SpannedEntry {
+        entries.get(key).unwrap()
+    }
+
+    #[test]
+    fn spans_cover_nested_containers() {
+        let input = "[[1, (2,)], {\"a\": @(b = [])}]";
+        let root = parse_spanned(input).unwrap();
+        let SpannedValue::Array(items) = &root.value else {
+            panic!("Expected array, found {:?}", root.value);
+        };
+        let SpannedValue::Object(entries) = &items[1].value else {
+            panic!("Expected object, found {:?}", items[1].value);
+        };
+        let SpannedValue::Struct(_, fields) = &field(entries, "a").value.value else {
+            panic!("Expected struct");
+        };
+
+        assert_eq!(&input[root.span.range()], input);
+        assert_eq!(&input[items[0].span.range()], "[1, (2,)]");
+        assert_eq!(&input[items[1].span.range()], "{\"a\": @(b = [])}");
+        assert_eq!(&input[field(entries, "a").key.range()], "\"a\"");
+        assert_eq!(&input[field(fields, "b").key.range()], "b");
+        assert_eq!(&input[field(fields, "b").value.span.range()], "[]");
+    }
+
+    #[test]
+    fn spans_go_through_escapes_and_comments() {
+        let root = parse_spanned(INPUT).unwrap();
+        let SpannedValue::Object(entries) = &root.value else {
+            panic!("Expected object, found {:?}", root.value);
+        };
+        let entry = field(entries, "k\u{e9}y\n");
+        let SpannedValue::Array(items) = &entry.value.value else {
+            panic!("Expected array, found {:?}", entry.value.value);
+        };
+        let SpannedValue::Struct(_, fields) = &items[1].value else {
+            panic!("Expected struct, found {:?}", items[1].value);
+        };
+        let x = &field(fields, "x").value;
+
+        // The comment before the document isn't part of it
+        assert_eq!(root.span.start.line, 2);
+        assert_eq!(root.span.start.column, 1);
+        assert_eq!(&INPUT[entry.key.range()], "\"k\\u00e9y\\n\"");
+        assert_eq!(&INPUT[entry.value.span.range()], "[1, @P(x = 'é\\'')]");
+        assert_eq!(entry.value.span.start.line, 3);
+        assert_eq!(entry.value.span.start.column, 25);
+        assert_eq!(&INPUT[x.span.range()], "'é\\''");
+        assert!(matches!(&x.value, SpannedValue::Text(text) if text == "é'"));
+        // Columns count characters, the offset bytes
+        assert_eq!(x.span.end.column, 41);
+        assert_eq!(x.span.end.offset - x.span.start.offset, 6);
+    }
+
+    #[test]
+    fn stripping_spans_gives_the_value() {
+        assert_eq!(
+            parse_spanned(INPUT).unwrap().into_value(),
+            parse(INPUT).unwrap()
+        );
+    }
+}